@@ -0,0 +1,42 @@
+//! Embeds only the volume panel in a minimal ratatui app.
+//!
+//! Run with `cargo run --example volume_chart` and press any key to exit.
+
+use crossterm::event::{self, Event};
+use crypto_tracking::{
+    Candle,
+    chart::{Theme, render_volume_chart},
+};
+use std::io;
+
+fn main() -> Result<(), io::Error> {
+    let candles: Vec<Candle> = (0..30)
+        .map(|i| Candle {
+            time: 1_700_000_000 + i * 60,
+            open: 100.0,
+            high: 101.0,
+            low: 99.0,
+            close: 100.5,
+            volume: 500.0 + (i as f64 * 0.7).sin() * 300.0,
+        })
+        .collect();
+    let theme = Theme::default();
+
+    let mut terminal = ratatui::init();
+    let result = loop {
+        if let Err(e) =
+            terminal.draw(|f| render_volume_chart(f, f.area(), None, &candles, true, &theme))
+        {
+            break Err(e);
+        }
+
+        match event::read() {
+            Ok(Event::Key(_)) => break Ok(()),
+            Ok(_) => {}
+            Err(e) => break Err(e),
+        }
+    };
+    ratatui::restore();
+
+    result
+}
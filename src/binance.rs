@@ -0,0 +1,179 @@
+//! Live one-minute candles from Binance's public kline WebSocket streams.
+//!
+//! Markets are written `QUOTE/BASE` like everywhere else in the app and are
+//! matched to Binance's USDT pairs, so `USD/BTC` follows `BTCUSDT`. Binance
+//! has no IDR pairs; those markets receive no candles from this feed.
+//!
+//! Binance pushes the still-open candle every couple of seconds, so the same
+//! candle time arrives repeatedly with updated prices until it closes.
+
+use crate::{
+    net::Network,
+    source::{FeedStatus, MarketDataSource, Message},
+};
+use crypto_tracking::Candle;
+use rand::Rng;
+use serde_json::Value;
+use std::{collections::HashMap, sync::mpsc, thread, time::Duration};
+use tungstenite::Message as WsMessage;
+
+const STREAM_URL: &str = "wss://stream.binance.com:9443/stream";
+const KLINES_URL: &str = "https://api.binance.com/api/v3/klines";
+const SANDBOX_STREAM_URL: &str = "wss://stream.testnet.binance.vision/stream";
+const SANDBOX_KLINES_URL: &str = "https://testnet.binance.vision/api/v3/klines";
+
+/// Longest wait between reconnection attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Live candles from Binance. A dropped or refused connection is retried with
+/// exponential backoff, reported through [`Message::FeedStatus`].
+#[derive(Debug)]
+pub struct BinanceFeed {
+    pub network: Network,
+}
+
+impl MarketDataSource for BinanceFeed {
+    fn subscribe(&self, markets: &[String]) -> mpsc::Receiver<Message> {
+        let (tx, rx) = mpsc::channel();
+        spawn_binance_feed(markets, tx, self.network.clone());
+        rx
+    }
+
+    fn fetch_history(&self, market: &str, limit: usize) -> Result<Vec<Candle>, String> {
+        let Some(symbol) = binance_symbol(market) else {
+            return Ok(Vec::new());
+        };
+        // Binance caps a single request at 1000 candles
+        let url = format!(
+            "{}?symbol={}&interval=1m&limit={}",
+            self.network.endpoint(KLINES_URL, SANDBOX_KLINES_URL),
+            symbol,
+            limit.min(1000)
+        );
+
+        let body = self
+            .network
+            .agent()
+            .get(&url)
+            .call()
+            .and_then(|mut response| response.body_mut().read_to_string())
+            .map_err(|e| format!("fetching {} history: {}", market, e))?;
+        let rows: Vec<Value> = serde_json::from_str(&body)
+            .map_err(|e| format!("parsing {} history: {}", market, e))?;
+
+        Ok(rows.iter().filter_map(parse_kline_row).collect())
+    }
+}
+
+fn spawn_binance_feed(markets: &[String], tx: mpsc::Sender<Message>, network: Network) {
+    // Binance symbol (e.g. BTCUSDT) to the market it feeds
+    let symbols: HashMap<String, String> = markets
+        .iter()
+        .filter_map(|m| Some((binance_symbol(m)?, m.clone())))
+        .collect();
+    if symbols.is_empty() {
+        return;
+    }
+
+    thread::spawn(move || {
+        let streams: Vec<String> = symbols
+            .keys()
+            .map(|s| format!("{}@kline_1m", s.to_lowercase()))
+            .collect();
+        let url = format!(
+            "{}?streams={}",
+            network.endpoint(STREAM_URL, SANDBOX_STREAM_URL),
+            streams.join("/")
+        );
+
+        let markets: Vec<String> = symbols.values().cloned().collect();
+        let status = |status| Message::FeedStatus(markets.clone(), status);
+        let mut rng = rand::rng();
+        let mut attempt = 0;
+
+        loop {
+            if let Ok(mut socket) = network.connect(&url) {
+                attempt = 0;
+                if tx.send(status(FeedStatus::Connected)).is_err() {
+                    return;
+                }
+
+                while let Ok(message) = socket.read() {
+                    let WsMessage::Text(text) = message else {
+                        continue;
+                    };
+
+                    if let Some((symbol, candle)) = parse_kline(text.as_str())
+                        && let Some(market) = symbols.get(&symbol)
+                        && tx.send(Message::NewCandle(market.clone(), candle)).is_err()
+                    {
+                        return;
+                    }
+                }
+            }
+
+            attempt += 1;
+            let retry_in = backoff(attempt, &mut rng);
+            if tx
+                .send(status(FeedStatus::Reconnecting { attempt, retry_in }))
+                .is_err()
+            {
+                return;
+            }
+            thread::sleep(retry_in);
+        }
+    });
+}
+
+/// Wait before retry `attempt`: 1s doubling up to a minute, with up to half
+/// of it randomly taken off so clients dropped together don't all retry at
+/// once
+fn backoff(attempt: u32, rng: &mut impl Rng) -> Duration {
+    let base = Duration::from_secs(1 << attempt.saturating_sub(1).min(6)).min(MAX_BACKOFF);
+    base.mul_f64(rng.random_range(0.5..=1.0))
+}
+
+/// The Binance pair for a market, e.g. `BTCUSDT` for `USD/BTC`
+pub fn binance_symbol(market: &str) -> Option<String> {
+    let (quote, base) = market.split_once('/')?;
+    let quote = match quote {
+        "USD" => "USDT",
+        _ => return None,
+    };
+
+    Some(format!("{}{}", base, quote))
+}
+
+/// The symbol and candle from a combined-stream kline event. Binance sends
+/// prices as strings and times in milliseconds.
+fn parse_kline(text: &str) -> Option<(String, Candle)> {
+    let event: Value = serde_json::from_str(text).ok()?;
+    let kline = &event["data"]["k"];
+    let price = |field: &str| kline[field].as_str()?.parse::<f64>().ok();
+
+    let candle = Candle {
+        time: kline["t"].as_i64()? / 1000,
+        open: price("o")?,
+        high: price("h")?,
+        low: price("l")?,
+        close: price("c")?,
+        volume: price("v")?,
+    };
+
+    Some((kline["s"].as_str()?.to_string(), candle))
+}
+
+/// A candle from the REST klines endpoint, where each kline is an array of
+/// `[open time, open, high, low, close, volume, ...]`
+fn parse_kline_row(row: &Value) -> Option<Candle> {
+    let price = |i: usize| row[i].as_str()?.parse::<f64>().ok();
+
+    Some(Candle {
+        time: row[0].as_i64()? / 1000,
+        open: price(1)?,
+        high: price(2)?,
+        low: price(3)?,
+        close: price(4)?,
+        volume: price(5)?,
+    })
+}
@@ -0,0 +1,308 @@
+//! Full-screen market browser: every market in one table that can be sorted
+//! by price, change or volume and narrowed with a filter.
+//!
+//! Typing edits the filter, Left/Right pick the sort column, Tab flips the
+//! sort direction and Up/Down move the highlight. Space marks the highlighted
+//! market as the ratio-panel benchmark, Enter opens it on the main chart and
+//! Esc leaves without changing the selection.
+
+use crate::{AppState, ChangeThreshold, change_color, display_name, percent_change};
+use crossterm::event::KeyCode;
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
+};
+use std::{cmp::Ordering, collections::HashMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortColumn {
+    Market,
+    Price,
+    Change,
+    Volume,
+}
+
+impl SortColumn {
+    const ALL: [SortColumn; 4] = [
+        SortColumn::Market,
+        SortColumn::Price,
+        SortColumn::Change,
+        SortColumn::Volume,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            SortColumn::Market => "Market",
+            SortColumn::Price => "Price",
+            SortColumn::Change => "Change",
+            SortColumn::Volume => "Volume",
+        }
+    }
+
+    fn shift(self, step: isize) -> Self {
+        let i = Self::ALL.iter().position(|&c| c == self).unwrap_or(0) as isize;
+        Self::ALL[(i + step).rem_euclid(Self::ALL.len() as isize) as usize]
+    }
+}
+
+/// What the app should do after the browser handled a key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BrowserEvent {
+    /// Close the browser and show this market on the main chart
+    Select(String),
+    /// Close the browser, keeping the current selection
+    Close,
+    /// Use this market as the ratio-panel benchmark, or clear it when it
+    /// already is
+    ToggleBenchmark(String),
+}
+
+/// One market's row, computed fresh each frame from the live state
+struct MarketRow<'a> {
+    market: &'a str,
+    price: Option<f64>,
+    /// Percent change since the session opened
+    change: Option<f64>,
+    /// Total volume over the retained candles
+    volume: f64,
+}
+
+#[derive(Debug)]
+pub struct MarketBrowser {
+    filter: String,
+    sort: SortColumn,
+    descending: bool,
+    /// Tracked by symbol so the highlight follows the market while live
+    /// updates reorder the rows
+    highlighted: String,
+}
+
+impl MarketBrowser {
+    pub fn new(selected: &str) -> Self {
+        MarketBrowser {
+            filter: String::new(),
+            sort: SortColumn::Market,
+            descending: false,
+            highlighted: selected.to_string(),
+        }
+    }
+
+    pub fn handle_key(
+        &mut self,
+        code: KeyCode,
+        state: &AppState,
+        names: &HashMap<String, String>,
+    ) -> Option<BrowserEvent> {
+        match code {
+            KeyCode::Esc => return Some(BrowserEvent::Close),
+            KeyCode::Enter => {
+                return self
+                    .highlighted_row(state, names)
+                    .map(|market| BrowserEvent::Select(market.to_string()));
+            }
+            KeyCode::Char(' ') => {
+                return self
+                    .highlighted_row(state, names)
+                    .map(|market| BrowserEvent::ToggleBenchmark(market.to_string()));
+            }
+            KeyCode::Char(c) => self.filter.push(c),
+            KeyCode::Backspace => {
+                self.filter.pop();
+            }
+            KeyCode::Left => self.sort = self.sort.shift(-1),
+            KeyCode::Right => self.sort = self.sort.shift(1),
+            KeyCode::Tab => self.descending = !self.descending,
+            KeyCode::Up | KeyCode::Down => {
+                let rows = self.rows(state, names);
+                if !rows.is_empty() {
+                    let i = self.highlighted_index(&rows);
+                    let i = if code == KeyCode::Up {
+                        i.checked_sub(1).unwrap_or(rows.len() - 1)
+                    } else {
+                        (i + 1) % rows.len()
+                    };
+                    self.highlighted = rows[i].market.to_string();
+                }
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    /// Draws the browser over `area`. `benchmark` is marked in the first
+    /// column; changes are colored like the sidebar.
+    pub fn render(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        state: &AppState,
+        names: &HashMap<String, String>,
+        benchmark: Option<&str>,
+        thresholds: &[ChangeThreshold],
+    ) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .split(area);
+
+        let filter = Paragraph::new(format!("{}_", self.filter))
+            .block(Block::default().title(" Filter ").borders(Borders::ALL));
+        f.render_widget(filter, chunks[0]);
+
+        let rows = self.rows(state, names);
+        let arrow = if self.descending { "▼" } else { "▲" };
+        let header = Row::new(
+            std::iter::once(Cell::from("")).chain(SortColumn::ALL.iter().map(|&column| {
+                if column == self.sort {
+                    Cell::from(format!("{} {}", column.title(), arrow))
+                        .style(Style::default().fg(Color::Yellow))
+                } else {
+                    Cell::from(column.title())
+                }
+            })),
+        )
+        .style(Style::default().add_modifier(Modifier::BOLD));
+
+        let table_rows = rows.iter().map(|row| {
+            let change_cell = match row.change {
+                Some(pct) => Cell::from(format!("{:+.2}%", pct))
+                    .style(Style::default().fg(change_color(pct, thresholds))),
+                None => Cell::from("-"),
+            };
+
+            Row::new(vec![
+                Cell::from(if benchmark == Some(row.market) {
+                    "*"
+                } else {
+                    ""
+                }),
+                Cell::from(display_name(names, row.market).to_string()),
+                Cell::from(
+                    row.price
+                        .map(|price| {
+                            state
+                                .currency(row.market)
+                                .format_price(price, state.number_format)
+                        })
+                        .unwrap_or_else(|| "-".to_string()),
+                ),
+                change_cell,
+                Cell::from(format!("{:.2}", row.volume)),
+            ])
+        });
+
+        let table = Table::new(
+            table_rows,
+            [
+                Constraint::Length(1),
+                Constraint::Min(12),
+                Constraint::Min(16),
+                Constraint::Length(10),
+                Constraint::Min(10),
+            ],
+        )
+        .header(header)
+        .block(
+            Block::default()
+                .title(format!(
+                    " Markets ({}/{}) ",
+                    rows.len(),
+                    state.markets.len()
+                ))
+                .borders(Borders::ALL),
+        )
+        .row_highlight_style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+        let mut table_state = TableState::default();
+        if !rows.is_empty() {
+            table_state.select(Some(self.highlighted_index(&rows)));
+        }
+        f.render_stateful_widget(table, chunks[1], &mut table_state);
+
+        let help = Paragraph::new(Line::from(
+            "type to filter  ←/→ sort  tab reverse  space benchmark  enter open  esc back",
+        ))
+        .style(Style::default().fg(Color::DarkGray));
+        f.render_widget(help, chunks[2]);
+    }
+
+    /// Markets matching the filter by symbol or display name, in sort order
+    fn rows<'a>(&self, state: &'a AppState, names: &HashMap<String, String>) -> Vec<MarketRow<'a>> {
+        let filter = self.filter.to_lowercase();
+        let mut rows: Vec<MarketRow> = state
+            .markets
+            .iter()
+            .filter(|m| {
+                m.to_lowercase().contains(&filter)
+                    || display_name(names, m).to_lowercase().contains(&filter)
+            })
+            .map(|m| {
+                let price = state.latest_price_map.get(m).copied();
+                MarketRow {
+                    market: m,
+                    price,
+                    change: state
+                        .session_open
+                        .get(m)
+                        .zip(price)
+                        .and_then(|(open, price)| percent_change(*open, price)),
+                    volume: state
+                        .data
+                        .get(m)
+                        .map(|candles| candles.iter().map(|c| c.volume).sum())
+                        .unwrap_or(0.0),
+                }
+            })
+            .collect();
+
+        // Markets without a value yet sort last in either direction
+        let by_value = |a: Option<f64>, b: Option<f64>, descending: bool| match (a, b) {
+            (Some(a), Some(b)) if descending => b.total_cmp(&a),
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        rows.sort_by(|a, b| match self.sort {
+            SortColumn::Market if self.descending => b.market.cmp(a.market),
+            SortColumn::Market => a.market.cmp(b.market),
+            SortColumn::Price => by_value(a.price, b.price, self.descending),
+            SortColumn::Change => by_value(a.change, b.change, self.descending),
+            SortColumn::Volume => by_value(Some(a.volume), Some(b.volume), self.descending),
+        });
+
+        rows
+    }
+
+    /// Position of the highlighted market in `rows`, or the first row when
+    /// the filter hides it
+    fn highlighted_index(&self, rows: &[MarketRow]) -> usize {
+        rows.iter()
+            .position(|row| row.market == self.highlighted)
+            .unwrap_or(0)
+    }
+
+    fn highlighted_row<'a>(
+        &self,
+        state: &'a AppState,
+        names: &HashMap<String, String>,
+    ) -> Option<&'a str> {
+        let rows = self.rows(state, names);
+        rows.get(self.highlighted_index(&rows))
+            .map(|row| row.market)
+    }
+}
@@ -0,0 +1,125 @@
+//! On-disk cache of fetched history, so a restart only downloads the candles
+//! since the last run and still has a chart when the exchange is unreachable.
+//!
+//! Candles are kept per feed and market as one-minute CSV files under
+//! `$XDG_CACHE_HOME/crypto_tracking` (or `~/.cache/crypto_tracking`), e.g.
+//! `binance/USD-BTC-1m.csv`. Only history goes through the cache; live
+//! candles come straight from the wrapped source.
+
+use crate::source::{MarketDataSource, Message};
+use chrono::Utc;
+use crypto_tracking::Candle;
+use std::{fs, path::PathBuf, sync::mpsc};
+
+/// Every feed serves one-minute candles
+const TIMEFRAME: &str = "1m";
+const CANDLE_SECS: i64 = 60;
+
+/// Most candles kept per file
+const MAX_CACHED: usize = 5000;
+
+/// A source whose history is cached under `dir`
+pub struct Cached {
+    source: Box<dyn MarketDataSource>,
+    dir: PathBuf,
+}
+
+/// Where `feed`'s history is cached; `None` without a home directory
+pub fn cache_dir(feed: &str) -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+
+    Some(base.join("crypto_tracking").join(feed))
+}
+
+impl Cached {
+    pub fn new(dir: PathBuf, source: Box<dyn MarketDataSource>) -> Self {
+        Cached { source, dir }
+    }
+
+    fn path(&self, market: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}-{}.csv", market.replace('/', "-"), TIMEFRAME))
+    }
+
+    fn load(&self, market: &str) -> Vec<Candle> {
+        let Ok(text) = fs::read_to_string(self.path(market)) else {
+            return Vec::new();
+        };
+
+        text.lines()
+            .filter_map(|line| {
+                let mut fields = line.split(',');
+                let time = fields.next()?.parse().ok()?;
+                let mut price = || fields.next()?.parse::<f64>().ok();
+                Some(Candle {
+                    time,
+                    open: price()?,
+                    high: price()?,
+                    low: price()?,
+                    close: price()?,
+                    volume: price()?,
+                })
+            })
+            .collect()
+    }
+
+    fn save(&self, market: &str, candles: &[Candle]) -> Result<(), String> {
+        let skip = candles.len().saturating_sub(MAX_CACHED);
+        let text: String = candles[skip..]
+            .iter()
+            .map(|c| {
+                format!(
+                    "{},{},{},{},{},{}\n",
+                    c.time, c.open, c.high, c.low, c.close, c.volume
+                )
+            })
+            .collect();
+
+        fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(self.path(market), text))
+            .map_err(|e| format!("caching {} history: {}", market, e))
+    }
+}
+
+impl MarketDataSource for Cached {
+    fn subscribe(&self, markets: &[String]) -> mpsc::Receiver<Message> {
+        self.source.subscribe(markets)
+    }
+
+    /// Cached candles topped up with the ones since the newest of them. The
+    /// newest cached candle is fetched again, as it may have still been open.
+    fn fetch_history(&self, market: &str, limit: usize) -> Result<Vec<Candle>, String> {
+        let mut candles = self.load(market);
+        let missing = match candles.last() {
+            Some(last) => {
+                let behind = (Utc::now().timestamp() - last.time) / CANDLE_SECS + 1;
+                (behind.max(1) as usize).min(limit)
+            }
+            None => limit,
+        };
+
+        match self.source.fetch_history(market, missing) {
+            Ok(fresh) => {
+                if let Some(first) = fresh.first() {
+                    candles.retain(|c| c.time < first.time);
+                }
+                candles.extend(fresh);
+                if !candles.is_empty()
+                    && let Err(e) = self.save(market, &candles)
+                {
+                    eprintln!("Warning: {}", e);
+                }
+            }
+            // Offline, the cache is better than nothing
+            Err(e) if !candles.is_empty() => {
+                eprintln!("Warning: {}, using cached history", e)
+            }
+            Err(e) => return Err(e),
+        }
+
+        let skip = candles.len().saturating_sub(limit);
+        Ok(candles.split_off(skip))
+    }
+}
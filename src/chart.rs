@@ -0,0 +1,1946 @@
+//! Reusable ratatui panels for drawing candle data.
+
+use crate::{
+    Candle, OrderBook, Side, Trade,
+    format::{Currency, NumberFormat, format_clock, format_time},
+    indicators::{Cross, Macd},
+    patterns::Pattern,
+};
+use ratatui::{
+    Frame,
+    layout::{Alignment, Rect},
+    style::{Color, Style},
+    symbols,
+    text::{Line, Span},
+    widgets::{
+        Axis, Block, Borders, Chart, Clear, Dataset, GraphType, List, ListItem, Padding, Paragraph,
+        canvas::{Canvas, Line as CanvasLine, Points, Rectangle},
+    },
+};
+
+/// Which wick of a pin bar is the long one, i.e. the direction price was
+/// rejected from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinDir {
+    /// Long lower wick: sellers pushed down and were rejected
+    Bullish,
+    /// Long upper wick: buyers pushed up and were rejected
+    Bearish,
+}
+
+/// Colors and block layout shared by every panel
+#[derive(Debug)]
+pub struct Theme {
+    /// Fill behind the chart panels; `None` leaves the terminal background
+    pub chart_background: Option<Color>,
+    /// Where panel titles sit on the top border
+    pub title_alignment: Alignment,
+    /// Blank cells between each panel's border and its contents
+    pub chart_padding: u16,
+    /// Grid lines, dim so they stay behind the data
+    pub grid_color: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            chart_background: None,
+            title_alignment: Alignment::default(),
+            chart_padding: 0,
+            grid_color: Color::DarkGray,
+        }
+    }
+}
+
+impl Theme {
+    /// The bordered block every panel draws in. A title too long for `area`
+    /// is left-aligned so it's clipped only at the end rather than off both
+    /// sides.
+    fn chart_block<'a>(&self, title: impl Into<Line<'a>>, area: Rect) -> Block<'a> {
+        let title = title.into();
+        let alignment = if title.width() + 2 > area.width as usize {
+            Alignment::Left
+        } else {
+            self.title_alignment
+        };
+
+        let block = Block::default()
+            .title(title)
+            .title_alignment(alignment)
+            .borders(Borders::ALL)
+            .padding(Padding::uniform(self.chart_padding));
+        match self.chart_background {
+            Some(color) => block.style(Style::default().bg(color)),
+            None => block,
+        }
+    }
+}
+
+/// Canvas rows below which the candlestick chart leaves out its legend and
+/// the glyphs over candles, which would cover most of a panel that small
+const DETAIL_ROWS: u16 = 8;
+
+/// Full-intensity body colors used when shading by volume
+const UP_RGB: (u8, u8, u8) = (0, 230, 118);
+const DOWN_RGB: (u8, u8, u8) = (255, 82, 82);
+
+/// When candles are drawn without wicks as thin bodies, which lets more of
+/// them fit side by side
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CompactCandles {
+    /// Compact once the panel has fewer than two columns per candle, the
+    /// point where full bodies and wicks start to overlap
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// How each candle's prices are drawn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CandleStyle {
+    /// A filled body from open to close on a high-low wick
+    #[default]
+    Candles,
+    /// A high-low line with the open ticked on its left and the close on its
+    /// right
+    Bars,
+}
+
+impl CandleStyle {
+    pub fn name(self) -> &'static str {
+        match self {
+            CandleStyle::Candles => "candles",
+            CandleStyle::Bars => "bars",
+        }
+    }
+
+    /// The style called `name`, as returned by [`CandleStyle::name`]
+    pub fn from_name(name: &str) -> Option<Self> {
+        [CandleStyle::Candles, CandleStyle::Bars]
+            .into_iter()
+            .find(|style| style.name() == name)
+    }
+}
+
+/// A line drawn over the candles, such as a moving average, with its legend
+/// entry
+#[derive(Debug, Clone)]
+pub struct Overlay {
+    pub label: String,
+    pub color: Color,
+    /// `(x, price)` points in the candlestick x layout, e.g. from
+    /// [`crate::indicators::sma`]
+    pub points: Vec<(f64, f64)>,
+}
+
+/// Another market's closes drawn as a line over the candles on their price
+/// scale, e.g. from [`comparison_series`], with a legend entry for each
+/// market
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    /// Legend entry for the drawn candles, such as their change over the
+    /// window
+    pub label: String,
+    pub color: Color,
+    /// The other market's line and legend entry
+    pub line: Overlay,
+}
+
+/// An area shaded behind the candles between two lines with the same x
+/// values, such as Bollinger Bands
+#[derive(Debug, Clone)]
+pub struct Band {
+    pub color: Color,
+    pub lower: Vec<(f64, f64)>,
+    pub upper: Vec<(f64, f64)>,
+}
+
+/// A horizontal line across the candles at a price, such as a support
+/// level, labelled at its left end
+#[derive(Debug, Clone)]
+pub struct PriceLine {
+    pub price: f64,
+    pub color: Color,
+    pub label: String,
+}
+
+/// A glyph printed just above or below one candle, such as a detected
+/// pattern
+#[derive(Debug, Clone)]
+pub struct Marker {
+    /// Position of the candle in the drawn slice
+    pub index: usize,
+    pub above: bool,
+    pub symbol: &'static str,
+    pub color: Color,
+}
+
+/// A cursor on one candle, with lines through it and a readout of its
+/// values
+#[derive(Debug, Clone)]
+pub struct Crosshair {
+    /// Position of the candle in the drawn slice
+    pub index: usize,
+    /// Lines of the readout box, top to bottom
+    pub readout: Vec<String>,
+}
+
+/// Options for [`render_candlestick_chart`]
+#[derive(Debug, Clone)]
+pub struct CandlestickOptions {
+    pub title: String,
+    /// Accent for the title text; `None` keeps the default style
+    pub title_color: Option<Color>,
+    /// Color of the border, e.g. to pick out one of several panels; `None`
+    /// keeps the default style
+    pub border_color: Option<Color>,
+    /// Minimum wick-to-body ratio for a candle to be marked as a pin bar;
+    /// zero or less turns the markers off
+    pub pin_bar_ratio: f64,
+    /// Shade each body by its volume relative to the busiest visible candle
+    pub volume_shading: bool,
+    pub compact: CompactCandles,
+    pub style: CandleStyle,
+    /// Fixed y bounds (e.g. from [`snapped_price_bounds`]) instead of fitting
+    /// the visible prices
+    pub y_bounds: Option<(f64, f64)>,
+    /// Lines drawn over the candles, listed in a legend at the top left;
+    /// an empty label leaves a line out of the legend
+    pub overlays: Vec<Overlay>,
+    /// Listed in the legend ahead of the overlays; without fixed y bounds
+    /// the y range widens to fit its line
+    pub comparison: Option<Comparison>,
+    pub bands: Vec<Band>,
+    /// Glyphs over or under individual candles, a little further out than
+    /// the pin bar markers
+    pub markers: Vec<Marker>,
+    /// Drawn behind the candles; lines outside the y range are left out
+    pub price_lines: Vec<PriceLine>,
+    pub crosshair: Option<Crosshair>,
+    /// Labels the price axis along the right edge with prices in this
+    /// currency; `None` leaves it unlabelled
+    pub price_axis: Option<(Currency, NumberFormat)>,
+    /// Labels candle times along the bottom edge
+    pub time_axis: bool,
+    /// The latest price, marked by a dashed line and a tag on the price axis
+    /// in the given color
+    pub last_price: Option<(f64, Color)>,
+    /// Dotted grid lines at the price and time axis ticks
+    pub grid: bool,
+}
+
+impl Default for CandlestickOptions {
+    fn default() -> Self {
+        CandlestickOptions {
+            title: "Candlestick Chart".to_string(),
+            title_color: None,
+            border_color: None,
+            pin_bar_ratio: 2.0,
+            volume_shading: false,
+            compact: CompactCandles::default(),
+            style: CandleStyle::default(),
+            y_bounds: None,
+            overlays: Vec::new(),
+            comparison: None,
+            bands: Vec::new(),
+            markers: Vec::new(),
+            price_lines: Vec::new(),
+            crosshair: None,
+            price_axis: None,
+            time_axis: false,
+            last_price: None,
+            grid: false,
+        }
+    }
+}
+
+/// Draws `candles` oldest to newest as a bordered candlestick panel filling
+/// `area`, with candle i centred at x = i + 0.5 of `[0, candles.len()]`.
+///
+/// The border takes two rows and columns, so `area` should be at least a few
+/// rows taller and about two columns per candle wider than that for the
+/// bodies to stay distinct. Candles with non-finite prices are skipped; if
+/// none are left (including empty input) only the titled block is drawn.
+/// Gaps in the candle times (see [`find_gaps`]) are marked with a faint
+/// vertical divider rather than drawn as if contiguous. In compact mode (see
+/// [`CompactCandles`]) each candle is a one-column body line without wicks,
+/// and an OHLC bar (see [`CandleStyle`]) its high-low line without ticks.
+/// Bands and price lines are drawn behind the candles, and overlays and a
+/// comparison line on top of them. A crosshair's lines meet behind its candle at the close, and its
+/// readout sits in the top corner away from it. Price axis labels (see
+/// [`price_ticks`]) take a column on the right and time labels the bottom
+/// row, each left out when the panel is too small to spare it. Time labels
+/// go on the first candle of each span of time, so they stay with their
+/// candles as the chart scrolls. Grid lines fall on the same prices and
+/// times whether or not they're labelled. The last price's line is dashed
+/// behind the candles, and its tag covers the price label it lands on.
+///
+/// Small panels, such as one of several side by side, keep the candles and
+/// lines but drop the legend, pin bar markers and other glyphs once the
+/// canvas is under [`DETAIL_ROWS`] rows, and a crosshair readout that
+/// doesn't fit.
+///
+/// Returns the area the candles were drawn in, for a panel stacked under the
+/// chart to put its bars in the same columns (see [`render_volume_chart`]).
+pub fn render_candlestick_chart(
+    f: &mut Frame,
+    area: Rect,
+    candles: &[Candle],
+    options: &CandlestickOptions,
+    theme: &Theme,
+) -> Rect {
+    let title = match options.title_color {
+        Some(color) => Span::styled(options.title.as_str(), Style::default().fg(color)),
+        None => Span::raw(options.title.as_str()),
+    };
+    let pin_bar_ratio = options.pin_bar_ratio;
+
+    let Some((mut min_price, mut max_price)) = price_bounds(candles) else {
+        let block = theme.chart_block(title, area);
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+        return inner;
+    };
+    if let Some(comparison) = &options.comparison {
+        for &(_, price) in &comparison.line.points {
+            min_price = min_price.min(price);
+            max_price = max_price.max(price);
+        }
+    }
+
+    let y_padding = axis_padding(min_price, max_price);
+    let (y_min, y_max) = options
+        .y_bounds
+        .unwrap_or((min_price - y_padding, max_price + y_padding));
+    let gaps = find_gaps(candles);
+    let max_volume = candles
+        .iter()
+        .map(|c| c.volume)
+        .filter(|v| v.is_finite())
+        .fold(0.0, f64::max);
+    let compact = match options.compact {
+        CompactCandles::Auto => (area.width.saturating_sub(2) as usize) < candles.len() * 2,
+        CompactCandles::Always => true,
+        CompactCandles::Never => false,
+    };
+    let crosshair = options.crosshair.as_ref().and_then(|crosshair| {
+        let candle = candles
+            .get(crosshair.index)
+            .filter(|c| is_finite_candle(c))?;
+        Some((crosshair, candle))
+    });
+
+    let mut block = theme.chart_block(title, area);
+    if let Some(color) = options.border_color {
+        block = block.border_style(Style::default().fg(color));
+    }
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let tick_prices = price_ticks(y_min, y_max, (inner.height as usize / 4).max(2));
+    let ticks: Vec<(f64, String)> = match &options.price_axis {
+        Some((currency, number_format)) => tick_prices
+            .iter()
+            .map(|&tick| (tick, currency.format_price(tick, *number_format)))
+            .collect(),
+        None => Vec::new(),
+    };
+    let last_price = options
+        .last_price
+        .filter(|(price, _)| (y_min..=y_max).contains(price));
+    let last_price_tag = match (last_price, &options.price_axis) {
+        (Some((price, _)), Some((currency, number_format))) => {
+            Some(currency.format_price(price, *number_format))
+        }
+        _ => None,
+    };
+    let gutter = ticks
+        .iter()
+        .map(|(_, label)| label)
+        .chain(&last_price_tag)
+        .map(|label| label.chars().count() as u16 + 1)
+        .max()
+        .filter(|&width| inner.width > width + 10)
+        .unwrap_or(0);
+    let time_row = options.time_axis && inner.height > 6;
+    let canvas_area = Rect {
+        width: inner.width - gutter,
+        height: inner.height - time_row as u16,
+        ..inner
+    };
+    let time_marks = time_labels(candles, canvas_area.width);
+    let detailed = canvas_area.height >= DETAIL_ROWS;
+
+    let canvas = Canvas::default()
+        .background_color(theme.chart_background.unwrap_or(Color::Reset))
+        .x_bounds([0.0, candles.len() as f64])
+        .y_bounds([y_min, y_max])
+        .paint(|ctx| {
+            // Missing intervals get a faint divider on the boundary before
+            // the candle that follows them, behind the candles themselves
+            for &i in &gaps {
+                ctx.draw(&CanvasLine {
+                    x1: i as f64,
+                    y1: y_min,
+                    x2: i as f64,
+                    y2: y_max,
+                    color: Color::DarkGray,
+                });
+            }
+            if options.grid {
+                // A dot per cell: columns and rows across the canvas
+                let (column, row) = (
+                    candles.len() as f64 / canvas_area.width.max(1) as f64,
+                    (y_max - y_min) / canvas_area.height.max(1) as f64,
+                );
+                for &price in &tick_prices {
+                    let coords: Vec<(f64, f64)> = (0..canvas_area.width)
+                        .map(|i| ((i as f64 + 0.5) * column, price))
+                        .collect();
+                    ctx.draw(&Points {
+                        coords: &coords,
+                        color: theme.grid_color,
+                    });
+                }
+                for (index, _, _) in &time_marks {
+                    let coords: Vec<(f64, f64)> = (0..canvas_area.height)
+                        .map(|i| (*index as f64 + 0.5, y_min + (i as f64 + 0.5) * row))
+                        .collect();
+                    ctx.draw(&Points {
+                        coords: &coords,
+                        color: theme.grid_color,
+                    });
+                }
+            }
+            ctx.layer();
+
+            // Shaded with vertical strokes, several per candle so the fill
+            // has no holes between them
+            for band in &options.bands {
+                for (lower, upper) in band.lower.windows(2).zip(band.upper.windows(2)) {
+                    let steps = 4;
+                    for step in 0..steps {
+                        let t = step as f64 / steps as f64;
+                        let lerp = |pair: &[(f64, f64)]| pair[0].1 + (pair[1].1 - pair[0].1) * t;
+                        let x = lower[0].0 + (lower[1].0 - lower[0].0) * t;
+                        ctx.draw(&CanvasLine {
+                            x1: x,
+                            y1: lerp(lower),
+                            x2: x,
+                            y2: lerp(upper),
+                            color: band.color,
+                        });
+                    }
+                }
+            }
+            for line in &options.price_lines {
+                if !(y_min..=y_max).contains(&line.price) {
+                    continue;
+                }
+                ctx.draw(&CanvasLine {
+                    x1: 0.0,
+                    y1: line.price,
+                    x2: candles.len() as f64,
+                    y2: line.price,
+                    color: line.color,
+                });
+                ctx.print(
+                    0.0,
+                    line.price,
+                    Span::styled(line.label.clone(), Style::default().fg(line.color)),
+                );
+            }
+            if let Some((price, color)) = last_price {
+                // Dashes two columns long with two columns between them
+                let column = candles.len() as f64 / canvas_area.width.max(1) as f64;
+                let mut x = 0.0;
+                while x < candles.len() as f64 {
+                    ctx.draw(&CanvasLine {
+                        x1: x,
+                        y1: price,
+                        x2: (x + column * 1.5).min(candles.len() as f64),
+                        y2: price,
+                        color,
+                    });
+                    x += column * 4.0;
+                }
+            }
+            if let Some((crosshair, candle)) = crosshair {
+                let x = crosshair.index as f64 + 0.5;
+                ctx.draw(&CanvasLine {
+                    x1: x,
+                    y1: y_min,
+                    x2: x,
+                    y2: y_max,
+                    color: Color::DarkGray,
+                });
+                ctx.draw(&CanvasLine {
+                    x1: 0.0,
+                    y1: candle.close,
+                    x2: candles.len() as f64,
+                    y2: candle.close,
+                    color: Color::DarkGray,
+                });
+            }
+            ctx.layer();
+
+            for (i, candle) in candles.iter().enumerate() {
+                if !is_finite_candle(candle) {
+                    continue;
+                }
+
+                let x = i as f64 + 0.5;
+
+                if !compact && options.style == CandleStyle::Candles {
+                    ctx.draw(&CanvasLine {
+                        x1: x,
+                        y1: candle.low,
+                        x2: x,
+                        y2: candle.high,
+                        color: Color::White,
+                    });
+                }
+
+                let (body_bottom, body_top) = if candle.close >= candle.open {
+                    (candle.open, candle.close)
+                } else {
+                    (candle.close, candle.open)
+                };
+
+                let color = match (candle.close >= candle.open, options.volume_shading) {
+                    (true, false) => Color::Green,
+                    (false, false) => Color::Red,
+                    (true, true) => volume_shade(UP_RGB, candle.volume, max_volume),
+                    (false, true) => volume_shade(DOWN_RGB, candle.volume, max_volume),
+                };
+
+                if options.style == CandleStyle::Bars {
+                    ctx.draw(&CanvasLine {
+                        x1: x,
+                        y1: candle.low,
+                        x2: x,
+                        y2: candle.high,
+                        color,
+                    });
+                    if !compact {
+                        ctx.draw(&CanvasLine {
+                            x1: x - 0.3,
+                            y1: candle.open,
+                            x2: x,
+                            y2: candle.open,
+                            color,
+                        });
+                        ctx.draw(&CanvasLine {
+                            x1: x,
+                            y1: candle.close,
+                            x2: x + 0.3,
+                            y2: candle.close,
+                            color,
+                        });
+                    }
+                } else if compact {
+                    ctx.draw(&CanvasLine {
+                        x1: x,
+                        y1: body_bottom,
+                        x2: x,
+                        y2: body_top,
+                        color,
+                    });
+                } else {
+                    ctx.draw(&Rectangle {
+                        x: x - 0.3,
+                        y: body_bottom,
+                        width: 0.6,
+                        height: body_top - body_bottom,
+                        color,
+                    });
+                }
+
+                if detailed && pin_bar_ratio > 0.0 {
+                    let marker_style = Style::default().fg(Color::Yellow);
+                    match is_pin_bar(candle, pin_bar_ratio) {
+                        Some(PinDir::Bullish) => ctx.print(
+                            x,
+                            candle.low - y_padding * 0.5,
+                            Span::styled("▲", marker_style),
+                        ),
+                        Some(PinDir::Bearish) => ctx.print(
+                            x,
+                            candle.high + y_padding * 0.5,
+                            Span::styled("▼", marker_style),
+                        ),
+                        None => {}
+                    }
+                }
+            }
+
+            for marker in options.markers.iter().filter(|_| detailed) {
+                let Some(candle) = candles.get(marker.index).filter(|c| is_finite_candle(c)) else {
+                    continue;
+                };
+                let y = if marker.above {
+                    candle.high + y_padding * 0.8
+                } else {
+                    candle.low - y_padding * 0.8
+                };
+                ctx.print(
+                    marker.index as f64 + 0.5,
+                    y,
+                    Span::styled(marker.symbol, Style::default().fg(marker.color)),
+                );
+            }
+
+            ctx.layer();
+            let comparison_line = options.comparison.as_ref().map(|c| &c.line);
+            for overlay in options.overlays.iter().chain(comparison_line) {
+                for pair in overlay.points.windows(2) {
+                    ctx.draw(&CanvasLine {
+                        x1: pair[0].0,
+                        y1: pair[0].1,
+                        x2: pair[1].0,
+                        y2: pair[1].1,
+                        color: overlay.color,
+                    });
+                }
+            }
+
+            let comparison = options
+                .comparison
+                .iter()
+                .flat_map(|c| [(&c.label, c.color), (&c.line.label, c.line.color)]);
+            let legend: Vec<Span> = comparison
+                .chain(
+                    options
+                        .overlays
+                        .iter()
+                        .map(|overlay| (&overlay.label, overlay.color)),
+                )
+                .filter(|(label, _)| !label.is_empty())
+                .map(|(label, color)| {
+                    Span::styled(format!("{} ", label), Style::default().fg(color))
+                })
+                .collect();
+            if detailed && !legend.is_empty() {
+                ctx.print(0.0, y_max, Line::from(legend));
+            }
+        });
+
+    f.render_widget(canvas, canvas_area);
+
+    if gutter > 0 && canvas_area.height > 0 {
+        // Rows as the canvas places points, four braille dots to a row
+        let dots = canvas_area.height as f64 * 4.0 - 1.0;
+        let row = |price: f64| {
+            let row = ((y_max - price) / (y_max - y_min) * dots / 4.0) as u16;
+            canvas_area.y + row.min(canvas_area.height - 1)
+        };
+        for (tick, label) in &ticks {
+            f.buffer_mut().set_string(
+                canvas_area.right() + 1,
+                row(*tick),
+                label,
+                Style::default().fg(Color::Gray),
+            );
+        }
+        if let (Some((price, color)), Some(tag)) = (last_price, &last_price_tag) {
+            f.buffer_mut().set_string(
+                canvas_area.right(),
+                row(price),
+                format!(" {:<width$}", tag, width = gutter as usize - 1),
+                Style::default().fg(Color::Black).bg(color),
+            );
+        }
+    }
+
+    if time_row {
+        let y = canvas_area.bottom();
+        for (_, column, label) in &time_marks {
+            f.buffer_mut().set_string(
+                canvas_area.x + column,
+                y,
+                label,
+                Style::default().fg(Color::Gray),
+            );
+        }
+    }
+
+    let readout = crosshair.and_then(|(crosshair, _)| {
+        let width = crosshair
+            .readout
+            .iter()
+            .map(|line| line.chars().count() as u16 + 2)
+            .max()
+            .unwrap_or(0);
+        let height = crosshair.readout.len() as u16 + 2;
+        (width + 2 <= area.width && height + 3 <= area.height).then_some((crosshair, width, height))
+    });
+    if let Some((crosshair, width, height)) = readout {
+        // Kept clear of the legend row, on the side away from the cursor
+        let x = if crosshair.index * 2 < candles.len() {
+            area.x + area.width.saturating_sub(width + 1)
+        } else {
+            area.x + 1
+        };
+        let readout_area = Rect {
+            x,
+            y: area.y + 2,
+            width,
+            height,
+        };
+        let lines: Vec<Line> = crosshair
+            .readout
+            .iter()
+            .map(|line| Line::raw(line.as_str()))
+            .collect();
+        f.render_widget(Clear, readout_area);
+        f.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::DarkGray)),
+            ),
+            readout_area,
+        );
+    }
+
+    canvas_area
+}
+
+/// A named series of `(x, value)` points in the candlestick x layout, drawn
+/// in its color
+pub type PaneSeries<'a> = (&'a str, &'a [(f64, f64)], Color);
+
+/// Series with one value per candle sharing a y axis, drawn by
+/// [`render_series_pane`]
+#[derive(Debug, Clone)]
+pub struct SeriesPane<'a> {
+    /// Panel and y axis title
+    pub title: &'a str,
+    pub series: Vec<PaneSeries<'a>>,
+    pub graph_type: GraphType,
+    /// Fixed y bounds; `None` fits the values with some padding
+    pub y_bounds: Option<(f64, f64)>,
+    /// Dotted grid lines at the middle y label and at the candlestick
+    /// chart's time ticks
+    pub grid: bool,
+}
+
+/// Draws `pane` under a bordered panel filling `area`, laid out on the same x
+/// positions as [`render_candlestick_chart`] of `candles` so the two line up
+/// when stacked in equally wide areas.
+///
+/// The axes use the left columns for the bottom, middle and top values,
+/// written by `format_value`, and the bottom rows for the first/last candle
+/// times, so `area` needs roughly 6 rows and 20 columns before the series is
+/// visible. Without candles or points only the titled block is drawn.
+pub fn render_series_pane(
+    f: &mut Frame,
+    area: Rect,
+    candles: &[Candle],
+    pane: &SeriesPane,
+    format_value: impl Fn(f64) -> String,
+    theme: &Theme,
+) {
+    let mut values = pane.series.iter().flat_map(|(_, points, _)| points.iter());
+    if candles.is_empty() || values.clone().next().is_none() {
+        f.render_widget(theme.chart_block(pane.title, area), area);
+        return;
+    }
+
+    let (y_min, y_max) = pane.y_bounds.unwrap_or_else(|| {
+        let (min, max) = values
+            .by_ref()
+            .fold((f64::MAX, f64::MIN), |(min, max), &(_, v)| {
+                (min.min(v), max.max(v))
+            });
+        let padding = axis_padding(min, max);
+        (min - padding, max + padding)
+    });
+
+    // Dots a cell apart, roughly, as the axes take a few cells of `area`
+    let grid: Vec<(f64, f64)> = if pane.grid {
+        let (width, height) = (area.width.max(1), area.height.max(1));
+        let (column, row) = (
+            candles.len() as f64 / width as f64,
+            (y_max - y_min) / height as f64,
+        );
+        let middle = (0..width).map(|i| ((i as f64 + 0.5) * column, (y_min + y_max) / 2.0));
+        let verticals = time_labels(candles, width)
+            .into_iter()
+            .flat_map(|(index, _, _)| {
+                (0..height).map(move |i| (index as f64 + 0.5, y_min + (i as f64 + 0.5) * row))
+            });
+        middle.chain(verticals).collect()
+    } else {
+        Vec::new()
+    };
+
+    let mut datasets = vec![
+        Dataset::default()
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Scatter)
+            .style(Style::default().fg(theme.grid_color))
+            .data(&grid),
+    ];
+    datasets.extend(pane.series.iter().map(|&(name, points, color)| {
+        Dataset::default()
+            .name(name)
+            .marker(symbols::Marker::Braille)
+            .graph_type(pane.graph_type)
+            .style(Style::default().fg(color))
+            .data(points)
+    }));
+
+    let x_labels = if candles.len() > 5 {
+        vec![
+            Span::from(format_time(candles.first().unwrap().time)),
+            Span::from(format_time(candles.last().unwrap().time)),
+        ]
+    } else {
+        candles
+            .iter()
+            .map(|c| Span::from(format_time(c.time)))
+            .collect()
+    };
+
+    let y_labels = vec![
+        Span::from(format_value(y_min)),
+        Span::from(format_value((y_min + y_max) / 2.0)),
+        Span::from(format_value(y_max)),
+    ];
+
+    let chart = Chart::new(datasets)
+        .block(theme.chart_block(pane.title, area))
+        .x_axis(
+            Axis::default()
+                .title(Line::from("Time"))
+                .style(Style::default().fg(Color::Gray))
+                .bounds([0.0, candles.len() as f64])
+                .labels(x_labels),
+        )
+        .y_axis(
+            Axis::default()
+                .title(Line::from(pane.title))
+                .style(Style::default().fg(Color::Gray))
+                .bounds([y_min, y_max])
+                .labels(y_labels),
+        );
+
+    f.render_widget(chart, area);
+}
+
+/// Draws the candles' volume as bars in a bordered panel filling `area`,
+/// from zero up to a little over the busiest candle, with that peak written
+/// in the columns to the right of the bars when they leave room for it.
+///
+/// Under a candlestick chart, pass the area [`render_candlestick_chart`]
+/// returned for the same candles as `plot`: the bars then span its columns,
+/// so each sits right under its candle whatever the price labels take up.
+/// `None` spreads them across the whole panel. Empty input draws only the
+/// titled block.
+pub fn render_volume_chart(
+    f: &mut Frame,
+    area: Rect,
+    plot: Option<Rect>,
+    candles: &[Candle],
+    grid: bool,
+    theme: &Theme,
+) {
+    let block = theme.chart_block("Volume", area);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let peak = candles
+        .iter()
+        .map(|c| c.volume)
+        .filter(|v| v.is_finite())
+        .fold(0.0, f64::max);
+    let bars_area = match plot {
+        Some(plot) => inner.intersection(Rect {
+            y: inner.y,
+            height: inner.height,
+            ..plot
+        }),
+        None => inner,
+    };
+    if candles.is_empty() || bars_area.is_empty() {
+        return;
+    }
+    let max_volume = if peak > 0.0 { peak * 1.1 } else { 1.0 };
+    let time_marks = time_labels(candles, bars_area.width);
+
+    let canvas = Canvas::default()
+        .background_color(theme.chart_background.unwrap_or(Color::Reset))
+        .x_bounds([0.0, candles.len() as f64])
+        .y_bounds([0.0, max_volume])
+        .paint(|ctx| {
+            if grid {
+                // A dot per row at each of the candlestick chart's time ticks
+                let row = max_volume / bars_area.height as f64;
+                for (index, _, _) in &time_marks {
+                    let coords: Vec<(f64, f64)> = (0..bars_area.height)
+                        .map(|i| (*index as f64 + 0.5, (i as f64 + 0.5) * row))
+                        .collect();
+                    ctx.draw(&Points {
+                        coords: &coords,
+                        color: theme.grid_color,
+                    });
+                }
+                ctx.layer();
+            }
+
+            // Same x layout as the candlestick canvas: candle i is centred at
+            // i + 0.5 within [0, len]
+            for (i, candle) in candles.iter().enumerate() {
+                if candle.volume.is_finite() && candle.volume > 0.0 {
+                    let x = i as f64 + 0.5;
+                    ctx.draw(&CanvasLine {
+                        x1: x,
+                        y1: 0.0,
+                        x2: x,
+                        y2: candle.volume,
+                        color: Color::Blue,
+                    });
+                }
+            }
+        });
+    f.render_widget(canvas, bars_area);
+
+    let label = format!("{:.0}", peak);
+    if bars_area.right() + (label.len() as u16) < inner.right() {
+        f.buffer_mut().set_string(
+            bars_area.right() + 1,
+            bars_area.y,
+            label,
+            Style::default().fg(Color::Gray),
+        );
+    }
+}
+
+/// Draws `book` as cumulative depth curves in a bordered panel filling
+/// `area`: bids in green stepping up to the left of the spread, asks in red
+/// stepping up to the right, with x as price and y as the total size resting
+/// up to that price.
+///
+/// The lowest and highest prices are printed along the bottom and the
+/// deepest total along the top. A book with an empty side draws only the
+/// titled block.
+pub fn render_depth_chart(f: &mut Frame, area: Rect, book: &OrderBook, theme: &Theme) {
+    let (Some(&(lowest, _)), Some(&(highest, _))) = (book.bids.last(), book.asks.last()) else {
+        f.render_widget(theme.chart_block("Depth", area), area);
+        return;
+    };
+
+    // Running totals outward from the spread, as (price, size so far)
+    let cumulative = |levels: &[(f64, f64)]| -> Vec<(f64, f64)> {
+        levels
+            .iter()
+            .scan(0.0, |total, &(price, size)| {
+                *total += size;
+                Some((price, *total))
+            })
+            .collect()
+    };
+    let bids = cumulative(&book.bids);
+    let asks = cumulative(&book.asks);
+    let deepest = bids
+        .iter()
+        .chain(&asks)
+        .map(|&(_, total)| total)
+        .fold(0.0, f64::max)
+        * 1.1;
+
+    let canvas = Canvas::default()
+        .block(theme.chart_block("Depth", area))
+        .background_color(theme.chart_background.unwrap_or(Color::Reset))
+        .x_bounds([lowest, highest])
+        .y_bounds([0.0, deepest])
+        .paint(|ctx| {
+            for (levels, color) in [(&bids, Color::Green), (&asks, Color::Red)] {
+                // Each level rises at its price, then runs flat to the next
+                let mut previous = 0.0;
+                for (i, &(price, total)) in levels.iter().enumerate() {
+                    ctx.draw(&CanvasLine {
+                        x1: price,
+                        y1: previous,
+                        x2: price,
+                        y2: total,
+                        color,
+                    });
+                    if let Some(&(next_price, _)) = levels.get(i + 1) {
+                        ctx.draw(&CanvasLine {
+                            x1: price,
+                            y1: total,
+                            x2: next_price,
+                            y2: total,
+                            color,
+                        });
+                    }
+                    previous = total;
+                }
+            }
+
+            let label_style = Style::default().fg(Color::Gray);
+            ctx.print(
+                lowest,
+                0.0,
+                Span::styled(format!("{:.2}", lowest), label_style),
+            );
+            ctx.print(
+                lowest,
+                deepest,
+                Span::styled(format!("{:.2}", deepest), label_style),
+            );
+            let highest_label = format!("{:.2}", highest);
+            // Right-aligned so the label stays inside the panel
+            let label_width = (highest - lowest) * highest_label.len() as f64
+                / area.width.saturating_sub(2).max(1) as f64;
+            ctx.print(
+                highest - label_width,
+                0.0,
+                Span::styled(highest_label, label_style),
+            );
+        });
+
+    f.render_widget(canvas, area);
+}
+
+/// Draws `trades` (oldest first) as a time-and-sales list in a bordered
+/// panel filling `area`, newest at the top, with buys in green and sells in
+/// red. `format_price` formats the price column, so the caller picks the
+/// currency.
+///
+/// Each row takes about 30 columns; only as many trades as fit are shown.
+pub fn render_trade_tape(
+    f: &mut Frame,
+    area: Rect,
+    trades: &[Trade],
+    format_price: impl Fn(f64) -> String,
+    theme: &Theme,
+) {
+    let rows = area.height.saturating_sub(2) as usize;
+    let items: Vec<ListItem> = trades
+        .iter()
+        .rev()
+        .take(rows)
+        .map(|trade| {
+            let color = match trade.side {
+                Side::Buy => Color::Green,
+                Side::Sell => Color::Red,
+            };
+            ListItem::new(Line::styled(
+                format!(
+                    "{} {} {:.4}",
+                    format_clock(trade.time),
+                    format_price(trade.price),
+                    trade.size
+                ),
+                Style::default().fg(color),
+            ))
+        })
+        .collect();
+
+    let list = List::new(items).block(theme.chart_block("Trades", area));
+    f.render_widget(list, area);
+}
+
+/// Draws detected candlestick patterns as `(time, market, pattern)` (oldest
+/// first) in a bordered panel filling `area`, newest at the top, colored by
+/// the direction each pattern hints at.
+///
+/// Only as many entries as fit are shown.
+pub fn render_pattern_log(
+    f: &mut Frame,
+    area: Rect,
+    events: &[(i64, &str, Pattern)],
+    theme: &Theme,
+) {
+    let rows = area.height.saturating_sub(2) as usize;
+    let items: Vec<ListItem> = events
+        .iter()
+        .rev()
+        .take(rows)
+        .map(|&(time, market, pattern)| {
+            let color = match pattern.bullish() {
+                Some(true) => Color::Green,
+                Some(false) => Color::Red,
+                None => Color::Gray,
+            };
+            ListItem::new(Line::styled(
+                format!("{} {} {}", format_time(time), market, pattern.name()),
+                Style::default().fg(color),
+            ))
+        })
+        .collect();
+
+    let list = List::new(items).block(theme.chart_block("Patterns", area));
+    f.render_widget(list, area);
+}
+
+/// Draws golden and death crosses as `(time, cross, price)` (oldest first) in
+/// a bordered panel filling `area`, newest at the top, skipping the `scroll`
+/// newest. The title counts the ones above and below when they don't all fit.
+pub fn render_signal_log(
+    f: &mut Frame,
+    area: Rect,
+    signals: &[(i64, Cross, String)],
+    scroll: usize,
+    theme: &Theme,
+) {
+    let rows = area.height.saturating_sub(2) as usize;
+    let scroll = scroll.min(signals.len().saturating_sub(rows));
+    let items: Vec<ListItem> = signals
+        .iter()
+        .rev()
+        .skip(scroll)
+        .take(rows)
+        .map(|(time, cross, price)| {
+            let (name, color) = match cross {
+                Cross::Golden => ("Golden ▲", Color::Green),
+                Cross::Death => ("Death ▼", Color::Red),
+            };
+            ListItem::new(Line::styled(
+                format!("{} {} {}", format_time(*time), name, price),
+                Style::default().fg(color),
+            ))
+        })
+        .collect();
+
+    let below = signals.len().saturating_sub(scroll + rows);
+    let title = if scroll > 0 || below > 0 {
+        format!("Signals ↑{} ↓{}", scroll, below)
+    } else {
+        "Signals".to_string()
+    };
+    let list = List::new(items).block(theme.chart_block(title, area));
+    f.render_widget(list, area);
+}
+
+/// Draws a line of `points` (as produced by [`ratio_series`]) in a bordered
+/// panel filling `area`, with an x range of `[0, candle_count]` so it lines up
+/// with a candlestick panel of `candle_count` candles.
+///
+/// Only y labels are drawn, so 3 rows is enough for a thin strip. Empty
+/// input draws only the titled block.
+pub fn render_ratio_chart(
+    f: &mut Frame,
+    area: Rect,
+    points: &[(f64, f64)],
+    candle_count: usize,
+    title: &str,
+    theme: &Theme,
+) {
+    if points.is_empty() {
+        f.render_widget(theme.chart_block(title, area), area);
+        return;
+    }
+
+    let (min_ratio, max_ratio) = points
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(min, max), &(_, r)| {
+            (min.min(r), max.max(r))
+        });
+
+    let y_padding = axis_padding(min_ratio, max_ratio);
+    let y_min = min_ratio - y_padding;
+    let y_max = max_ratio + y_padding;
+
+    let format_ratio = |r: f64| {
+        if r.abs() >= 100.0 {
+            format!("{:.0}", r)
+        } else {
+            format!("{:.4}", r)
+        }
+    };
+
+    let datasets = vec![
+        Dataset::default()
+            .name("Ratio")
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Magenta))
+            .data(points),
+    ];
+
+    let chart = Chart::new(datasets)
+        .block(theme.chart_block(title, area))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds([0.0, candle_count as f64]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds([y_min, y_max])
+                .labels(vec![
+                    Span::from(format_ratio(y_min)),
+                    Span::from(format_ratio(y_max)),
+                ]),
+        );
+
+    f.render_widget(chart, area);
+}
+
+/// Draws a rate-of-change series (see [`crate::indicators::roc`]) as a line
+/// oscillating around a dim zero line, in a bordered panel filling `area`
+/// with the same x range as a candlestick panel of `candle_count` candles.
+///
+/// The y range always includes zero. Like [`render_ratio_chart`], 3 rows is
+/// enough; empty input (e.g. fewer candles than the period) draws only the
+/// titled block.
+pub fn render_roc_chart(
+    f: &mut Frame,
+    area: Rect,
+    points: &[(f64, f64)],
+    candle_count: usize,
+    title: &str,
+    theme: &Theme,
+) {
+    if points.is_empty() {
+        f.render_widget(theme.chart_block(title, area), area);
+        return;
+    }
+
+    let (min_roc, max_roc) = points
+        .iter()
+        .fold((0.0_f64, 0.0_f64), |(min, max), &(_, r)| {
+            (min.min(r), max.max(r))
+        });
+
+    let y_padding = axis_padding(min_roc, max_roc);
+    let y_min = min_roc - y_padding;
+    let y_max = max_roc + y_padding;
+
+    let zero_line = [(0.0, 0.0), (candle_count as f64, 0.0)];
+    let datasets = vec![
+        Dataset::default()
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::DarkGray))
+            .data(&zero_line),
+        Dataset::default()
+            .name("ROC")
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Cyan))
+            .data(points),
+    ];
+
+    let chart = Chart::new(datasets)
+        .block(theme.chart_block(title, area))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds([0.0, candle_count as f64]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds([y_min, y_max])
+                .labels(vec![
+                    Span::from(format!("{:+.2}%", y_min)),
+                    Span::from(format!("{:+.2}%", y_max)),
+                ]),
+        );
+
+    f.render_widget(chart, area);
+}
+
+/// RSI above this is overbought
+pub const RSI_OVERBOUGHT: f64 = 70.0;
+/// RSI below this is oversold
+pub const RSI_OVERSOLD: f64 = 30.0;
+
+/// Draws an RSI series (see [`crate::indicators::rsi`]) on a fixed 0–100
+/// scale in a bordered panel filling `area`, with the same x range as a
+/// candlestick panel of `candle_count` candles.
+///
+/// Dim guide lines mark [`RSI_OVERBOUGHT`] and [`RSI_OVERSOLD`], and the line
+/// turns red above the first and green below the second. The latest value
+/// follows the title. Empty input draws only the titled block.
+pub fn render_rsi_chart(
+    f: &mut Frame,
+    area: Rect,
+    points: &[(f64, f64)],
+    candle_count: usize,
+    title: &str,
+    theme: &Theme,
+) {
+    let zone_color = |value: f64| {
+        if value > RSI_OVERBOUGHT {
+            Color::Red
+        } else if value < RSI_OVERSOLD {
+            Color::Green
+        } else {
+            Color::Yellow
+        }
+    };
+
+    let Some(&(_, latest)) = points.last() else {
+        f.render_widget(theme.chart_block(title, area), area);
+        return;
+    };
+    let title = Line::from(vec![
+        Span::raw(format!("{} ", title)),
+        Span::styled(
+            format!("{:.1}", latest),
+            Style::default().fg(zone_color(latest)),
+        ),
+    ]);
+
+    let width = candle_count as f64;
+    let canvas = Canvas::default()
+        .block(theme.chart_block(title, area))
+        .background_color(theme.chart_background.unwrap_or(Color::Reset))
+        .x_bounds([0.0, width])
+        .y_bounds([0.0, 100.0])
+        .paint(|ctx| {
+            for level in [RSI_OVERSOLD, RSI_OVERBOUGHT] {
+                ctx.draw(&CanvasLine {
+                    x1: 0.0,
+                    y1: level,
+                    x2: width,
+                    y2: level,
+                    color: Color::DarkGray,
+                });
+                ctx.print(
+                    0.0,
+                    level,
+                    Span::styled(format!("{:.0}", level), Style::default().fg(Color::Gray)),
+                );
+            }
+            ctx.layer();
+
+            // Each segment takes the zone of its more extreme end
+            for pair in points.windows(2) {
+                let (from, to) = (pair[0].1, pair[1].1);
+                let extreme = if (to - 50.0).abs() > (from - 50.0).abs() {
+                    to
+                } else {
+                    from
+                };
+                ctx.draw(&CanvasLine {
+                    x1: pair[0].0,
+                    y1: from,
+                    x2: pair[1].0,
+                    y2: to,
+                    color: zone_color(extreme),
+                });
+            }
+        });
+
+    f.render_widget(canvas, area);
+}
+
+/// Draws a [`Macd`] in a bordered panel filling `area`, with the same x
+/// range as a candlestick panel of `candle_count` candles: the histogram as
+/// green and red bars around a dim zero line, the MACD line in cyan and the
+/// signal line in magenta.
+///
+/// The y range always includes zero, with its ends printed at the left. An
+/// empty MACD line (fewer candles than the slow period) draws only the
+/// titled block.
+pub fn render_macd_chart(
+    f: &mut Frame,
+    area: Rect,
+    macd: &Macd,
+    candle_count: usize,
+    title: &str,
+    theme: &Theme,
+) {
+    if macd.line.is_empty() {
+        f.render_widget(theme.chart_block(title, area), area);
+        return;
+    }
+
+    let (min, max) = macd
+        .line
+        .iter()
+        .chain(&macd.signal)
+        .chain(&macd.histogram)
+        .fold((0.0_f64, 0.0_f64), |(min, max), &(_, v)| {
+            (min.min(v), max.max(v))
+        });
+    let y_padding = axis_padding(min, max);
+    let (y_min, y_max) = (min - y_padding, max + y_padding);
+    let width = candle_count as f64;
+
+    let canvas = Canvas::default()
+        .block(theme.chart_block(title, area))
+        .background_color(theme.chart_background.unwrap_or(Color::Reset))
+        .x_bounds([0.0, width])
+        .y_bounds([y_min, y_max])
+        .paint(|ctx| {
+            ctx.draw(&CanvasLine {
+                x1: 0.0,
+                y1: 0.0,
+                x2: width,
+                y2: 0.0,
+                color: Color::DarkGray,
+            });
+            for &(x, value) in &macd.histogram {
+                ctx.draw(&CanvasLine {
+                    x1: x,
+                    y1: 0.0,
+                    x2: x,
+                    y2: value,
+                    color: if value >= 0.0 {
+                        Color::Green
+                    } else {
+                        Color::Red
+                    },
+                });
+            }
+            ctx.layer();
+
+            for (points, color) in [(&macd.line, Color::Cyan), (&macd.signal, Color::Magenta)] {
+                for pair in points.windows(2) {
+                    ctx.draw(&CanvasLine {
+                        x1: pair[0].0,
+                        y1: pair[0].1,
+                        x2: pair[1].0,
+                        y2: pair[1].1,
+                        color,
+                    });
+                }
+            }
+
+            let label_style = Style::default().fg(Color::Gray);
+            ctx.print(
+                0.0,
+                y_max,
+                Span::styled(format!("{:.2}", y_max), label_style),
+            );
+            ctx.print(
+                0.0,
+                y_min,
+                Span::styled(format!("{:.2}", y_min), label_style),
+            );
+        });
+
+    f.render_widget(canvas, area);
+}
+
+/// Stochastic above this is overbought
+pub const STOCHASTIC_OVERBOUGHT: f64 = 80.0;
+/// Stochastic below this is oversold
+pub const STOCHASTIC_OVERSOLD: f64 = 20.0;
+
+/// Draws the stochastic oscillator's %K (cyan) and %D (magenta) lines, as
+/// from [`crate::indicators::Stochastic`], on a fixed 0–100 scale in a
+/// bordered panel filling `area`, with the same x range as a candlestick
+/// panel of `candle_count` candles.
+///
+/// Dim guide lines mark [`STOCHASTIC_OVERBOUGHT`] and
+/// [`STOCHASTIC_OVERSOLD`], and the latest %K and %D follow the title. An
+/// empty %K draws only the titled block.
+pub fn render_stochastic_chart(
+    f: &mut Frame,
+    area: Rect,
+    k: &[(f64, f64)],
+    d: &[(f64, f64)],
+    candle_count: usize,
+    title: &str,
+    theme: &Theme,
+) {
+    let Some(&(_, latest_k)) = k.last() else {
+        f.render_widget(theme.chart_block(title, area), area);
+        return;
+    };
+    let mut spans = vec![
+        Span::raw(format!("{} ", title)),
+        Span::styled(
+            format!("%K {:.1}", latest_k),
+            Style::default().fg(Color::Cyan),
+        ),
+    ];
+    if let Some(&(_, latest_d)) = d.last() {
+        spans.push(Span::styled(
+            format!(" %D {:.1}", latest_d),
+            Style::default().fg(Color::Magenta),
+        ));
+    }
+
+    let width = candle_count as f64;
+    let canvas = Canvas::default()
+        .block(theme.chart_block(Line::from(spans), area))
+        .background_color(theme.chart_background.unwrap_or(Color::Reset))
+        .x_bounds([0.0, width])
+        .y_bounds([0.0, 100.0])
+        .paint(|ctx| {
+            for level in [STOCHASTIC_OVERSOLD, STOCHASTIC_OVERBOUGHT] {
+                ctx.draw(&CanvasLine {
+                    x1: 0.0,
+                    y1: level,
+                    x2: width,
+                    y2: level,
+                    color: Color::DarkGray,
+                });
+                ctx.print(
+                    0.0,
+                    level,
+                    Span::styled(format!("{:.0}", level), Style::default().fg(Color::Gray)),
+                );
+            }
+            ctx.layer();
+
+            for (points, color) in [(k, Color::Cyan), (d, Color::Magenta)] {
+                for pair in points.windows(2) {
+                    ctx.draw(&CanvasLine {
+                        x1: pair[0].0,
+                        y1: pair[0].1,
+                        x2: pair[1].0,
+                        y2: pair[1].1,
+                        color,
+                    });
+                }
+            }
+        });
+
+    f.render_widget(canvas, area);
+}
+
+/// Draws an average true range series (see [`crate::indicators::atr`]) as a
+/// yellow line in a bordered panel filling `area`, with the same x range as a
+/// candlestick panel of `candle_count` candles.
+///
+/// `format_price` writes the latest value after the title and the y labels,
+/// as the range is in the market's price units. Empty input draws only the
+/// titled block.
+pub fn render_atr_chart(
+    f: &mut Frame,
+    area: Rect,
+    points: &[(f64, f64)],
+    candle_count: usize,
+    title: &str,
+    format_price: impl Fn(f64) -> String,
+    theme: &Theme,
+) {
+    let Some(&(_, latest)) = points.last() else {
+        f.render_widget(theme.chart_block(title, area), area);
+        return;
+    };
+    let title = Line::from(vec![
+        Span::raw(format!("{} ", title)),
+        Span::styled(format_price(latest), Style::default().fg(Color::Yellow)),
+    ]);
+
+    let (min_atr, max_atr) = points
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(min, max), &(_, a)| {
+            (min.min(a), max.max(a))
+        });
+
+    let y_padding = axis_padding(min_atr, max_atr);
+    let y_min = (min_atr - y_padding).max(0.0);
+    let y_max = max_atr + y_padding;
+
+    let datasets = vec![
+        Dataset::default()
+            .name("ATR")
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Yellow))
+            .data(points),
+    ];
+
+    let chart = Chart::new(datasets)
+        .block(theme.chart_block(title, area))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds([0.0, candle_count as f64]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds([y_min, y_max])
+                .labels(vec![
+                    Span::from(format_price(y_min)),
+                    Span::from(format_price(y_max)),
+                ]),
+        );
+
+    f.render_widget(chart, area);
+}
+
+/// Close-to-close ratio of `a` against `b`, aligned on the most recent
+/// candles. X values follow the candlestick layout of `a` (candle i at i + 0.5)
+/// so the line sits under the matching candles; points where `b` has a zero or
+/// non-finite close are skipped.
+pub fn ratio_series(a: &[Candle], b: &[Candle]) -> Vec<(f64, f64)> {
+    let n = a.len().min(b.len());
+    let a_start = a.len() - n;
+    let b_start = b.len() - n;
+
+    a[a_start..]
+        .iter()
+        .zip(&b[b_start..])
+        .enumerate()
+        .filter(|(_, (_, bc))| bc.close != 0.0 && bc.close.is_finite())
+        .map(|(i, (ac, bc))| ((a_start + i) as f64 + 0.5, ac.close / bc.close))
+        .filter(|(_, ratio)| ratio.is_finite())
+        .collect()
+}
+
+/// Closes of `b` scaled onto the prices of `a`, so both start the window
+/// together and move by the same percentages from there. Aligned on the most
+/// recent candles like [`ratio_series`], with x values in the candlestick
+/// layout of `a`; the window starts at the first pair where both closes are
+/// finite and non-zero.
+pub fn comparison_series(a: &[Candle], b: &[Candle]) -> Vec<(f64, f64)> {
+    let n = a.len().min(b.len());
+    let a_start = a.len() - n;
+    let b_start = b.len() - n;
+    let usable = |close: f64| close != 0.0 && close.is_finite();
+
+    let pairs = a[a_start..].iter().zip(&b[b_start..]).enumerate();
+    let Some((a_base, b_base)) = pairs
+        .clone()
+        .find(|(_, (ac, bc))| usable(ac.close) && usable(bc.close))
+        .map(|(_, (ac, bc))| (ac.close, bc.close))
+    else {
+        return Vec::new();
+    };
+
+    pairs
+        .skip_while(|(_, (ac, bc))| !(usable(ac.close) && usable(bc.close)))
+        .filter(|(_, (_, bc))| bc.close.is_finite())
+        .map(|(i, (_, bc))| ((a_start + i) as f64 + 0.5, a_base * bc.close / b_base))
+        .collect()
+}
+
+/// Scales `base` by the candle's share of `max_volume`: the busiest candle gets
+/// the full color and a zero-volume one drops to 30% intensity, so quiet
+/// candles fade without disappearing.
+pub fn volume_shade(base: (u8, u8, u8), volume: f64, max_volume: f64) -> Color {
+    let share = if max_volume > 0.0 && volume.is_finite() {
+        (volume / max_volume).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+    let intensity = 0.3 + 0.7 * share;
+    let scale = |channel: u8| (channel as f64 * intensity).round() as u8;
+
+    Color::Rgb(scale(base.0), scale(base.1), scale(base.2))
+}
+
+/// Indices of candles that follow one or more missing intervals. The expected
+/// interval is the smallest positive spacing between consecutive candles, and
+/// anything more than 1.5 times that counts as a gap.
+pub fn find_gaps(candles: &[Candle]) -> Vec<usize> {
+    let Some(interval) = candles
+        .windows(2)
+        .map(|w| w[1].time - w[0].time)
+        .filter(|&delta| delta > 0)
+        .min()
+    else {
+        return Vec::new();
+    };
+
+    candles
+        .windows(2)
+        .enumerate()
+        .filter(|(_, w)| (w[1].time - w[0].time) * 2 > interval * 3)
+        .map(|(i, _)| i + 1)
+        .collect()
+}
+
+/// Detects a pin bar: a candle whose longer wick is at least `ratio` times its
+/// body. A candle with equal wicks is ambiguous and never qualifies.
+pub fn is_pin_bar(candle: &Candle, ratio: f64) -> Option<PinDir> {
+    let body = (candle.close - candle.open).abs();
+    let upper_wick = candle.high - candle.open.max(candle.close);
+    let lower_wick = candle.open.min(candle.close) - candle.low;
+
+    if lower_wick > upper_wick && lower_wick >= body * ratio {
+        Some(PinDir::Bullish)
+    } else if upper_wick > lower_wick && upper_wick >= body * ratio {
+        Some(PinDir::Bearish)
+    } else {
+        None
+    }
+}
+
+/// Round-number y bounds for `candles` that stay put while prices move inside
+/// them, so live updates don't rescale the axis on every tick.
+///
+/// `current` is kept while every price stays within it and the prices still
+/// span at least a quarter of its height. Otherwise the padded price range is
+/// widened outward to multiples of a 1, 2 or 5 step, about a quarter of the
+/// range. Returns `None` when there are no finite candles.
+pub fn snapped_price_bounds(candles: &[Candle], current: Option<(f64, f64)>) -> Option<(f64, f64)> {
+    let (min_price, max_price) = price_bounds(candles)?;
+
+    if let Some((low, high)) = current
+        && low <= min_price
+        && max_price <= high
+        && (max_price - min_price) * 4.0 >= high - low
+    {
+        return current;
+    }
+
+    let padding = axis_padding(min_price, max_price);
+    let (min, max) = (min_price - padding, max_price + padding);
+    let step = nice_step((max - min) / 4.0);
+
+    Some(((min / step).floor() * step, (max / step).ceil() * step))
+}
+
+/// Round prices between `min` and `max` to label an axis with, about
+/// `count` of them: multiples of the smallest 1, 2 or 5 step that splits the
+/// range into no more than `count` parts. Empty for an empty or non-finite
+/// range.
+pub fn price_ticks(min: f64, max: f64, count: usize) -> Vec<f64> {
+    if !(min.is_finite() && max.is_finite() && max > min) || count == 0 {
+        return Vec::new();
+    }
+
+    let step = nice_step((max - min) / count as f64);
+    let first = (min / step).ceil() as i64;
+    let last = (max / step).floor() as i64;
+    (first..=last).map(|i| i as f64 * step).collect()
+}
+
+/// Columns apart, at least, that time labels are placed
+const TIME_LABEL_SPACING: f64 = 10.0;
+
+/// Spans of time, in seconds, that time labels are spread by: the first
+/// that keeps them far enough apart is used
+const TIME_LABEL_SPANS: [i64; 18] = [
+    1, 2, 5, 10, 15, 30, 60, 120, 300, 600, 900, 1800, 3600, 7200, 14400, 21600, 43200, 86400,
+];
+
+/// Time labels for `candles` drawn across `width` columns, as the position
+/// of the candle each is for and the column it starts at. A label goes on
+/// the first candle of each span of time
+/// (see [`TIME_LABEL_SPANS`]) wide enough to keep them
+/// [`TIME_LABEL_SPACING`] apart, centred under it.
+fn time_labels(candles: &[Candle], width: u16) -> Vec<(usize, u16, String)> {
+    let Some(interval) = candles
+        .windows(2)
+        .map(|pair| pair[1].time - pair[0].time)
+        .filter(|&gap| gap > 0)
+        .min()
+    else {
+        return Vec::new();
+    };
+
+    let columns_per_candle = width as f64 / candles.len() as f64;
+    let shortest = interval * (TIME_LABEL_SPACING / columns_per_candle).ceil().max(1.0) as i64;
+    let span = TIME_LABEL_SPANS
+        .into_iter()
+        .find(|&span| span >= shortest)
+        .unwrap_or(shortest);
+
+    let mut labels = Vec::new();
+    let mut free_from = 0;
+    for (i, pair) in candles.windows(2).enumerate() {
+        if pair[0].time.div_euclid(span) == pair[1].time.div_euclid(span) {
+            continue;
+        }
+        let label = format_time(pair[1].time);
+        let width_needed = label.chars().count() as u16;
+        let centre = ((i + 1) as f64 + 0.5) * columns_per_candle;
+        let column = (centre as u16)
+            .saturating_sub(width_needed / 2)
+            .min(width.saturating_sub(width_needed));
+        if column >= free_from {
+            free_from = column + width_needed + 1;
+            labels.push((i + 1, column, label));
+        }
+    }
+    labels
+}
+
+/// The smallest 1, 2 or 5 times a power of ten that's at least `raw_step`
+fn nice_step(raw_step: f64) -> f64 {
+    let magnitude = 10f64.powf(raw_step.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|m| m * magnitude)
+        .find(|&step| step >= raw_step)
+        .unwrap_or(magnitude * 10.0)
+}
+
+/// Padding added above and below a value range: 10% of the range, or for a
+/// flat (or nearly flat) range a band of 1% of the value so it still renders
+/// in the middle of the panel instead of collapsing the bounds.
+fn axis_padding(min: f64, max: f64) -> f64 {
+    let range = max - min;
+    let magnitude = min.abs().max(max.abs());
+
+    if range > magnitude * 1e-9 {
+        range * 0.1
+    } else if magnitude > 0.0 {
+        magnitude * 0.01
+    } else {
+        1.0
+    }
+}
+
+fn is_finite_candle(candle: &Candle) -> bool {
+    candle.open.is_finite()
+        && candle.high.is_finite()
+        && candle.low.is_finite()
+        && candle.close.is_finite()
+}
+
+/// Lowest low and highest high across the candles, ignoring any candle with a
+/// non-finite price so a single bad row can't turn the bounds into NaN.
+fn price_bounds(candles: &[Candle]) -> Option<(f64, f64)> {
+    candles
+        .iter()
+        .filter(|c| is_finite_candle(c))
+        .fold(None, |bounds, c| match bounds {
+            None => Some((c.low, c.high)),
+            Some((min, max)) => Some((f64::min(min, c.low), f64::max(max, c.high))),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{Terminal, backend::TestBackend, buffer::Buffer};
+
+    fn candle(time: i64, low: f64, high: f64) -> Candle {
+        Candle {
+            time,
+            open: low,
+            high,
+            low,
+            close: high,
+            volume: 1.0,
+        }
+    }
+
+    /// Columns between `left` and `right` with anything drawn in the rows
+    /// from `top` to `bottom`
+    fn drawn_columns(
+        buffer: &Buffer,
+        (left, right): (u16, u16),
+        (top, bottom): (u16, u16),
+    ) -> Vec<u16> {
+        (left..right)
+            .filter(|&x| (top..bottom).any(|y| buffer[(x, y)].symbol() != " "))
+            .collect()
+    }
+
+    #[test]
+    fn volume_bars_line_up_under_their_candles() {
+        // Only candle 7 reaches up to 200 and has any volume
+        let candles: Vec<Candle> = (0..12)
+            .map(|i| Candle {
+                time: i * 60,
+                open: 100.0,
+                high: if i == 7 { 200.0 } else { 101.0 },
+                low: 99.0,
+                close: 100.5,
+                volume: if i == 7 { 5.0 } else { 0.0 },
+            })
+            .collect();
+        let options = CandlestickOptions {
+            pin_bar_ratio: 0.0,
+            price_axis: Some((Currency::Usd, NumberFormat::Humanized)),
+            ..CandlestickOptions::default()
+        };
+        let theme = Theme::default();
+
+        for width in [40, 61, 90] {
+            let mut terminal = Terminal::new(TestBackend::new(width, 30)).unwrap();
+            let mut plot = Rect::default();
+            terminal
+                .draw(|f| {
+                    let candle_area = Rect::new(0, 0, width, 20);
+                    plot = render_candlestick_chart(f, candle_area, &candles, &options, &theme);
+                    let volume_area = Rect::new(0, 20, width, 10);
+                    render_volume_chart(f, volume_area, Some(plot), &candles, false, &theme);
+                })
+                .unwrap();
+            let buffer = terminal.backend().buffer();
+            let columns = (plot.left(), plot.right());
+
+            let wick = drawn_columns(buffer, columns, (plot.top(), plot.top() + plot.height / 3));
+            let bar = drawn_columns(buffer, columns, (21, 29));
+            assert!(!wick.is_empty(), "width {width}");
+            assert_eq!(wick, bar, "width {width}");
+        }
+    }
+
+    #[test]
+    fn price_bounds_skip_non_finite_highs() {
+        let candles = [
+            candle(0, 10.0, 12.0),
+            candle(60, 11.0, f64::NAN),
+            candle(120, 9.0, f64::INFINITY),
+            candle(180, 10.5, 13.0),
+        ];
+
+        assert_eq!(price_bounds(&candles), Some((10.0, 13.0)));
+    }
+
+    #[test]
+    fn flat_ranges_still_get_padding() {
+        assert_eq!(axis_padding(100.0, 100.0), 1.0);
+        assert_eq!(axis_padding(-50.0, -50.0), 0.5);
+        assert_eq!(axis_padding(0.0, 0.0), 1.0);
+        assert_eq!(axis_padding(100.0, 110.0), 1.0);
+    }
+
+    #[test]
+    fn a_missing_minute_is_marked_as_a_gap() {
+        let candles: Vec<_> = [0, 60, 180, 240, 300]
+            .into_iter()
+            .map(|time| candle(time, 10.0, 11.0))
+            .collect();
+
+        assert_eq!(find_gaps(&candles), vec![2]);
+        assert!(find_gaps(&candles[2..]).is_empty());
+    }
+
+    #[test]
+    fn snapped_bounds_hold_until_the_price_leaves_them() {
+        let mut candles = vec![candle(0, 100.0, 110.0)];
+        let bounds = snapped_price_bounds(&candles, None);
+        assert_eq!(bounds, Some((95.0, 115.0)));
+
+        candles.push(candle(60, 105.0, 112.0));
+        assert_eq!(snapped_price_bounds(&candles, bounds), bounds);
+
+        candles.push(candle(120, 110.0, 116.0));
+        assert_eq!(snapped_price_bounds(&candles, bounds), Some((95.0, 120.0)));
+    }
+
+    fn pin(open: f64, high: f64, low: f64, close: f64) -> Candle {
+        Candle {
+            time: 0,
+            open,
+            high,
+            low,
+            close,
+            volume: 1.0,
+        }
+    }
+
+    #[test]
+    fn pin_bars_need_a_wick_of_at_least_ratio_times_the_body() {
+        // A body of 1 against a threshold of 3
+        assert_eq!(
+            is_pin_bar(&pin(10.0, 11.0, 6.9, 11.0), 3.0),
+            Some(PinDir::Bullish)
+        );
+        assert_eq!(
+            is_pin_bar(&pin(10.0, 11.0, 7.0, 11.0), 3.0),
+            Some(PinDir::Bullish)
+        );
+        assert_eq!(is_pin_bar(&pin(10.0, 11.0, 7.1, 11.0), 3.0), None);
+
+        assert_eq!(
+            is_pin_bar(&pin(11.0, 14.1, 10.0, 10.0), 3.0),
+            Some(PinDir::Bearish)
+        );
+        assert_eq!(is_pin_bar(&pin(11.0, 13.9, 10.0, 10.0), 3.0), None);
+    }
+}
@@ -0,0 +1,140 @@
+//! One-minute candles polled from Coinbase Exchange's public REST API.
+//!
+//! Only USD markets have Coinbase products (`USD/BTC` is `BTC-USD`); other
+//! markets receive no candles from this feed. Each poll re-reads the latest
+//! candles, so the still-open candle arrives repeatedly as it updates.
+
+use crate::{
+    net::Network,
+    source::{MarketDataSource, Message},
+};
+use crypto_tracking::Candle;
+use serde_json::Value;
+use std::{
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+use ureq::Agent;
+
+const CANDLES_URL: &str = "https://api.exchange.coinbase.com/products";
+const SANDBOX_CANDLES_URL: &str = "https://api-public.sandbox.exchange.coinbase.com/products";
+
+/// Most candles Coinbase returns for one request
+const MAX_CANDLES: usize = 300;
+
+/// Least time between two requests, well under Coinbase's public rate limit
+/// even with many markets
+pub const MIN_REQUEST_GAP: Duration = Duration::from_millis(250);
+
+/// Polls every market's latest candles once per `interval`
+#[derive(Debug)]
+pub struct CoinbaseFeed {
+    pub interval: Duration,
+    pub network: Network,
+}
+
+impl MarketDataSource for CoinbaseFeed {
+    fn subscribe(&self, markets: &[String]) -> mpsc::Receiver<Message> {
+        let (tx, rx) = mpsc::channel();
+        let products: Vec<(String, String)> = markets
+            .iter()
+            .filter_map(|m| Some((m.clone(), coinbase_product(m)?)))
+            .collect();
+        let interval = self.interval;
+        let agent = self.network.agent();
+        let base = self
+            .network
+            .endpoint(CANDLES_URL, SANDBOX_CANDLES_URL)
+            .to_string();
+
+        thread::spawn(move || {
+            // Newest candle time sent per market; older ones are skipped.
+            // The first poll only sends the open candle, as anything older is
+            // history.
+            let mut sent: Vec<Option<i64>> = vec![None; products.len()];
+
+            loop {
+                let round_started = Instant::now();
+
+                for ((market, product), last_sent) in products.iter().zip(&mut sent) {
+                    let request_started = Instant::now();
+
+                    // A failed poll is retried on the next round rather than
+                    // ending the feed
+                    if let Ok(candles) = fetch_candles(&agent, &base, product) {
+                        let since = last_sent.or(candles.last().map(|c| c.time));
+                        for candle in candles.into_iter().filter(|c| Some(c.time) >= since) {
+                            *last_sent = Some(candle.time);
+                            if tx.send(Message::NewCandle(market.clone(), candle)).is_err() {
+                                return;
+                            }
+                        }
+                    }
+
+                    if let Some(wait) = MIN_REQUEST_GAP.checked_sub(request_started.elapsed()) {
+                        thread::sleep(wait);
+                    }
+                }
+
+                if tx.send(Message::Heartbeat).is_err() {
+                    return;
+                }
+                if let Some(wait) = interval.checked_sub(round_started.elapsed()) {
+                    thread::sleep(wait);
+                }
+            }
+        });
+
+        rx
+    }
+
+    fn fetch_history(&self, market: &str, limit: usize) -> Result<Vec<Candle>, String> {
+        let Some(product) = coinbase_product(market) else {
+            return Ok(Vec::new());
+        };
+
+        let base = self.network.endpoint(CANDLES_URL, SANDBOX_CANDLES_URL);
+        let candles = fetch_candles(&self.network.agent(), base, &product)
+            .map_err(|e| format!("fetching {} history: {}", market, e))?;
+        let skip = candles.len().saturating_sub(limit.min(MAX_CANDLES));
+
+        Ok(candles.into_iter().skip(skip).collect())
+    }
+}
+
+/// The Coinbase product for a market, e.g. `BTC-USD` for `USD/BTC`
+fn coinbase_product(market: &str) -> Option<String> {
+    match market.split_once('/')? {
+        ("USD", base) => Some(format!("{}-USD", base)),
+        _ => None,
+    }
+}
+
+/// The latest one-minute candles for `product` from the API at `base`,
+/// oldest first
+fn fetch_candles(agent: &Agent, base: &str, product: &str) -> Result<Vec<Candle>, String> {
+    let url = format!("{}/{}/candles?granularity=60", base, product);
+    let body = agent
+        .get(&url)
+        .call()
+        .and_then(|mut response| response.body_mut().read_to_string())
+        .map_err(|e| e.to_string())?;
+    let rows: Vec<Value> = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+
+    // Coinbase lists candles newest first
+    Ok(rows.iter().rev().filter_map(parse_candle_row).collect())
+}
+
+/// A candle from a `[time, low, high, open, close, volume]` row, with the
+/// time in seconds
+fn parse_candle_row(row: &Value) -> Option<Candle> {
+    Some(Candle {
+        time: row[0].as_i64()?,
+        open: row[3].as_f64()?,
+        high: row[2].as_f64()?,
+        low: row[1].as_f64()?,
+        close: row[4].as_f64()?,
+        volume: row[5].as_f64()?,
+    })
+}
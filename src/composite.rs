@@ -0,0 +1,216 @@
+//! One market price from several exchanges at once.
+//!
+//! Every source is subscribed to the same markets. Each time one of them
+//! sends a candle, the candles all sources have for that same minute are
+//! merged into a volume-weighted composite, which is what reaches the chart.
+//! Each source's latest close follows as a [`Message::Quotes`] so the UI can
+//! show how far apart the exchanges are.
+
+use crate::source::{MarketDataSource, Message};
+use crypto_tracking::Candle;
+use std::{collections::HashMap, sync::mpsc, thread};
+
+/// Several named sources merged into one composite candle per market
+#[derive(Default)]
+pub struct Composite {
+    sources: Vec<(String, Box<dyn MarketDataSource>)>,
+}
+
+impl Composite {
+    pub fn source(mut self, name: &str, source: Box<dyn MarketDataSource>) -> Self {
+        self.sources.push((name.to_string(), source));
+        self
+    }
+}
+
+impl MarketDataSource for Composite {
+    fn subscribe(&self, markets: &[String]) -> mpsc::Receiver<Message> {
+        let (tx, rx) = mpsc::channel();
+        let (tagged_tx, tagged_rx) = mpsc::channel();
+
+        for (i, (_, source)) in self.sources.iter().enumerate() {
+            let source_rx = source.subscribe(markets);
+            let tagged_tx = tagged_tx.clone();
+            thread::spawn(move || {
+                for message in source_rx {
+                    if tagged_tx.send((i, message)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        drop(tagged_tx);
+
+        let names: Vec<String> = self.sources.iter().map(|(name, _)| name.clone()).collect();
+        thread::spawn(move || {
+            let mut merger = Merger::new(names.len());
+
+            for (i, message) in tagged_rx {
+                let (market, candle) = match message {
+                    Message::NewCandle(market, candle) => (market, candle),
+                    other => {
+                        if tx.send(other).is_err() {
+                            return;
+                        }
+                        continue;
+                    }
+                };
+
+                let Some(composite) = merger.push(i, &market, candle) else {
+                    continue;
+                };
+                let quotes = names
+                    .iter()
+                    .zip(merger.closes(&market))
+                    .filter_map(|(name, close)| Some((name.clone(), close?)))
+                    .collect();
+
+                if tx
+                    .send(Message::NewCandle(market.clone(), composite))
+                    .is_err()
+                    || tx.send(Message::Quotes(market, quotes)).is_err()
+                {
+                    return;
+                }
+            }
+        });
+
+        rx
+    }
+
+    /// History from the first source that has any
+    fn fetch_history(&self, market: &str, limit: usize) -> Result<Vec<Candle>, String> {
+        let mut error = None;
+        for (_, source) in &self.sources {
+            match source.fetch_history(market, limit) {
+                Ok(candles) if !candles.is_empty() => return Ok(candles),
+                Ok(_) => {}
+                Err(e) => error = Some(e),
+            }
+        }
+
+        error.map_or(Ok(Vec::new()), Err)
+    }
+}
+
+/// Each source's latest candle per market, merged as they arrive
+struct Merger {
+    sources: usize,
+    latest: HashMap<String, Vec<Option<Candle>>>,
+    /// Minute of the newest composite sent, per market
+    newest: HashMap<String, i64>,
+}
+
+impl Merger {
+    fn new(sources: usize) -> Self {
+        Merger {
+            sources,
+            latest: HashMap::new(),
+            newest: HashMap::new(),
+        }
+    }
+
+    /// The composite for the minute of `source`'s `candle`. Sources roll
+    /// over to a new minute at different times, and a polled one may resend
+    /// the minute the others have left; such a candle gives `None`, as the
+    /// chart has moved past it.
+    fn push(&mut self, source: usize, market: &str, candle: Candle) -> Option<Candle> {
+        let newest = self.newest.entry(market.to_string()).or_insert(candle.time);
+        if candle.time < *newest {
+            return None;
+        }
+        *newest = candle.time;
+
+        let candles = self
+            .latest
+            .entry(market.to_string())
+            .or_insert_with(|| vec![None; self.sources]);
+        candles[source] = Some(candle);
+
+        // Sources still on an earlier minute are left out until they catch
+        // up
+        let same_minute: Vec<&Candle> = candles
+            .iter()
+            .flatten()
+            .filter(|c| c.time == *newest)
+            .collect();
+        Some(volume_weighted(&same_minute))
+    }
+
+    /// Each source's latest close for `market`, in source order
+    fn closes(&self, market: &str) -> Vec<Option<f64>> {
+        self.latest.get(market).map_or_else(
+            || vec![None; self.sources],
+            |candles| {
+                candles
+                    .iter()
+                    .map(|c| c.as_ref().map(|c| c.close))
+                    .collect()
+            },
+        )
+    }
+}
+
+/// Candles for the same minute merged into one, each price weighted by its
+/// candle's volume. Without any volume every candle counts equally.
+fn volume_weighted(candles: &[&Candle]) -> Candle {
+    let total: f64 = candles.iter().map(|c| c.volume).sum();
+    let weight = |c: &Candle| {
+        if total > 0.0 {
+            c.volume / total
+        } else {
+            1.0 / candles.len() as f64
+        }
+    };
+    let average =
+        |price: fn(&Candle) -> f64| -> f64 { candles.iter().map(|c| price(c) * weight(c)).sum() };
+
+    Candle {
+        time: candles[0].time,
+        open: average(|c| c.open),
+        high: average(|c| c.high),
+        low: average(|c| c.low),
+        close: average(|c| c.close),
+        volume: total,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(time: i64, close: f64) -> Candle {
+        Candle {
+            time,
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 1.0,
+        }
+    }
+
+    #[test]
+    fn sources_rolling_over_out_of_step_never_go_back_a_minute() {
+        let mut merger = Merger::new(2);
+        assert_eq!(
+            merger.push(0, "USD/BTC", candle(60, 100.0)).map(|c| c.time),
+            Some(60)
+        );
+        assert_eq!(
+            merger
+                .push(1, "USD/BTC", candle(60, 102.0))
+                .map(|c| c.close),
+            Some(101.0)
+        );
+
+        // The first source moves on, then the second resends the old minute
+        let rolled = merger.push(0, "USD/BTC", candle(120, 104.0)).unwrap();
+        assert_eq!((rolled.time, rolled.close), (120, 104.0));
+        assert!(merger.push(1, "USD/BTC", candle(60, 103.0)).is_none());
+
+        let caught_up = merger.push(1, "USD/BTC", candle(120, 106.0)).unwrap();
+        assert_eq!((caught_up.time, caught_up.close), (120, 105.0));
+        assert_eq!(merger.closes("USD/BTC"), vec![Some(104.0), Some(106.0)]);
+    }
+}
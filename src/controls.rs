@@ -0,0 +1,494 @@
+//! What the keyboard changes between frames: which market is selected, what
+//! the charts show, and the popups open over them.
+//!
+//! Each part handles its own keys and says whether it took one, so the main
+//! loop tries them in turn. Keys that need more of the app, such as
+//! recording or replay control, stay with the loop.
+
+use crate::{
+    AppState, BandField, FAVORITE_TAG, Fib, FibPlacement, Indicators, InfoMode, ParamEditor,
+    StatusMessage, SubPanel, ViewMode, adjust_bands,
+    browser::{BrowserEvent, MarketBrowser},
+    form::{Form, FormEvent},
+    save_settings,
+    settings::{KEYS, is_valid_tag},
+};
+use crossterm::event::KeyCode;
+use std::collections::HashMap;
+
+/// Which markets are picked out
+#[derive(Debug, Default)]
+pub struct Selection {
+    /// Position in the market list of the market the charts are for
+    pub market: usize,
+    /// Market the ratio panel divides the selected one by
+    pub benchmark: Option<usize>,
+    /// Market whose closes are drawn over the candles of the others
+    pub compared: Option<usize>,
+    /// Moves to whichever market is busiest as candles arrive
+    pub auto_follow: bool,
+}
+
+impl Selection {
+    /// Selects `market` if it's one of `markets`, taking over from
+    /// auto-follow
+    pub fn select(&mut self, markets: &[String], market: &str) {
+        if let Some(i) = markets.iter().position(|m| m == market) {
+            self.market = i;
+            self.auto_follow = false;
+        }
+    }
+
+    /// Handles Up/Down through `count` markets and the keys that pick the
+    /// benchmark, the compared market and auto-follow; `false` for any
+    /// other key
+    pub fn handle_key(&mut self, code: KeyCode, count: usize) -> bool {
+        match code {
+            KeyCode::Down => {
+                self.market = (self.market + 1) % count;
+                self.auto_follow = false;
+            }
+            KeyCode::Up => {
+                self.market = self.market.checked_sub(1).unwrap_or(count - 1);
+                self.auto_follow = false;
+            }
+            KeyCode::Char('b') => self.toggle_benchmark(self.market),
+            KeyCode::Char('K') => {
+                self.compared = if self.compared == Some(self.market) {
+                    None
+                } else {
+                    Some(self.market)
+                };
+            }
+            KeyCode::Char('a') => self.auto_follow = !self.auto_follow,
+            _ => return false,
+        }
+        true
+    }
+
+    fn toggle_benchmark(&mut self, market: usize) {
+        self.benchmark = if self.benchmark == Some(market) {
+            None
+        } else {
+            Some(market)
+        };
+    }
+}
+
+/// What the charts show and how
+#[derive(Debug)]
+pub struct Panels {
+    pub info_mode: InfoMode,
+    pub view_mode: ViewMode,
+    pub indicators: Indicators,
+    /// What `c` restores after hiding every indicator
+    pub hidden_indicators: Option<Indicators>,
+    /// The first panel under the candles: volume, or the order book, MACD
+    /// or OBV in its place
+    pub lower_panel: SubPanel,
+    pub show_tape: bool,
+    /// Dotted grid lines on the price and lower panels
+    pub grid: bool,
+    /// Draws the candlestick chart with Heikin-Ashi candles
+    pub heikin_ashi: bool,
+    /// Candles of every market on the page of the selected one, in a grid
+    /// in place of the charts
+    pub market_grid: bool,
+    /// Which of the panels under the candles is highlighted, counting up
+    /// with `p`
+    pub active_subpanel: usize,
+    /// Newest signals scrolled past in the signals panel
+    pub signal_scroll: usize,
+    /// Per-market y bounds while the axis is snapped to round numbers
+    pub snapped_bounds: Option<HashMap<String, (f64, f64)>>,
+}
+
+impl Default for Panels {
+    fn default() -> Self {
+        Panels {
+            info_mode: InfoMode::Price,
+            view_mode: ViewMode::Normal,
+            indicators: Indicators {
+                pin_bars: true,
+                scripts: true,
+                ..Indicators::NONE
+            },
+            hidden_indicators: None,
+            lower_panel: SubPanel::Volume,
+            show_tape: false,
+            grid: true,
+            heikin_ashi: false,
+            market_grid: false,
+            active_subpanel: 0,
+            signal_scroll: 0,
+            snapped_bounds: None,
+        }
+    }
+}
+
+impl Panels {
+    /// Handles a key that turns an indicator or panel on or off; `false`
+    /// for any other key. `signals` is how many signals the selected market
+    /// has, for scrolling through them, and `scripts` whether any indicator
+    /// scripts are loaded. Keys whose effect is hard to see say what they
+    /// did in `status`.
+    pub fn handle_key(
+        &mut self,
+        code: KeyCode,
+        signals: usize,
+        scripts: bool,
+        status: &mut Option<StatusMessage>,
+    ) -> bool {
+        let indicators = &mut self.indicators;
+        match code {
+            KeyCode::Char('i') => self.info_mode = self.info_mode.next(),
+            KeyCode::Char('d') => self.view_mode = self.view_mode.toggle(),
+            KeyCode::Char('p') => self.active_subpanel += 1,
+            KeyCode::Char('r') => indicators.roc = !indicators.roc,
+            KeyCode::Char('I') => {
+                indicators.oscillator = match indicators.oscillator {
+                    None => Some(SubPanel::Rsi),
+                    Some(SubPanel::Rsi) => Some(SubPanel::Stochastic),
+                    Some(_) => None,
+                };
+            }
+            KeyCode::Char('A') => indicators.atr = !indicators.atr,
+            KeyCode::Char('M') => indicators.sma = !indicators.sma,
+            KeyCode::Char('E') => indicators.ema = !indicators.ema,
+            KeyCode::Char('W') => indicators.vwap = !indicators.vwap,
+            KeyCode::Char('B') => indicators.bollinger = !indicators.bollinger,
+            KeyCode::Char('v') => indicators.volume_shading = !indicators.volume_shading,
+            KeyCode::Char('P') => indicators.patterns = !indicators.patterns,
+            KeyCode::Char('L') => indicators.levels = !indicators.levels,
+            KeyCode::Char('V') => indicators.pivots = !indicators.pivots,
+            KeyCode::Char('U') if scripts => indicators.scripts = !indicators.scripts,
+            KeyCode::Char('G') => {
+                indicators.signals = !indicators.signals;
+                self.signal_scroll = 0;
+            }
+            // Scroll the signals panel towards older or newer signals
+            KeyCode::Char('[') if indicators.signals => {
+                self.signal_scroll = (self.signal_scroll + 1).min(signals.saturating_sub(1));
+            }
+            KeyCode::Char(']') if indicators.signals => {
+                self.signal_scroll = self.signal_scroll.saturating_sub(1);
+            }
+            KeyCode::Char('c') => match self.hidden_indicators.take() {
+                Some(saved) if *indicators == Indicators::NONE => {
+                    *indicators = saved;
+                    *status = Some(StatusMessage::new("indicators restored"));
+                }
+                _ => {
+                    self.hidden_indicators = Some(*indicators);
+                    *indicators = Indicators::NONE;
+                    *status = Some(StatusMessage::new("indicators hidden"));
+                }
+            },
+            KeyCode::Char('s') => {
+                self.snapped_bounds = match self.snapped_bounds {
+                    Some(_) => {
+                        *status = Some(StatusMessage::new("y-axis follows price"));
+                        None
+                    }
+                    None => {
+                        *status = Some(StatusMessage::new("y-axis snapped"));
+                        Some(HashMap::new())
+                    }
+                };
+            }
+            KeyCode::Char('T') => self.show_tape = !self.show_tape,
+            KeyCode::Char('H') => self.heikin_ashi = !self.heikin_ashi,
+            KeyCode::Char('g') => self.grid = !self.grid,
+            KeyCode::Char('D') => self.market_grid = !self.market_grid,
+            KeyCode::Char('o') => self.toggle_lower_panel(SubPanel::Depth),
+            KeyCode::Char('x') => self.toggle_lower_panel(SubPanel::Macd),
+            KeyCode::Char('O') => self.toggle_lower_panel(SubPanel::Obv),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Puts `panel` in place of the volume, or the volume back if it's there
+    fn toggle_lower_panel(&mut self, panel: SubPanel) {
+        self.lower_panel = if self.lower_panel == panel {
+            SubPanel::Volume
+        } else {
+            panel
+        };
+    }
+}
+
+/// Popups and prompts drawn over the charts. While one is open it takes
+/// every key.
+#[derive(Debug)]
+pub struct Popups {
+    pub browser: Option<MarketBrowser>,
+    /// Open while this holds the Bollinger Bands field being edited
+    pub bands: Option<BandField>,
+    pub param_editor: Option<ParamEditor>,
+    /// Open while naming the tag to toggle on the selected market
+    pub tag_form: Option<Form>,
+    /// The tag last toggled, offered again the next time
+    pub last_tag: String,
+    pub fib_placement: Option<FibPlacement>,
+    pub confirming_quit: bool,
+}
+
+impl Default for Popups {
+    fn default() -> Self {
+        Popups {
+            browser: None,
+            bands: None,
+            param_editor: None,
+            tag_form: None,
+            last_tag: FAVORITE_TAG.to_string(),
+            fib_placement: None,
+            confirming_quit: false,
+        }
+    }
+}
+
+impl Popups {
+    /// Whether a popup is open that the mouse wheel shouldn't zoom the
+    /// charts under
+    pub fn blocks_mouse(&self) -> bool {
+        self.browser.is_some()
+            || self.bands.is_some()
+            || self.param_editor.is_some()
+            || self.tag_form.is_some()
+    }
+
+    /// Opens the parameter form on `market`'s settings, titled with its
+    /// display `name`
+    pub fn edit_settings(&mut self, state: &AppState, market: &str, name: &str) {
+        let original = state.settings.for_market(market).clone();
+        let fields = KEYS
+            .iter()
+            .map(|&key| (key.to_string(), original.get(key).unwrap_or_default()))
+            .collect();
+        self.param_editor = Some(ParamEditor {
+            form: Form::new(&format!("{} settings", name), fields),
+            market: market.to_string(),
+            original,
+        });
+    }
+
+    /// Asks for the tag to toggle on the market with display `name`
+    pub fn name_tag(&mut self, name: &str) {
+        self.tag_form = Some(Form::new(
+            &format!("Toggle tag on {}", name),
+            vec![("Tag".to_string(), self.last_tag.clone())],
+        ));
+    }
+
+    /// Passes `code` to the open popup that's on top, returning `false`
+    /// when none of them is open. Changes to settings are saved as they're
+    /// confirmed, saying how that went in `status`.
+    pub fn handle_key(
+        &mut self,
+        code: KeyCode,
+        state: &mut AppState,
+        selection: &mut Selection,
+        fibs: &mut HashMap<String, Fib>,
+        status: &mut Option<StatusMessage>,
+        display_names: &HashMap<String, String>,
+    ) -> bool {
+        let market = state.markets[selection.market].clone();
+        if let Some(browser) = self.browser.as_mut() {
+            match browser.handle_key(code, state, display_names) {
+                Some(BrowserEvent::Select(market)) => {
+                    selection.select(&state.markets, &market);
+                    self.browser = None;
+                }
+                Some(BrowserEvent::Close) => self.browser = None,
+                Some(BrowserEvent::ToggleBenchmark(market)) => {
+                    if let Some(i) = state.markets.iter().position(|m| *m == market) {
+                        selection.toggle_benchmark(i);
+                    }
+                }
+                None => {}
+            }
+        } else if let Some(field) = self.bands.as_mut() {
+            match code {
+                KeyCode::Up | KeyCode::Down | KeyCode::Tab => *field = field.other(),
+                KeyCode::Left | KeyCode::Char('-') => {
+                    state.update_settings(&market, |settings| adjust_bands(settings, *field, -1));
+                }
+                KeyCode::Right | KeyCode::Char('+') => {
+                    state.update_settings(&market, |settings| adjust_bands(settings, *field, 1));
+                }
+                KeyCode::Esc | KeyCode::Enter | KeyCode::Char(',') => {
+                    self.bands = None;
+                    *status = Some(save_settings(&state.settings));
+                }
+                _ => {}
+            }
+        } else if let Some(editor) = self.param_editor.as_mut() {
+            match editor.form.handle_key(code) {
+                // Usable values apply straight away so the chart follows
+                // along; the rest keep their previous value until fixed
+                Some(FormEvent::Changed) => {
+                    let mut settings = editor.original.clone();
+                    let errors: Vec<Option<String>> = editor
+                        .form
+                        .fields()
+                        .iter()
+                        .map(|field| settings.set(&field.label, field.value.trim()).err())
+                        .collect();
+                    for (i, error) in errors.into_iter().enumerate() {
+                        editor.form.set_error(i, error);
+                    }
+                    state.update_settings(&editor.market, |s| *s = settings);
+                }
+                Some(FormEvent::Submit)
+                    if editor
+                        .form
+                        .fields()
+                        .iter()
+                        .all(|field| field.error.is_none()) =>
+                {
+                    self.param_editor = None;
+                    *status = Some(save_settings(&state.settings));
+                }
+                Some(FormEvent::Cancel) => {
+                    let original = editor.original.clone();
+                    state.update_settings(&editor.market, |s| *s = original);
+                    self.param_editor = None;
+                }
+                // Enter with unusable values keeps the form open
+                _ => {}
+            }
+        } else if let Some(form) = self.tag_form.as_mut() {
+            match form.handle_key(code) {
+                Some(FormEvent::Changed) => {
+                    let tag = form.fields()[0].value.trim();
+                    let error = if tag.is_empty() {
+                        Some("name the tag")
+                    } else if !is_valid_tag(tag) {
+                        Some("no commas or # in tags")
+                    } else {
+                        None
+                    };
+                    form.set_error(0, error.map(str::to_string));
+                }
+                Some(FormEvent::Submit) => {
+                    let tag = form.fields()[0].value.trim().to_string();
+                    if is_valid_tag(&tag) {
+                        // Saved right away, like other changes to a market
+                        let market_tags = &mut state.settings.for_market_mut(&market).tags;
+                        match market_tags.iter().position(|t| *t == tag) {
+                            Some(i) => {
+                                market_tags.remove(i);
+                            }
+                            None => market_tags.push(tag.clone()),
+                        }
+                        *status = Some(save_settings(&state.settings));
+                        self.last_tag = tag;
+                        self.tag_form = None;
+                    }
+                }
+                Some(FormEvent::Cancel) => self.tag_form = None,
+                None => {}
+            }
+        } else if let Some(placement) = self.fib_placement.as_mut() {
+            let candles = state.data.get(&market).map_or(&[][..], Vec::as_slice);
+            match code {
+                KeyCode::Left => {
+                    placement.cursor = (placement.cursor + 1).min(candles.len().saturating_sub(1));
+                }
+                KeyCode::Right => placement.cursor = placement.cursor.saturating_sub(1),
+                KeyCode::Enter => {
+                    if let Some(candle) = candles
+                        .len()
+                        .checked_sub(placement.cursor + 1)
+                        .map(|i| &candles[i])
+                    {
+                        match placement.high {
+                            None => placement.high = Some((candle.time, candle.high)),
+                            Some(high) => {
+                                fibs.insert(
+                                    market,
+                                    Fib {
+                                        high,
+                                        low: (candle.time, candle.low),
+                                    },
+                                );
+                                self.fib_placement = None;
+                            }
+                        }
+                    }
+                }
+                KeyCode::Esc => self.fib_placement = None,
+                _ => {}
+            }
+        } else {
+            return false;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_wraps_around_the_market_list() {
+        let mut selection = Selection {
+            auto_follow: true,
+            ..Selection::default()
+        };
+        assert!(selection.handle_key(KeyCode::Up, 3));
+        assert_eq!(selection.market, 2);
+        assert!(!selection.auto_follow);
+        selection.handle_key(KeyCode::Down, 3);
+        assert_eq!(selection.market, 0);
+        assert!(!selection.handle_key(KeyCode::Char('q'), 3));
+    }
+
+    #[test]
+    fn benchmark_and_compared_markets_toggle_on_the_selected_one() {
+        let mut selection = Selection::default();
+        selection.select(&["a".to_string(), "b".to_string()], "b");
+        selection.handle_key(KeyCode::Char('b'), 2);
+        selection.handle_key(KeyCode::Char('K'), 2);
+        assert_eq!(
+            (selection.benchmark, selection.compared),
+            (Some(1), Some(1))
+        );
+        selection.handle_key(KeyCode::Char('b'), 2);
+        assert_eq!(selection.benchmark, None);
+
+        // Unknown markets leave the selection where it was
+        selection.select(&["a".to_string()], "c");
+        assert_eq!(selection.market, 1);
+    }
+
+    #[test]
+    fn hiding_indicators_restores_them_on_the_next_press() {
+        let mut panels = Panels::default();
+        let mut status = None;
+        let shown = panels.indicators;
+        panels.handle_key(KeyCode::Char('c'), 0, false, &mut status);
+        assert_eq!(panels.indicators, Indicators::NONE);
+        panels.handle_key(KeyCode::Char('c'), 0, false, &mut status);
+        assert_eq!(panels.indicators, shown);
+        assert_eq!(
+            status.map(|s| s.text).as_deref(),
+            Some("indicators restored")
+        );
+    }
+
+    #[test]
+    fn lower_panel_keys_swap_with_the_volume() {
+        let mut panels = Panels::default();
+        let mut status = None;
+        panels.handle_key(KeyCode::Char('o'), 0, false, &mut status);
+        panels.handle_key(KeyCode::Char('x'), 0, false, &mut status);
+        assert_eq!(panels.lower_panel, SubPanel::Macd);
+        panels.handle_key(KeyCode::Char('x'), 0, false, &mut status);
+        assert_eq!(panels.lower_panel, SubPanel::Volume);
+        // Script indicators only toggle once there are scripts
+        assert!(!panels.handle_key(KeyCode::Char('U'), 0, false, &mut status));
+    }
+}
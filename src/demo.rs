@@ -0,0 +1,111 @@
+//! Scripted input for reproducible demos and screen recordings.
+//!
+//! A script is a text file with one action per line, run top to bottom:
+//!
+//! ```text
+//! # comments and blank lines are ignored
+//! wait 2s          # pause before the next action (or e.g. 500ms)
+//! key d            # press a key: one character, or up/down/left/right/enter/esc/space/tab/backspace
+//! select USD/ETH   # jump straight to a market by symbol
+//! ```
+//!
+//! Actions run alongside real keyboard input, so the app can still be driven
+//! by hand while a script plays.
+
+use crossterm::event::KeyCode;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Key(KeyCode),
+    Select(String),
+}
+
+#[derive(Debug, Default)]
+pub struct DemoScript {
+    /// Actions paired with how long after start they become due, in order
+    steps: Vec<(Duration, Action)>,
+    next: usize,
+}
+
+impl DemoScript {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut at = Duration::ZERO;
+        let mut steps = Vec::new();
+
+        for (number, line) in source.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let error = |message: &str| format!("demo script line {}: {}", number + 1, message);
+            let (command, argument) = line
+                .split_once(char::is_whitespace)
+                .map(|(command, argument)| (command, argument.trim()))
+                .unwrap_or((line, ""));
+
+            match command {
+                "wait" => {
+                    at += parse_duration(argument)
+                        .ok_or_else(|| error("expected a duration like 2s or 500ms"))?;
+                }
+                "key" => {
+                    let key = parse_key(argument).ok_or_else(|| error("unknown key"))?;
+                    steps.push((at, Action::Key(key)));
+                }
+                "select" if !argument.is_empty() => {
+                    steps.push((at, Action::Select(argument.to_string())));
+                }
+                _ => return Err(error(&format!("unknown action: {}", line))),
+            }
+        }
+
+        Ok(DemoScript { steps, next: 0 })
+    }
+
+    /// The next action, once `elapsed` has reached its due time. Returns at
+    /// most one action per call so each goes through a full frame.
+    pub fn next_due(&mut self, elapsed: Duration) -> Option<Action> {
+        let (due, action) = self.steps.get(self.next)?;
+        if elapsed < *due {
+            return None;
+        }
+
+        self.next += 1;
+        Some(action.clone())
+    }
+}
+
+fn parse_duration(value: &str) -> Option<Duration> {
+    if let Some(millis) = value.strip_suffix("ms") {
+        millis.parse().ok().map(Duration::from_millis)
+    } else if let Some(secs) = value.strip_suffix('s') {
+        secs.parse::<f64>()
+            .ok()
+            .filter(|secs| secs.is_finite() && *secs >= 0.0)
+            .map(Duration::from_secs_f64)
+    } else {
+        None
+    }
+}
+
+fn parse_key(value: &str) -> Option<KeyCode> {
+    let mut chars = value.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+
+    match value {
+        "up" => Some(KeyCode::Up),
+        "down" => Some(KeyCode::Down),
+        "left" => Some(KeyCode::Left),
+        "right" => Some(KeyCode::Right),
+        "enter" => Some(KeyCode::Enter),
+        "esc" => Some(KeyCode::Esc),
+        "space" => Some(KeyCode::Char(' ')),
+        "tab" => Some(KeyCode::Tab),
+        "backspace" => Some(KeyCode::Backspace),
+        _ => None,
+    }
+}
@@ -0,0 +1,119 @@
+//! Funding rate and open interest of the perpetual contracts behind the
+//! markets, from Binance USDⓈ-M futures.
+//!
+//! This is an add-on to whichever feed runs: [`WithDerivatives`] passes the
+//! feed's messages through and adds a [`Message::Derivatives`] per market
+//! every poll. Markets without a Binance perpetual get none.
+
+use crate::{
+    binance::binance_symbol,
+    net::Network,
+    source::{MarketDataSource, Message},
+};
+use crypto_tracking::Candle;
+use serde_json::Value;
+use std::{sync::mpsc, thread, time::Duration};
+use ureq::Agent;
+
+const FUTURES_URL: &str = "https://fapi.binance.com/fapi/v1";
+const SANDBOX_FUTURES_URL: &str = "https://testnet.binancefuture.com/fapi/v1";
+
+/// Funding changes every few hours and open interest slowly, so there's no
+/// point polling often
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Positioning on a market's perpetual contract
+#[derive(Debug, Clone, Copy)]
+pub struct PerpStats {
+    /// Funding paid by longs to shorts per period, as a fraction
+    pub funding_rate: f64,
+    /// When the next funding payment happens, in seconds since the epoch
+    pub next_funding: i64,
+    /// Open contracts, in the base asset
+    pub open_interest: f64,
+}
+
+pub struct WithDerivatives {
+    source: Box<dyn MarketDataSource>,
+    network: Network,
+}
+
+impl WithDerivatives {
+    pub fn new(source: Box<dyn MarketDataSource>, network: Network) -> Self {
+        WithDerivatives { source, network }
+    }
+}
+
+impl MarketDataSource for WithDerivatives {
+    fn subscribe(&self, markets: &[String]) -> mpsc::Receiver<Message> {
+        let (tx, rx) = mpsc::channel();
+
+        let source_rx = self.source.subscribe(markets);
+        let source_tx = tx.clone();
+        thread::spawn(move || {
+            for message in source_rx {
+                if source_tx.send(message).is_err() {
+                    return;
+                }
+            }
+        });
+
+        let symbols: Vec<(String, String)> = markets
+            .iter()
+            .filter_map(|m| Some((m.clone(), binance_symbol(m)?)))
+            .collect();
+        let agent = self.network.agent();
+        let base = self
+            .network
+            .endpoint(FUTURES_URL, SANDBOX_FUTURES_URL)
+            .to_string();
+        thread::spawn(move || {
+            loop {
+                for (market, symbol) in &symbols {
+                    // A failed poll is retried on the next round
+                    if let Ok(stats) = fetch_stats(&agent, &base, symbol)
+                        && tx
+                            .send(Message::Derivatives(market.clone(), stats))
+                            .is_err()
+                    {
+                        return;
+                    }
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+
+        rx
+    }
+
+    fn fetch_history(&self, market: &str, limit: usize) -> Result<Vec<Candle>, String> {
+        self.source.fetch_history(market, limit)
+    }
+}
+
+/// Stats for `symbol` from the futures API at `base`
+fn fetch_stats(agent: &Agent, base: &str, symbol: &str) -> Result<PerpStats, String> {
+    let get = |endpoint: &str| -> Result<Value, String> {
+        let url = format!("{}/{}?symbol={}", base, endpoint, symbol);
+        let body = agent
+            .get(&url)
+            .call()
+            .and_then(|mut response| response.body_mut().read_to_string())
+            .map_err(|e| e.to_string())?;
+        serde_json::from_str(&body).map_err(|e| e.to_string())
+    };
+
+    // Binance sends the numbers as strings and times in milliseconds
+    let premium = get("premiumIndex")?;
+    let open_interest = get("openInterest")?;
+    let number = |value: &Value| value.as_str()?.parse::<f64>().ok();
+    let stats = (|| {
+        Some(PerpStats {
+            funding_rate: number(&premium["lastFundingRate"])?,
+            next_funding: premium["nextFundingTime"].as_i64()? / 1000,
+            open_interest: number(&open_interest["openInterest"])?,
+        })
+    })();
+
+    stats.ok_or_else(|| format!("unexpected derivatives data for {}", symbol))
+}
@@ -0,0 +1,153 @@
+//! Popup form of labelled text fields, edited from the keyboard.
+//!
+//! Up/Down (or Tab/Shift-Tab) move between fields, typing edits the
+//! highlighted one and Backspace deletes from its end. Enter submits and Esc
+//! cancels. The form only holds text: the caller checks the values after
+//! each change and marks fields it can't use with [`Form::set_error`].
+
+use crossterm::event::KeyCode;
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+/// What the app should do after the form handled a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormEvent {
+    /// The highlighted field's text changed
+    Changed,
+    Submit,
+    Cancel,
+}
+
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub label: String,
+    pub value: String,
+    /// Why the value can't be used, if it can't
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Form {
+    title: String,
+    fields: Vec<Field>,
+    highlighted: usize,
+}
+
+impl Form {
+    /// A form titled `title` with `(label, value)` fields, top to bottom
+    pub fn new(title: &str, fields: Vec<(String, String)>) -> Self {
+        Form {
+            title: title.to_string(),
+            fields: fields
+                .into_iter()
+                .map(|(label, value)| Field {
+                    label,
+                    value,
+                    error: None,
+                })
+                .collect(),
+            highlighted: 0,
+        }
+    }
+
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    pub fn set_error(&mut self, field: usize, error: Option<String>) {
+        if let Some(field) = self.fields.get_mut(field) {
+            field.error = error;
+        }
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<FormEvent> {
+        let count = self.fields.len().max(1);
+        match code {
+            KeyCode::Up | KeyCode::BackTab => {
+                self.highlighted = (self.highlighted + count - 1) % count;
+            }
+            KeyCode::Down | KeyCode::Tab => self.highlighted = (self.highlighted + 1) % count,
+            KeyCode::Backspace => {
+                self.fields.get_mut(self.highlighted)?.value.pop()?;
+                return Some(FormEvent::Changed);
+            }
+            KeyCode::Char(c) if !c.is_control() => {
+                self.fields.get_mut(self.highlighted)?.value.push(c);
+                return Some(FormEvent::Changed);
+            }
+            KeyCode::Enter => return Some(FormEvent::Submit),
+            KeyCode::Esc => return Some(FormEvent::Cancel),
+            _ => {}
+        }
+        None
+    }
+
+    /// Draws the form as a popup centred in `area`. Fields with an error are
+    /// red, and the highlighted field's error replaces the key hints at the
+    /// bottom.
+    pub fn render(&self, f: &mut Frame, area: Rect) {
+        let label_width = self
+            .fields
+            .iter()
+            .map(|field| field.label.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let lines: Vec<Line> = self
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let mut style = Style::default();
+                if field.error.is_some() {
+                    style = style.fg(Color::Red);
+                }
+                let value = if i == self.highlighted {
+                    style = style.add_modifier(Modifier::REVERSED);
+                    format!("{}_", field.value)
+                } else {
+                    field.value.clone()
+                };
+                Line::from(vec![
+                    Span::raw(format!(" {:<width$} ", field.label, width = label_width)),
+                    Span::styled(value, style),
+                ])
+            })
+            .collect();
+
+        let hint = match self
+            .fields
+            .get(self.highlighted)
+            .and_then(|field| field.error.as_deref())
+        {
+            Some(error) => Span::styled(format!(" {} ", error), Style::default().fg(Color::Red)),
+            None => Span::raw(" ↑/↓ field · enter save · esc cancel "),
+        };
+
+        let width = (label_width as u16 + 24)
+            .max(hint.width() as u16 + 2)
+            .min(area.width);
+        let height = (self.fields.len() as u16 + 2).min(area.height);
+        let popup_area = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        let popup = Paragraph::new(lines).block(
+            Block::default()
+                .title(format!(" {} ", self.title))
+                .title_bottom(hint)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Blue)),
+        );
+        f.render_widget(Clear, popup_area);
+        f.render_widget(popup, popup_area);
+    }
+}
@@ -0,0 +1,246 @@
+//! Price and time formatting shared by the chart panels and the app.
+
+use chrono::{DateTime, Local, TimeZone};
+
+/// Whether large prices are abbreviated with K/M/B suffixes or written out in
+/// full with thousands separators, whatever the currency
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NumberFormat {
+    #[default]
+    Humanized,
+    Full,
+}
+
+impl NumberFormat {
+    pub fn toggle(self) -> Self {
+        match self {
+            NumberFormat::Humanized => NumberFormat::Full,
+            NumberFormat::Full => NumberFormat::Humanized,
+        }
+    }
+}
+
+/// The currency a market is quoted in, which picks how its prices are written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Currency {
+    Usd,
+    Idr,
+    /// Any other currency, written generically as `CODE 123.45`
+    Other(String),
+}
+
+impl Currency {
+    pub fn from_code(code: &str) -> Self {
+        match code.to_ascii_uppercase().as_str() {
+            "USD" => Currency::Usd,
+            "IDR" => Currency::Idr,
+            other => Currency::Other(other.to_string()),
+        }
+    }
+
+    pub fn format_price(&self, price: f64, number_format: NumberFormat) -> String {
+        match self {
+            Currency::Usd => format_usd(price, number_format),
+            Currency::Idr => format!("Rp{}", format_idr(price, number_format)),
+            Currency::Other(code) => format!("{} {}", code, format_other(price, number_format)),
+        }
+    }
+}
+
+pub fn format_time(timestamp: i64) -> String {
+    match DateTime::from_timestamp(timestamp, 0) {
+        Some(dt) => {
+            let local_dt = Local.from_utc_datetime(&dt.naive_utc());
+            local_dt.format("%H:%M").to_string()
+        }
+        None => {
+            eprintln!("Warning: Invalid timestamp {}", timestamp);
+            "Invalid Time".to_string()
+        }
+    }
+}
+
+/// Like [`format_time`], with seconds
+pub fn format_clock(timestamp: i64) -> String {
+    match DateTime::from_timestamp(timestamp, 0) {
+        Some(dt) => {
+            let local_dt = Local.from_utc_datetime(&dt.naive_utc());
+            local_dt.format("%H:%M:%S").to_string()
+        }
+        None => "Invalid Time".to_string(),
+    }
+}
+
+pub fn format_usd(price: f64, number_format: NumberFormat) -> String {
+    if !price.is_finite() {
+        return "Invalid".to_string();
+    }
+
+    if price == 0.0 {
+        return "$0.00".to_string();
+    }
+
+    let abs_price = price.abs();
+    let sign = if price < 0.0 { "-" } else { "" };
+
+    if number_format == NumberFormat::Full {
+        let decimals = if abs_price >= 0.10 { 2 } else { 4 };
+        let fixed = format!("{:.*}", decimals, abs_price);
+        let (integer, fraction) = fixed.split_once('.').unwrap_or((&fixed, "00"));
+        return format!("${}{}.{}", sign, group_thousands(integer, ','), fraction);
+    }
+
+    let formatted = if let Some((scaled, suffix)) = abbreviate(abs_price) {
+        format!("{}{:.2}{}", sign, scaled, suffix)
+    } else if abs_price >= 0.10 {
+        format!("{}{:.2}", sign, abs_price)
+    } else {
+        format!("{}{:.4}", sign, abs_price) 
+    };
+
+    if (0.10..1_000.0).contains(&abs_price) {
+        let parts: Vec<&str> = formatted.split('.').collect();
+        let integer_part = parts[0]
+            .chars()
+            .rev()
+            .collect::<String>()
+            .as_bytes()
+            .chunks(3)
+            .map(|chunk| std::str::from_utf8(chunk).unwrap())
+            .collect::<Vec<&str>>()
+            .join(",")
+            .chars()
+            .rev()
+            .collect::<String>();
+
+        format!("${}.{}", integer_part, parts[1])
+    } else {
+        format!("${}", formatted)
+    }
+}
+
+/// `abs_price` scaled down to the largest of thousands, millions or billions
+/// it reaches, with the matching suffix. `None` below a thousand.
+fn abbreviate(abs_price: f64) -> Option<(f64, char)> {
+    [(1_000_000_000.0, 'B'), (1_000_000.0, 'M'), (1_000.0, 'K')]
+        .into_iter()
+        .find(|&(unit, _)| abs_price >= unit)
+        .map(|(unit, suffix)| (abs_price / unit, suffix))
+}
+
+fn group_thousands(digits: &str, separator: char) -> String {
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(separator);
+        }
+        grouped.push(c);
+    }
+
+    grouped
+}
+
+/// Rupiah, written the Indonesian way: `.` between thousands and `,` before
+/// the decimals of an abbreviated value
+pub fn format_idr(price: f64, number_format: NumberFormat) -> String {
+    if price.is_nan() || price.is_infinite() {
+        return "Invalid".to_string();
+    }
+
+    if number_format == NumberFormat::Humanized
+        && let Some((scaled, suffix)) = abbreviate(price.abs())
+    {
+        let sign = if price < 0.0 { "-" } else { "" };
+        return format!("{}{:.2}{}", sign, scaled, suffix).replace('.', ",");
+    }
+
+    let rounded = price.round() as i64;
+    let mut s = rounded.to_string();
+    let mut result = String::new();
+
+    while s.len() > 3 {
+        let len = s.len();
+        result = format!(".{}{}", &s[len - 3..], result);
+        s.truncate(len - 3);
+    }
+
+    format!("{}{}", s, result)
+}
+
+/// A price in any other currency, to two decimals
+fn format_other(price: f64, number_format: NumberFormat) -> String {
+    if !price.is_finite() {
+        return "Invalid".to_string();
+    }
+
+    let sign = if price < 0.0 { "-" } else { "" };
+    let abs_price = price.abs();
+
+    match (number_format, abbreviate(abs_price)) {
+        (NumberFormat::Humanized, Some((scaled, suffix))) => {
+            format!("{}{:.2}{}", sign, scaled, suffix)
+        }
+        _ => {
+            let fixed = format!("{:.2}", abs_price);
+            let (integer, fraction) = fixed.split_once('.').unwrap_or((&fixed, "00"));
+            format!("{}{}.{}", sign, group_thousands(integer, ','), fraction)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn currency_codes_pick_the_price_format() {
+        assert_eq!(Currency::from_code("usd"), Currency::Usd);
+        assert_eq!(Currency::from_code("IDR"), Currency::Idr);
+        assert_eq!(
+            Currency::from_code("eur"),
+            Currency::Other("EUR".to_string())
+        );
+
+        let price = 1234.5;
+        assert_eq!(
+            Currency::Usd.format_price(price, NumberFormat::Full),
+            "$1,234.50"
+        );
+        assert_eq!(
+            Currency::Idr.format_price(price, NumberFormat::Full),
+            "Rp1.235"
+        );
+        assert_eq!(
+            Currency::from_code("EUR").format_price(price, NumberFormat::Humanized),
+            "EUR 1.23K"
+        );
+    }
+
+    #[test]
+    fn both_number_formats_write_the_same_value() {
+        let price = 1_234_567.891;
+
+        assert_eq!(format_usd(price, NumberFormat::Humanized), "$1.23M");
+        assert_eq!(format_usd(price, NumberFormat::Full), "$1,234,567.89");
+
+        assert_eq!(format_idr(price, NumberFormat::Humanized), "1,23M");
+        assert_eq!(format_idr(price, NumberFormat::Full), "1.234.568");
+
+        let eur = Currency::from_code("EUR");
+        assert_eq!(
+            eur.format_price(price, NumberFormat::Humanized),
+            "EUR 1.23M"
+        );
+        assert_eq!(
+            eur.format_price(price, NumberFormat::Full),
+            "EUR 1,234,567.89"
+        );
+
+        // Below a thousand there's nothing to abbreviate
+        assert_eq!(
+            eur.format_price(-12.5, NumberFormat::Humanized),
+            eur.format_price(-12.5, NumberFormat::Full)
+        );
+    }
+}
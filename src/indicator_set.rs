@@ -0,0 +1,116 @@
+//! Each market's indicators, kept up to date candle by candle as they
+//! arrive, so drawing a frame only reads them.
+//!
+//! ROC is cheap enough that it's still computed from the candles when drawn.
+
+use crate::settings::Settings;
+use crypto_tracking::{
+    Candle,
+    indicators::{
+        Accumulator, Atr, Bollinger, Ema, Incremental, Indicator, MacdPart, MacdSeries, Obv, Rsi,
+        Sma, Stochastic, StochasticSeries, Vwap, points,
+    },
+};
+
+/// A series an [`IndicatorSet`] keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Study {
+    Sma,
+    /// EMA with the configured period at this index
+    Ema(usize),
+    BollingerLower,
+    BollingerMiddle,
+    BollingerUpper,
+    Rsi,
+    Atr,
+    Obv,
+    Macd(MacdSeries),
+    Stochastic(StochasticSeries),
+    /// The fast and slow SMAs whose crossings are signals
+    CrossFast,
+    CrossSlow,
+    /// Anchored at the session start, however long ago that was
+    Vwap,
+}
+
+#[derive(Debug)]
+pub struct IndicatorSet {
+    indicators: Vec<(Study, Box<dyn Indicator>)>,
+}
+
+impl IndicatorSet {
+    /// Every indicator `settings` configures, before any candles
+    pub fn new(settings: &Settings) -> Self {
+        fn boxed(state: impl Accumulator + 'static) -> Box<dyn Indicator> {
+            Box::new(Incremental::new(state))
+        }
+
+        let (period, width) = (settings.bollinger_period, settings.bollinger_width);
+        let (fast, slow, signal) = settings.macd;
+        let mut indicators = vec![
+            (Study::Sma, boxed(Sma::new(settings.sma_period))),
+            (Study::BollingerLower, boxed(Bollinger::new(period, -width))),
+            (Study::BollingerMiddle, boxed(Bollinger::new(period, 0.0))),
+            (Study::BollingerUpper, boxed(Bollinger::new(period, width))),
+            (Study::Rsi, boxed(Rsi::new(settings.rsi_period))),
+            (Study::Atr, boxed(Atr::new(settings.atr_period))),
+            (Study::Obv, boxed(Obv::new())),
+            (Study::Vwap, boxed(Vwap::new(settings.session_start))),
+        ];
+        for (i, &period) in settings.ema_periods.iter().enumerate() {
+            indicators.push((Study::Ema(i), boxed(Ema::new(period))));
+        }
+        for series in [MacdSeries::Line, MacdSeries::Signal, MacdSeries::Histogram] {
+            indicators.push((
+                Study::Macd(series),
+                boxed(MacdPart::new(fast, slow, signal, series)),
+            ));
+        }
+
+        let (fast, slow) = settings.cross;
+        indicators.push((Study::CrossFast, boxed(Sma::new(fast))));
+        indicators.push((Study::CrossSlow, boxed(Sma::new(slow))));
+
+        let (lookback, smoothing, d) = settings.stochastic;
+        for series in [StochasticSeries::K, StochasticSeries::D] {
+            indicators.push((
+                Study::Stochastic(series),
+                boxed(Stochastic::new(lookback, smoothing, d, series)),
+            ));
+        }
+
+        IndicatorSet { indicators }
+    }
+
+    /// [`IndicatorSet::new`] caught up on `candles`
+    pub fn with_history(settings: &Settings, candles: &[Candle]) -> Self {
+        let mut set = IndicatorSet::new(settings);
+        for candle in candles {
+            set.update(candle, candles.len());
+        }
+        set
+    }
+
+    /// Takes the next candle (see [`Indicator::update`]), keeping values for
+    /// the newest `window` candles
+    pub fn update(&mut self, candle: &Candle, window: usize) {
+        for (_, indicator) in &mut self.indicators {
+            indicator.update(candle);
+            indicator.trim(window);
+        }
+    }
+
+    /// `study` as chart points; empty if it isn't kept
+    pub fn points(&self, study: Study) -> Vec<(f64, f64)> {
+        points(self.series(study))
+    }
+
+    /// `study`'s values, one per candle in the window (NaN during warm-up);
+    /// empty if it isn't kept
+    pub fn series(&self, study: Study) -> &[f64] {
+        self.indicators
+            .iter()
+            .find(|(s, _)| *s == study)
+            .map_or(&[], |(_, indicator)| indicator.series())
+    }
+}
@@ -0,0 +1,1020 @@
+//! Indicator series computed from candles.
+//!
+//! Each series is a list of `(x, value)` points using the candlestick x layout
+//! (candle i at x = i + 0.5), so it can be plotted straight under the candles.
+//! Candles before an indicator has enough history produce no point.
+//!
+//! Most indicators are also [`Accumulator`]s that take one candle at a time,
+//! so a live chart can keep them up to date as an [`Indicator`] per market
+//! instead of going over the whole window on every frame.
+
+use crate::Candle;
+use chrono::{Local, NaiveDate, NaiveTime, TimeZone};
+use std::{collections::VecDeque, fmt};
+
+/// Rate of change: the percent move of the close over the last `period`
+/// candles, `(close_t - close_{t-period}) / close_{t-period} * 100`.
+///
+/// The first `period` candles are warm-up and produce no point, nor does any
+/// candle whose reference close is zero or non-finite.
+pub fn roc(candles: &[Candle], period: usize) -> Vec<(f64, f64)> {
+    if period == 0 {
+        return Vec::new();
+    }
+
+    candles
+        .windows(period + 1)
+        .enumerate()
+        .filter_map(|(i, window)| {
+            let from = window[0].close;
+            let to = window[period].close;
+            let value = (to - from) / from * 100.0;
+
+            value
+                .is_finite()
+                .then_some(((i + period) as f64 + 0.5, value))
+        })
+        .collect()
+}
+
+/// An indicator computed one candle at a time, so a new candle costs one
+/// step rather than a pass over the whole window
+pub trait Indicator: fmt::Debug {
+    /// Takes the next candle. One with the same time as the previous candle
+    /// is a newer version of it, as live feeds send while a candle is open,
+    /// and replaces it.
+    fn update(&mut self, candle: &Candle);
+
+    /// One value per candle taken, oldest first; NaN while warming up
+    fn series(&self) -> &[f64];
+
+    /// Keeps only the newest `len` values, as old candles leave the window
+    fn trim(&mut self, len: usize);
+}
+
+/// The running state behind an indicator
+pub trait Accumulator: Clone + fmt::Debug {
+    /// Takes the next candle and returns the indicator's value at it, NaN
+    /// while warming up
+    fn next(&mut self, candle: &Candle) -> f64;
+}
+
+/// An [`Accumulator`] run as an [`Indicator`]. The state from before the
+/// newest candle is kept, so a newer version of that candle is computed from
+/// where the old one started.
+#[derive(Debug, Clone)]
+pub struct Incremental<A> {
+    state: A,
+    before_newest: A,
+    newest_time: Option<i64>,
+    values: Vec<f64>,
+}
+
+impl<A: Accumulator> Incremental<A> {
+    pub fn new(state: A) -> Self {
+        Incremental {
+            before_newest: state.clone(),
+            state,
+            newest_time: None,
+            values: Vec::new(),
+        }
+    }
+}
+
+impl<A: Accumulator> Indicator for Incremental<A> {
+    fn update(&mut self, candle: &Candle) {
+        if self.newest_time == Some(candle.time) {
+            self.state = self.before_newest.clone();
+            self.values.pop();
+        } else {
+            self.before_newest = self.state.clone();
+            self.newest_time = Some(candle.time);
+        }
+
+        let value = self.state.next(candle);
+        self.values.push(value);
+    }
+
+    fn series(&self) -> &[f64] {
+        &self.values
+    }
+
+    fn trim(&mut self, len: usize) {
+        let skip = self.values.len().saturating_sub(len);
+        self.values.drain(..skip);
+    }
+}
+
+/// An indicator series as `(x, value)` points in the candlestick x layout,
+/// leaving out the warm-up
+pub fn points(series: &[f64]) -> Vec<(f64, f64)> {
+    series
+        .iter()
+        .enumerate()
+        .filter(|(_, value)| value.is_finite())
+        .map(|(i, &value)| (i as f64 + 0.5, value))
+        .collect()
+}
+
+/// `state` run over all of `candles`
+fn run<A: Accumulator>(candles: &[Candle], mut state: A) -> Vec<(f64, f64)> {
+    let series: Vec<f64> = candles.iter().map(|c| state.next(c)).collect();
+    points(&series)
+}
+
+/// Simple moving average of the close over the last `period` candles. The
+/// sum rolls forward a candle at a time instead of re-adding the window.
+#[derive(Debug, Clone)]
+pub struct Sma {
+    period: usize,
+    window: VecDeque<f64>,
+    sum: f64,
+}
+
+impl Sma {
+    pub fn new(period: usize) -> Self {
+        Sma {
+            period,
+            window: VecDeque::with_capacity(period + 1),
+            sum: 0.0,
+        }
+    }
+
+    /// Takes the next value rather than a candle's close
+    fn push(&mut self, value: f64) -> f64 {
+        self.window.push_back(value);
+        self.sum += value;
+        if self.window.len() > self.period {
+            self.sum -= self.window.pop_front().unwrap_or_default();
+        }
+
+        if self.period > 0 && self.window.len() == self.period {
+            self.sum / self.period as f64
+        } else {
+            f64::NAN
+        }
+    }
+}
+
+impl Accumulator for Sma {
+    fn next(&mut self, candle: &Candle) -> f64 {
+        self.push(candle.close)
+    }
+}
+
+/// [`Sma`] over `candles`; the first `period - 1` candles are warm-up
+/// without a point
+pub fn sma(candles: &[Candle], period: usize) -> Vec<(f64, f64)> {
+    run(candles, Sma::new(period))
+}
+
+/// Exponential moving average, weighting each new value by
+/// `2 / (period + 1)`. It starts from the simple average of the first
+/// `period` values, so those are warm-up up to the last of them.
+#[derive(Debug, Clone)]
+pub struct Ema {
+    period: usize,
+    seen: usize,
+    value: f64,
+}
+
+impl Ema {
+    pub fn new(period: usize) -> Self {
+        Ema {
+            period,
+            seen: 0,
+            value: 0.0,
+        }
+    }
+
+    /// Takes the next value rather than a candle's close
+    fn push(&mut self, value: f64) -> f64 {
+        if self.period == 0 {
+            return f64::NAN;
+        }
+
+        self.seen += 1;
+        if self.seen < self.period {
+            self.value += value;
+            f64::NAN
+        } else if self.seen == self.period {
+            self.value = (self.value + value) / self.period as f64;
+            self.value
+        } else {
+            self.value += 2.0 / (self.period as f64 + 1.0) * (value - self.value);
+            self.value
+        }
+    }
+}
+
+impl Accumulator for Ema {
+    fn next(&mut self, candle: &Candle) -> f64 {
+        self.push(candle.close)
+    }
+}
+
+/// [`Ema`] of the close over `candles`
+pub fn ema(candles: &[Candle], period: usize) -> Vec<(f64, f64)> {
+    run(candles, Ema::new(period))
+}
+
+/// Bollinger Bands: a moving average with bands a number of standard
+/// deviations above and below it
+#[derive(Debug, Clone, Default)]
+pub struct Bands {
+    pub lower: Vec<(f64, f64)>,
+    pub middle: Vec<(f64, f64)>,
+    pub upper: Vec<(f64, f64)>,
+}
+
+/// One line of the Bollinger Bands: the [`Sma`] of the close moved by
+/// `offset` population standard deviations of the same closes, so -2, 0 and
+/// 2 give the usual lower, middle and upper lines
+#[derive(Debug, Clone)]
+pub struct Bollinger {
+    sma: Sma,
+    offset: f64,
+}
+
+impl Bollinger {
+    pub fn new(period: usize, offset: f64) -> Self {
+        Bollinger {
+            sma: Sma::new(period),
+            offset,
+        }
+    }
+}
+
+impl Accumulator for Bollinger {
+    fn next(&mut self, candle: &Candle) -> f64 {
+        let middle = self.sma.next(candle);
+        if !middle.is_finite() {
+            return f64::NAN;
+        }
+
+        let window = &self.sma.window;
+        let variance = window
+            .iter()
+            .map(|close| (close - middle).powi(2))
+            .sum::<f64>()
+            / window.len() as f64;
+        middle + self.offset * variance.sqrt()
+    }
+}
+
+/// Bollinger Bands over `candles`, `width` standard deviations either side
+/// of the `period` SMA
+pub fn bollinger(candles: &[Candle], period: usize, width: f64) -> Bands {
+    Bands {
+        lower: run(candles, Bollinger::new(period, -width)),
+        middle: run(candles, Bollinger::new(period, 0.0)),
+        upper: run(candles, Bollinger::new(period, width)),
+    }
+}
+
+/// Relative strength index of the close, 0 to 100, with Wilder's smoothing
+/// of the average gain and loss over `period` candles. A stretch without
+/// any losses reads 100.
+#[derive(Debug, Clone)]
+pub struct Rsi {
+    period: usize,
+    previous_close: Option<f64>,
+    changes: usize,
+    gain: f64,
+    loss: f64,
+}
+
+impl Rsi {
+    pub fn new(period: usize) -> Self {
+        Rsi {
+            period,
+            previous_close: None,
+            changes: 0,
+            gain: 0.0,
+            loss: 0.0,
+        }
+    }
+}
+
+impl Accumulator for Rsi {
+    fn next(&mut self, candle: &Candle) -> f64 {
+        let Some(previous) = self.previous_close.replace(candle.close) else {
+            return f64::NAN;
+        };
+        if self.period == 0 {
+            return f64::NAN;
+        }
+
+        let change = candle.close - previous;
+        let period = self.period as f64;
+        self.changes += 1;
+        if self.changes <= self.period {
+            // The first `period` changes seed plain averages
+            self.gain += change.max(0.0) / period;
+            self.loss += (-change).max(0.0) / period;
+            if self.changes < self.period {
+                return f64::NAN;
+            }
+        } else {
+            self.gain = (self.gain * (period - 1.0) + change.max(0.0)) / period;
+            self.loss = (self.loss * (period - 1.0) + (-change).max(0.0)) / period;
+        }
+
+        if self.loss == 0.0 {
+            100.0
+        } else {
+            100.0 - 100.0 / (1.0 + self.gain / self.loss)
+        }
+    }
+}
+
+/// [`Rsi`] over `candles`; the first `period` candles only seed the averages
+/// and produce no point
+pub fn rsi(candles: &[Candle], period: usize) -> Vec<(f64, f64)> {
+    run(candles, Rsi::new(period))
+}
+
+/// Average true range: Wilder's smoothing over `period` candles of each
+/// candle's true range, its high to low stretched to the previous close when
+/// price gapped past it. In the market's price units.
+#[derive(Debug, Clone)]
+pub struct Atr {
+    period: usize,
+    previous_close: Option<f64>,
+    ranges: usize,
+    average: f64,
+}
+
+impl Atr {
+    pub fn new(period: usize) -> Self {
+        Atr {
+            period,
+            previous_close: None,
+            ranges: 0,
+            average: 0.0,
+        }
+    }
+}
+
+impl Accumulator for Atr {
+    fn next(&mut self, candle: &Candle) -> f64 {
+        let true_range = match self.previous_close.replace(candle.close) {
+            Some(close) => candle.high.max(close) - candle.low.min(close),
+            None => candle.high - candle.low,
+        };
+        if self.period == 0 {
+            return f64::NAN;
+        }
+
+        let period = self.period as f64;
+        self.ranges += 1;
+        if self.ranges <= self.period {
+            // The first `period` ranges seed a plain average
+            self.average += true_range / period;
+            if self.ranges < self.period {
+                return f64::NAN;
+            }
+        } else {
+            self.average = (self.average * (period - 1.0) + true_range) / period;
+        }
+
+        self.average
+    }
+}
+
+/// [`Atr`] over `candles`, from the `period`th candle on
+pub fn atr(candles: &[Candle], period: usize) -> Vec<(f64, f64)> {
+    run(candles, Atr::new(period))
+}
+
+/// On-balance volume: a running total adding each candle's volume when it
+/// closes higher than the one before and subtracting it when lower. Starts at
+/// zero, so only its changes mean anything.
+#[derive(Debug, Clone, Default)]
+pub struct Obv {
+    previous_close: Option<f64>,
+    total: f64,
+}
+
+impl Obv {
+    pub fn new() -> Self {
+        Obv::default()
+    }
+}
+
+impl Accumulator for Obv {
+    fn next(&mut self, candle: &Candle) -> f64 {
+        if let Some(previous) = self.previous_close.replace(candle.close)
+            && candle.volume.is_finite()
+        {
+            if candle.close > previous {
+                self.total += candle.volume;
+            } else if candle.close < previous {
+                self.total -= candle.volume;
+            }
+        }
+        self.total
+    }
+}
+
+/// [`Obv`] over `candles`
+pub fn obv(candles: &[Candle]) -> Vec<(f64, f64)> {
+    run(candles, Obv::new())
+}
+
+/// MACD: the gap between a fast and a slow [`ema`], its own EMA as the
+/// signal line, and the histogram of the two's difference
+#[derive(Debug, Clone, Default)]
+pub struct Macd {
+    pub line: Vec<(f64, f64)>,
+    pub signal: Vec<(f64, f64)>,
+    pub histogram: Vec<(f64, f64)>,
+}
+
+/// Which of the MACD's series a [`MacdPart`] produces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MacdSeries {
+    Line,
+    Signal,
+    Histogram,
+}
+
+/// One series of a MACD with `fast` and `slow` EMA periods and a `signal`
+/// period EMA of the line. The line starts once the slower EMA has warmed
+/// up, and the signal and histogram `signal - 1` candles after that.
+#[derive(Debug, Clone)]
+pub struct MacdPart {
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+    series: MacdSeries,
+}
+
+impl MacdPart {
+    pub fn new(fast: usize, slow: usize, signal: usize, series: MacdSeries) -> Self {
+        MacdPart {
+            fast: Ema::new(fast),
+            slow: Ema::new(slow),
+            signal: Ema::new(signal),
+            series,
+        }
+    }
+}
+
+impl Accumulator for MacdPart {
+    fn next(&mut self, candle: &Candle) -> f64 {
+        let line = self.fast.next(candle) - self.slow.next(candle);
+        if !line.is_finite() {
+            return f64::NAN;
+        }
+
+        let signal = self.signal.push(line);
+        match self.series {
+            MacdSeries::Line => line,
+            MacdSeries::Signal => signal,
+            MacdSeries::Histogram => line - signal,
+        }
+    }
+}
+
+/// [`MacdPart`]s over `candles`
+pub fn macd(candles: &[Candle], fast: usize, slow: usize, signal: usize) -> Macd {
+    let part = |series| run(candles, MacdPart::new(fast, slow, signal, series));
+
+    Macd {
+        line: part(MacdSeries::Line),
+        signal: part(MacdSeries::Signal),
+        histogram: part(MacdSeries::Histogram),
+    }
+}
+
+/// Which of the stochastic oscillator's lines a [`Stochastic`] produces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StochasticSeries {
+    K,
+    D,
+}
+
+/// Stochastic oscillator, 0 to 100: where the close sits in the high–low
+/// range of the last `lookback` candles. %K is that averaged over
+/// `smoothing` candles and %D is %K averaged over `d_period`. A flat range
+/// reads 50.
+#[derive(Debug, Clone)]
+pub struct Stochastic {
+    lookback: usize,
+    ranges: VecDeque<(f64, f64)>,
+    k: Sma,
+    d: Sma,
+    series: StochasticSeries,
+}
+
+impl Stochastic {
+    pub fn new(
+        lookback: usize,
+        smoothing: usize,
+        d_period: usize,
+        series: StochasticSeries,
+    ) -> Self {
+        Stochastic {
+            lookback,
+            ranges: VecDeque::with_capacity(lookback + 1),
+            k: Sma::new(smoothing),
+            d: Sma::new(d_period),
+            series,
+        }
+    }
+}
+
+impl Accumulator for Stochastic {
+    fn next(&mut self, candle: &Candle) -> f64 {
+        self.ranges.push_back((candle.low, candle.high));
+        if self.ranges.len() > self.lookback {
+            self.ranges.pop_front();
+        }
+        if self.lookback == 0 || self.ranges.len() < self.lookback {
+            return f64::NAN;
+        }
+
+        let lowest = self
+            .ranges
+            .iter()
+            .map(|r| r.0)
+            .fold(f64::INFINITY, f64::min);
+        let highest = self
+            .ranges
+            .iter()
+            .map(|r| r.1)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let raw = if highest > lowest {
+            (candle.close - lowest) / (highest - lowest) * 100.0
+        } else {
+            50.0
+        };
+        if !raw.is_finite() {
+            return f64::NAN;
+        }
+
+        let k = self.k.push(raw);
+        if !k.is_finite() {
+            return f64::NAN;
+        }
+        let d = self.d.push(k);
+        match self.series {
+            StochasticSeries::K => k,
+            StochasticSeries::D => d,
+        }
+    }
+}
+
+/// Heikin-Ashi candles, built as raw candles arrive. Each open is the
+/// midpoint of the previous Heikin-Ashi body, so the series carries on from
+/// the first candle seen rather than restarting with the window.
+#[derive(Debug, Clone, Default)]
+pub struct HeikinAshi {
+    /// Open and close of the Heikin-Ashi candle before the newest one
+    before_newest: Option<(f64, f64)>,
+    candles: Vec<Candle>,
+}
+
+impl HeikinAshi {
+    pub fn new() -> Self {
+        HeikinAshi::default()
+    }
+
+    /// Takes the next raw candle, which may be a newer version of the newest
+    /// one, keeping the newest `window` Heikin-Ashi candles
+    pub fn update(&mut self, candle: &Candle, window: usize) {
+        if self
+            .candles
+            .last()
+            .is_some_and(|last| last.time == candle.time)
+        {
+            self.candles.pop();
+        } else {
+            self.before_newest = self.candles.last().map(|last| (last.open, last.close));
+        }
+
+        let close = (candle.open + candle.high + candle.low + candle.close) / 4.0;
+        let open = match self.before_newest {
+            Some((open, close)) => (open + close) / 2.0,
+            None => (candle.open + candle.close) / 2.0,
+        };
+        self.candles.push(Candle {
+            time: candle.time,
+            open,
+            high: candle.high.max(open).max(close),
+            low: candle.low.min(open).min(close),
+            close,
+            volume: candle.volume,
+        });
+
+        let skip = self.candles.len().saturating_sub(window);
+        self.candles.drain(..skip);
+    }
+
+    /// The Heikin-Ashi candles, oldest first
+    pub fn candles(&self) -> &[Candle] {
+        &self.candles
+    }
+}
+
+/// Which way a fast average crossed a slow one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cross {
+    /// The fast average rose above the slow one
+    Golden,
+    /// The fast average fell below the slow one
+    Death,
+}
+
+/// How `fast` crossed `slow` between their last two values, if it did. A
+/// touch that turns back isn't a cross.
+pub fn crossing(fast: &[f64], slow: &[f64]) -> Option<Cross> {
+    let gap = |back: usize| {
+        let gap =
+            fast.get(fast.len().checked_sub(back)?)? - slow.get(slow.len().checked_sub(back)?)?;
+        gap.is_finite().then_some(gap)
+    };
+    let (before, after) = (gap(2)?, gap(1)?);
+
+    if before <= 0.0 && after > 0.0 {
+        Some(Cross::Golden)
+    } else if before >= 0.0 && after < 0.0 {
+        Some(Cross::Death)
+    } else {
+        None
+    }
+}
+
+/// The trading session a candle at `time` is in, named by the day it starts
+/// on when sessions start every day at `session_start`, local time
+pub fn session_day(time: i64, session_start: NaiveTime) -> Option<NaiveDate> {
+    // The day the time falls on once the session start is moved back to
+    // midnight
+    let offset = session_start - NaiveTime::MIN;
+    Local
+        .timestamp_opt(time, 0)
+        .single()
+        .map(|time| (time - offset).date_naive())
+}
+
+/// Volume-weighted average close since the start of the session, starting
+/// over with each new one.
+///
+/// Sessions start every day at `session_start`, local time. Candles without
+/// volume at the start of a session are NaN until some arrives.
+#[derive(Debug, Clone)]
+pub struct Vwap {
+    session_start: NaiveTime,
+    session: Option<NaiveDate>,
+    traded: f64,
+    volume: f64,
+}
+
+impl Vwap {
+    pub fn new(session_start: NaiveTime) -> Self {
+        Vwap {
+            session_start,
+            session: None,
+            traded: 0.0,
+            volume: 0.0,
+        }
+    }
+}
+
+impl Accumulator for Vwap {
+    fn next(&mut self, candle: &Candle) -> f64 {
+        let session = session_day(candle.time, self.session_start);
+        if session != self.session {
+            self.session = session;
+            (self.traded, self.volume) = (0.0, 0.0);
+        }
+
+        self.traded += candle.close * candle.volume;
+        self.volume += candle.volume;
+        self.traded / self.volume
+    }
+}
+
+/// `series`, one value per candle of `candles`, as points split into one
+/// list per session so a line drawn from them breaks where it resets
+pub fn session_points(
+    series: &[f64],
+    candles: &[Candle],
+    session_start: NaiveTime,
+) -> Vec<Vec<(f64, f64)>> {
+    let mut sessions: Vec<Vec<(f64, f64)>> = Vec::new();
+    let mut current = None;
+
+    for (i, (value, candle)) in series.iter().zip(candles).enumerate() {
+        let session = session_day(candle.time, session_start);
+        if session != current || sessions.is_empty() {
+            current = session;
+            sessions.push(Vec::new());
+        }
+        if value.is_finite()
+            && let Some(points) = sessions.last_mut()
+        {
+            points.push((i as f64 + 0.5, *value));
+        }
+    }
+
+    sessions.retain(|points| !points.is_empty());
+    sessions
+}
+
+/// [`Vwap`] over `candles`, split by [`session_points`]. Sessions begun
+/// before the first candle start from it.
+pub fn vwap(candles: &[Candle], session_start: NaiveTime) -> Vec<Vec<(f64, f64)>> {
+    let mut state = Vwap::new(session_start);
+    let series: Vec<f64> = candles.iter().map(|c| state.next(c)).collect();
+    session_points(&series, candles, session_start)
+}
+
+/// Realized volatility: the sample standard deviation of close-to-close
+/// returns, in percent. Returns 0 with fewer than two returns to compare.
+pub fn realized_vol(candles: &[Candle]) -> f64 {
+    let returns: Vec<f64> = candles
+        .windows(2)
+        .map(|w| (w[1].close - w[0].close) / w[0].close * 100.0)
+        .filter(|r| r.is_finite())
+        .collect();
+
+    if returns.len() < 2 {
+        return 0.0;
+    }
+
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    let variance =
+        returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+
+    variance.sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(time: i64, close: f64, volume: f64) -> Candle {
+        Candle {
+            time,
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume,
+        }
+    }
+
+    #[test]
+    fn vwap_starts_over_at_the_session_boundary() {
+        let midnight = Local
+            .with_ymd_and_hms(2024, 1, 2, 0, 0, 0)
+            .single()
+            .unwrap()
+            .timestamp();
+        let mut state = Incremental::new(Vwap::new(NaiveTime::MIN));
+
+        state.update(&candle(midnight - 120, 10.0, 1.0));
+        state.update(&candle(midnight - 60, 20.0, 3.0));
+        state.update(&candle(midnight, 30.0, 1.0));
+        // A newer version of the first candle of the session
+        state.update(&candle(midnight, 40.0, 1.0));
+
+        assert_eq!(state.series(), &[10.0, 17.5, 40.0]);
+    }
+
+    /// One candle a minute closing at each of `closes`
+    fn closing_at(closes: &[f64]) -> Vec<Candle> {
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, &close)| candle(i as i64 * 60, close, 1.0))
+            .collect()
+    }
+
+    fn assert_points_close(actual: &[(f64, f64)], expected: &[(f64, f64)]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?} vs {expected:?}");
+        for (a, e) in actual.iter().zip(expected) {
+            assert_eq!(a.0, e.0, "{actual:?} vs {expected:?}");
+            assert!((a.1 - e.1).abs() < 1e-9, "{actual:?} vs {expected:?}");
+        }
+    }
+
+    fn bar(time: i64, open: f64, high: f64, low: f64, close: f64) -> Candle {
+        Candle {
+            time,
+            open,
+            high,
+            low,
+            close,
+            volume: 1.0,
+        }
+    }
+
+    fn assert_series_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?} vs {expected:?}");
+        for (a, e) in actual.iter().zip(expected) {
+            let same = (a.is_nan() && e.is_nan()) || (a - e).abs() < 1e-9;
+            assert!(same, "{actual:?} vs {expected:?}");
+        }
+    }
+
+    /// `state` over `candles`, one value per candle
+    fn series<A: Accumulator>(candles: &[Candle], mut state: A) -> Vec<f64> {
+        candles.iter().map(|c| state.next(c)).collect()
+    }
+
+    const NAN: f64 = f64::NAN;
+
+    #[test]
+    fn sma_and_ema_match_hand_worked_values() {
+        let candles = closing_at(&[2.0, 4.0, 6.0, 8.0, 4.0]);
+
+        assert_eq!(sma(&candles, 3), vec![(2.5, 4.0), (3.5, 6.0), (4.5, 6.0)]);
+        // Seeded with the average of the first three, then weighted by 2 / 4
+        assert_eq!(ema(&candles, 3), vec![(2.5, 4.0), (3.5, 6.0), (4.5, 5.0)]);
+        assert!(sma(&candles, 0).is_empty());
+        assert!(ema(&candles, 6).is_empty());
+    }
+
+    #[test]
+    fn rsi_matches_hand_worked_values() {
+        // Changes of +2, -1, +2 and 0: averages seed at a gain of 1 and a
+        // loss of 0.5, then smooth to 1.5 and 0.25, then 0.75 and 0.125
+        let candles = closing_at(&[10.0, 12.0, 11.0, 13.0, 13.0]);
+        assert_series_close(
+            &series(&candles, Rsi::new(2)),
+            &[
+                NAN,
+                NAN,
+                100.0 - 100.0 / 3.0,
+                100.0 - 100.0 / 7.0,
+                100.0 - 100.0 / 7.0,
+            ],
+        );
+
+        let rising = closing_at(&[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(rsi(&rising, 2), vec![(2.5, 100.0), (3.5, 100.0)]);
+    }
+
+    #[test]
+    fn atr_matches_hand_worked_values() {
+        // True ranges of 2, 2 (stretched to the previous close of 10), 4
+        // (from a gap up past 11) and 2
+        let candles = [
+            bar(0, 10.0, 11.0, 9.0, 10.0),
+            bar(60, 10.0, 12.0, 10.0, 11.0),
+            bar(120, 13.0, 15.0, 13.0, 14.0),
+            bar(180, 14.0, 14.0, 12.0, 13.0),
+        ];
+
+        assert_eq!(atr(&candles, 2), vec![(1.5, 2.0), (2.5, 3.0), (3.5, 2.5)]);
+    }
+
+    #[test]
+    fn macd_matches_hand_worked_values() {
+        // A fast period of 1 follows the close; the slow EMA(2) seeds at 2.5
+        // and weighs each close by 2 / 3 after that
+        let candles = closing_at(&[1.0, 4.0, 7.0, 4.0]);
+        let macd = macd(&candles, 1, 2, 2);
+
+        assert_points_close(&macd.line, &[(1.5, 1.5), (2.5, 1.5), (3.5, -0.5)]);
+        assert_points_close(&macd.signal, &[(2.5, 1.5), (3.5, 1.0 / 6.0)]);
+        assert_points_close(&macd.histogram, &[(2.5, 0.0), (3.5, -2.0 / 3.0)]);
+    }
+
+    #[test]
+    fn stochastic_matches_hand_worked_values() {
+        // Over two candles the close sits at 75%, 25% and 1/6 of the range
+        let candles = [
+            bar(0, 9.0, 10.0, 8.0, 9.0),
+            bar(60, 9.0, 12.0, 9.0, 11.0),
+            bar(120, 11.0, 13.0, 10.0, 10.0),
+            bar(180, 10.0, 11.0, 7.0, 8.0),
+        ];
+
+        assert_series_close(
+            &series(&candles, Stochastic::new(2, 1, 2, StochasticSeries::K)),
+            &[NAN, 75.0, 25.0, 100.0 / 6.0],
+        );
+        assert_series_close(
+            &series(&candles, Stochastic::new(2, 1, 2, StochasticSeries::D)),
+            &[NAN, NAN, 50.0, (25.0 + 100.0 / 6.0) / 2.0],
+        );
+
+        let flat = closing_at(&[5.0, 5.0]);
+        assert_eq!(
+            series(&flat, Stochastic::new(2, 1, 1, StochasticSeries::K))[1],
+            50.0
+        );
+    }
+
+    #[test]
+    fn heikin_ashi_matches_hand_worked_values() {
+        let mut heikin_ashi = HeikinAshi::new();
+        heikin_ashi.update(&bar(0, 10.0, 12.0, 9.0, 11.0), 10);
+        heikin_ashi.update(&bar(60, 11.0, 14.0, 10.0, 13.0), 10);
+
+        assert_eq!(
+            heikin_ashi.candles(),
+            &[
+                bar(0, 10.5, 12.0, 9.0, 10.5),
+                bar(60, 10.5, 14.0, 10.0, 12.0)
+            ]
+        );
+
+        // A newer version of the last candle still opens off the first
+        heikin_ashi.update(&bar(60, 11.0, 15.0, 10.0, 14.0), 1);
+        assert_eq!(heikin_ashi.candles(), &[bar(60, 10.5, 15.0, 10.0, 12.5)]);
+    }
+
+    #[test]
+    fn crossing_needs_the_gap_to_change_sign() {
+        assert_eq!(crossing(&[1.0, 3.0], &[2.0, 2.0]), Some(Cross::Golden));
+        assert_eq!(crossing(&[2.0, 3.0], &[2.0, 2.0]), Some(Cross::Golden));
+        assert_eq!(crossing(&[3.0, 1.0], &[2.0, 2.0]), Some(Cross::Death));
+
+        // Touching without crossing, staying on one side, or too little data
+        assert_eq!(crossing(&[1.0, 2.0], &[2.0, 2.0]), None);
+        assert_eq!(crossing(&[3.0, 4.0], &[2.0, 2.0]), None);
+        assert_eq!(crossing(&[NAN, 3.0], &[2.0, 2.0]), None);
+        assert_eq!(crossing(&[3.0], &[2.0]), None);
+    }
+
+    /// Candles that wander up and down, each sent twice as a live feed
+    /// would: first while still open, then closed
+    fn live_feed() -> Vec<Candle> {
+        (0..60)
+            .flat_map(|i| {
+                let open = 100.0 + (i as f64 * 0.7).sin() * 5.0 + i as f64 * 0.1;
+                let close = open + (i as f64 * 1.3).cos() * 2.0;
+                let (high, low) = (open.max(close) + 1.0, open.min(close) - 0.5);
+                let time = i * 60;
+                [
+                    bar(time, open, open + 0.5, open - 0.5, open + 0.2),
+                    Candle {
+                        volume: 1.0 + (i % 7) as f64,
+                        ..bar(time, open, high, low, close)
+                    },
+                ]
+            })
+            .collect()
+    }
+
+    fn assert_incremental_matches_batch<A: Accumulator>(state: A) {
+        let feed = live_feed();
+        let closed: Vec<Candle> = feed.iter().skip(1).step_by(2).cloned().collect();
+
+        let mut incremental = Incremental::new(state.clone());
+        for candle in &feed {
+            incremental.update(candle);
+        }
+        let batch = series(&closed, state);
+
+        assert_eq!(incremental.series().len(), batch.len());
+        for (a, b) in incremental.series().iter().zip(&batch) {
+            assert_eq!(a.to_bits(), b.to_bits(), "{a} vs {b}");
+        }
+
+        incremental.trim(10);
+        assert_eq!(incremental.series(), &batch[batch.len() - 10..]);
+    }
+
+    #[test]
+    fn incremental_updates_match_a_batch_recompute() {
+        assert_incremental_matches_batch(Sma::new(5));
+        assert_incremental_matches_batch(Ema::new(5));
+        assert_incremental_matches_batch(Bollinger::new(5, 2.0));
+        assert_incremental_matches_batch(Rsi::new(14));
+        assert_incremental_matches_batch(Atr::new(14));
+        assert_incremental_matches_batch(Obv::new());
+        assert_incremental_matches_batch(MacdPart::new(12, 26, 9, MacdSeries::Histogram));
+        assert_incremental_matches_batch(Stochastic::new(14, 3, 3, StochasticSeries::D));
+        assert_incremental_matches_batch(Vwap::new(NaiveTime::MIN));
+    }
+
+    #[test]
+    fn roc_of_a_linear_series_shrinks_as_the_base_grows() {
+        let candles = closing_at(&[100.0, 110.0, 120.0, 130.0, 140.0]);
+
+        assert_points_close(
+            &roc(&candles, 2),
+            &[(2.5, 20.0), (3.5, 200.0 / 11.0), (4.5, 50.0 / 3.0)],
+        );
+        assert!(roc(&candles, 5).is_empty());
+    }
+
+    #[test]
+    fn realized_vol_of_known_returns() {
+        // Doubling every candle is a steady 100% return, with no spread
+        assert_eq!(realized_vol(&closing_at(&[1.0, 2.0, 4.0, 8.0, 16.0])), 0.0);
+
+        // Returns of 100%, -50% and 100%: mean 50, squared deviations summing
+        // to 15000 over two degrees of freedom
+        let candles = closing_at(&[100.0, 200.0, 100.0, 200.0]);
+        assert_eq!(realized_vol(&candles), 7500f64.sqrt());
+
+        assert_eq!(realized_vol(&candles[..2]), 0.0);
+    }
+}
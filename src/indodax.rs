@@ -0,0 +1,209 @@
+//! Rupiah candles built from Indodax's public trade history.
+//!
+//! Indodax's public API has no candles, so trades are polled and grouped into
+//! one-minute candles here. Only IDR markets have Indodax pairs (`IDR/BTC` is
+//! `btcidr`); other markets receive no candles from this feed.
+
+use crate::{
+    net::Network,
+    source::{MarketDataSource, Message},
+};
+use crypto_tracking::Candle;
+use serde_json::Value;
+use std::{sync::mpsc, thread, time::Duration};
+use ureq::Agent;
+
+const TRADES_URL: &str = "https://indodax.com/api/trades";
+
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Indodax when its API answers, otherwise `fallback`, so IDR markets keep
+/// moving when the exchange can't be reached
+pub fn indodax_or(
+    network: Network,
+    fallback: Box<dyn MarketDataSource>,
+) -> Box<dyn MarketDataSource> {
+    match fetch_trades(&network.agent(), "btcidr") {
+        Ok(_) => Box::new(IndodaxFeed { network }),
+        Err(e) => {
+            eprintln!(
+                "Warning: Indodax unavailable ({}), simulating IDR markets",
+                e
+            );
+            fallback
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct IndodaxFeed {
+    network: Network,
+}
+
+impl MarketDataSource for IndodaxFeed {
+    fn subscribe(&self, markets: &[String]) -> mpsc::Receiver<Message> {
+        let (tx, rx) = mpsc::channel();
+        let pairs: Vec<(String, String)> = markets
+            .iter()
+            .filter_map(|m| Some((m.clone(), indodax_pair(m)?)))
+            .collect();
+        let agent = self.network.agent();
+
+        thread::spawn(move || {
+            // Newest trade seen and the candle it went into, per market
+            let mut progress: Vec<Option<(u64, Candle)>> = vec![None; pairs.len()];
+
+            loop {
+                for ((market, pair), progress) in pairs.iter().zip(&mut progress) {
+                    // A failed poll is retried on the next round rather than
+                    // ending the feed
+                    let Ok(trades) = fetch_trades(&agent, pair) else {
+                        continue;
+                    };
+
+                    // The first poll starts at the newest minute, as anything
+                    // older is history
+                    let (last_tid, mut current) = match progress.take() {
+                        Some((tid, candle)) => (tid, Some(candle)),
+                        None => {
+                            let Some(newest) = trades.last() else {
+                                continue;
+                            };
+                            let minute = minute_of(newest.time);
+                            let first = trades.iter().find(|t| minute_of(t.time) == minute);
+                            (first.map_or(newest.tid, |t| t.tid.saturating_sub(1)), None)
+                        }
+                    };
+
+                    for trade in trades.iter().filter(|t| t.tid > last_tid) {
+                        match current.as_mut() {
+                            Some(candle) if candle.time == minute_of(trade.time) => {
+                                trade.add_to(candle)
+                            }
+                            Some(candle) if candle.time > minute_of(trade.time) => {}
+                            _ => {
+                                // A new minute finishes the previous candle
+                                if let Some(finished) = current.replace(trade.candle())
+                                    && tx
+                                        .send(Message::NewCandle(market.clone(), finished))
+                                        .is_err()
+                                {
+                                    return;
+                                }
+                            }
+                        }
+                    }
+                    let last_tid = trades.last().map_or(last_tid, |t| t.tid.max(last_tid));
+
+                    if let Some(candle) = &current
+                        && tx
+                            .send(Message::NewCandle(market.clone(), candle.clone()))
+                            .is_err()
+                    {
+                        return;
+                    }
+                    *progress = current.map(|candle| (last_tid, candle));
+                }
+
+                if tx.send(Message::Heartbeat).is_err() {
+                    return;
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+
+        rx
+    }
+
+    /// Candles for the minutes covered by the recent trades Indodax returns,
+    /// so quiet minutes show up as gaps
+    fn fetch_history(&self, market: &str, limit: usize) -> Result<Vec<Candle>, String> {
+        let Some(pair) = indodax_pair(market) else {
+            return Ok(Vec::new());
+        };
+
+        let trades = fetch_trades(&self.network.agent(), &pair)
+            .map_err(|e| format!("fetching {} history: {}", market, e))?;
+        let mut candles: Vec<Candle> = Vec::new();
+        for trade in &trades {
+            match candles.last_mut() {
+                Some(candle) if candle.time == minute_of(trade.time) => trade.add_to(candle),
+                _ => candles.push(trade.candle()),
+            }
+        }
+
+        let skip = candles.len().saturating_sub(limit);
+        Ok(candles.into_iter().skip(skip).collect())
+    }
+}
+
+struct Trade {
+    /// Seconds since the epoch
+    time: i64,
+    price: f64,
+    amount: f64,
+    /// Trade id, increasing over time
+    tid: u64,
+}
+
+impl Trade {
+    /// A candle for this trade's minute, opened by this trade
+    fn candle(&self) -> Candle {
+        Candle {
+            time: minute_of(self.time),
+            open: self.price,
+            high: self.price,
+            low: self.price,
+            close: self.price,
+            volume: self.amount,
+        }
+    }
+
+    /// Extends `candle` with this later trade in the same minute
+    fn add_to(&self, candle: &mut Candle) {
+        candle.high = candle.high.max(self.price);
+        candle.low = candle.low.min(self.price);
+        candle.close = self.price;
+        candle.volume += self.amount;
+    }
+}
+
+/// The Indodax pair for a market, e.g. `btcidr` for `IDR/BTC`
+fn indodax_pair(market: &str) -> Option<String> {
+    match market.split_once('/')? {
+        ("IDR", base) => Some(format!("{}idr", base.to_lowercase())),
+        _ => None,
+    }
+}
+
+fn minute_of(time: i64) -> i64 {
+    time - time.rem_euclid(60)
+}
+
+/// Recent trades for `pair`, oldest first. Indodax sends every field as a
+/// string.
+fn fetch_trades(agent: &Agent, pair: &str) -> Result<Vec<Trade>, String> {
+    let url = format!("{}/{}", TRADES_URL, pair);
+    let body = agent
+        .get(&url)
+        .call()
+        .and_then(|mut response| response.body_mut().read_to_string())
+        .map_err(|e| e.to_string())?;
+    let rows: Vec<Value> = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+
+    let field = |row: &Value, name: &str| row[name].as_str()?.parse::<f64>().ok();
+    let mut trades: Vec<Trade> = rows
+        .iter()
+        .filter_map(|row| {
+            Some(Trade {
+                time: row["date"].as_str()?.parse().ok()?,
+                price: field(row, "price")?,
+                amount: field(row, "amount")?,
+                tid: row["tid"].as_str()?.parse().ok()?,
+            })
+        })
+        .collect();
+    trades.sort_by_key(|t| t.tid);
+
+    Ok(trades)
+}
@@ -0,0 +1,326 @@
+//! Horizontal price levels: support and resistance found in the candles'
+//! swing highs and lows, and pivot points from the previous session.
+//!
+//! A swing high is a high no candle within [`SWING_REACH`] on either side
+//! tops, and a swing low the same for lows. Swings close enough together
+//! are merged into one level, and each swing merged in counts as a touch.
+//! Levels are computed from whatever window is passed, so they follow a
+//! rolling window as it advances.
+//!
+//! Pivots need a whole session, which is usually longer than the window, so
+//! a [`SessionTracker`] follows sessions as candles arrive instead.
+//!
+//! Fibonacci retracements come from a swing the user picks, see
+//! [`retracement`].
+
+use crate::{Candle, indicators::session_day};
+use chrono::{NaiveDate, NaiveTime};
+
+/// Candles on each side a swing has to stand out from
+pub const SWING_REACH: usize = 2;
+
+/// Most levels returned, strongest first
+const MAX_LEVELS: usize = 6;
+
+/// A price the candles turned at more than once
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Level {
+    /// Average of the swings merged into it
+    pub price: f64,
+    /// How many swings turned at it
+    pub touches: usize,
+}
+
+/// Levels with at least two touches in `candles`, ordered by price. Swings
+/// within half the average candle range of each other merge into one level.
+pub fn support_resistance(candles: &[Candle]) -> Vec<Level> {
+    let candles: Vec<&Candle> = candles
+        .iter()
+        .filter(|c| c.high.is_finite() && c.low.is_finite())
+        .collect();
+    if candles.len() < SWING_REACH * 2 + 1 {
+        return Vec::new();
+    }
+
+    let tolerance =
+        candles.iter().map(|c| c.high - c.low).sum::<f64>() / candles.len() as f64 * 0.5;
+
+    let mut swings = Vec::new();
+    for i in SWING_REACH..candles.len() - SWING_REACH {
+        let neighbours = || {
+            candles[i - SWING_REACH..=i + SWING_REACH]
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != SWING_REACH)
+                .map(|(_, c)| c)
+        };
+        if neighbours().all(|c| c.high < candles[i].high) {
+            swings.push(candles[i].high);
+        }
+        if neighbours().all(|c| c.low > candles[i].low) {
+            swings.push(candles[i].low);
+        }
+    }
+    swings.sort_by(f64::total_cmp);
+
+    // Each cluster starts at its lowest swing and takes the ones above it
+    // until the gap from that start grows past the tolerance
+    let mut levels: Vec<Level> = Vec::new();
+    let mut cluster: Vec<f64> = Vec::new();
+    for swing in swings {
+        if cluster
+            .first()
+            .is_some_and(|&start| swing - start > tolerance)
+        {
+            levels.push(merge(&cluster));
+            cluster.clear();
+        }
+        cluster.push(swing);
+    }
+    if !cluster.is_empty() {
+        levels.push(merge(&cluster));
+    }
+
+    levels.retain(|level| level.touches >= 2);
+    levels.sort_by_key(|level| std::cmp::Reverse(level.touches));
+    levels.truncate(MAX_LEVELS);
+    levels.sort_by(|a, b| a.price.total_cmp(&b.price));
+    levels
+}
+
+fn merge(swings: &[f64]) -> Level {
+    Level {
+        price: swings.iter().sum::<f64>() / swings.len() as f64,
+        touches: swings.len(),
+    }
+}
+
+/// Classic floor pivot points from one session's high, low and close
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pivots {
+    pub pivot: f64,
+    pub r1: f64,
+    pub r2: f64,
+    pub r3: f64,
+    pub s1: f64,
+    pub s2: f64,
+    pub s3: f64,
+}
+
+impl Pivots {
+    pub fn classic(high: f64, low: f64, close: f64) -> Self {
+        let pivot = (high + low + close) / 3.0;
+        let range = high - low;
+        Pivots {
+            pivot,
+            r1: 2.0 * pivot - low,
+            r2: pivot + range,
+            r3: high + 2.0 * (pivot - low),
+            s1: 2.0 * pivot - high,
+            s2: pivot - range,
+            s3: low - 2.0 * (high - pivot),
+        }
+    }
+
+    /// Every level with its name, highest first
+    pub fn levels(&self) -> [(&'static str, f64); 7] {
+        [
+            ("R3", self.r3),
+            ("R2", self.r2),
+            ("R1", self.r1),
+            ("P", self.pivot),
+            ("S1", self.s1),
+            ("S2", self.s2),
+            ("S3", self.s3),
+        ]
+    }
+}
+
+/// High, low and close of a session so far
+#[derive(Debug, Clone, Copy)]
+struct SessionRange {
+    day: NaiveDate,
+    high: f64,
+    low: f64,
+    close: f64,
+    /// Whether the session's first candles were seen, rather than it being
+    /// underway when the first candle arrived
+    whole: bool,
+}
+
+/// Follows a market's sessions candle by candle, keeping the range of the
+/// last finished one for its [`Pivots`]
+#[derive(Debug, Clone)]
+pub struct SessionTracker {
+    session_start: NaiveTime,
+    current: Option<SessionRange>,
+    previous: Option<SessionRange>,
+}
+
+impl SessionTracker {
+    /// Sessions start every day at `session_start`, local time
+    pub fn new(session_start: NaiveTime) -> Self {
+        SessionTracker {
+            session_start,
+            current: None,
+            previous: None,
+        }
+    }
+
+    /// Takes the next candle, which may be an update of the newest one.
+    /// Candles from a session before the current one are ignored.
+    pub fn update(&mut self, candle: &Candle) {
+        let Some(day) = session_day(candle.time, self.session_start) else {
+            return;
+        };
+        if !(candle.high.is_finite() && candle.low.is_finite() && candle.close.is_finite()) {
+            return;
+        }
+
+        match &mut self.current {
+            Some(current) if current.day == day => {
+                current.high = current.high.max(candle.high);
+                current.low = current.low.min(candle.low);
+                current.close = candle.close;
+            }
+            Some(current) if current.day > day => {}
+            _ => {
+                let finished = self.current.replace(SessionRange {
+                    day,
+                    high: candle.high,
+                    low: candle.low,
+                    close: candle.close,
+                    whole: self.current.is_some(),
+                });
+                if let Some(finished) = finished.filter(|s| s.whole) {
+                    self.previous = Some(finished);
+                }
+            }
+        }
+    }
+
+    /// Pivots from the last finished session; `None` until one has been
+    /// seen from start to end, which takes history back past the start of
+    /// the previous session
+    pub fn pivots(&self) -> Option<Pivots> {
+        self.previous
+            .map(|session| Pivots::classic(session.high, session.low, session.close))
+    }
+}
+
+/// Fibonacci retracement ratios, from the end of a move back to its start
+pub const FIB_RATIOS: [f64; 7] = [0.0, 0.236, 0.382, 0.5, 0.618, 0.786, 1.0];
+
+/// Retracement levels of a move from `from` to `to` as `(ratio, price)`,
+/// with ratio 0 at `to` and 1 back at `from`
+pub fn retracement(from: f64, to: f64) -> [(f64, f64); 7] {
+    FIB_RATIOS.map(|ratio| (ratio, to - (to - from) * ratio))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Local, TimeZone};
+
+    fn bar(time: i64, high: f64, low: f64, close: f64) -> Candle {
+        Candle {
+            time,
+            open: close,
+            high,
+            low,
+            close,
+            volume: 1.0,
+        }
+    }
+
+    #[test]
+    fn swings_turning_at_the_same_price_make_a_level() {
+        // Swing highs at 110 and 111, swing lows at 90 and 91
+        let ranges = [
+            (100.0, 95.0),
+            (102.0, 96.0),
+            (110.0, 98.0),
+            (103.0, 96.0),
+            (101.0, 90.0),
+            (104.0, 95.0),
+            (111.0, 97.0),
+            (103.0, 96.0),
+            (100.0, 91.0),
+            (102.0, 95.0),
+            (101.0, 96.0),
+        ];
+        let candles: Vec<Candle> = ranges
+            .iter()
+            .enumerate()
+            .map(|(i, &(high, low))| bar(i as i64 * 60, high, low, (high + low) / 2.0))
+            .collect();
+
+        assert_eq!(
+            support_resistance(&candles),
+            vec![
+                Level {
+                    price: 90.5,
+                    touches: 2
+                },
+                Level {
+                    price: 110.5,
+                    touches: 2
+                },
+            ]
+        );
+        assert!(support_resistance(&candles[..4]).is_empty());
+    }
+
+    #[test]
+    fn classic_pivots_match_the_floor_formulas() {
+        assert_eq!(
+            Pivots::classic(110.0, 90.0, 100.0),
+            Pivots {
+                pivot: 100.0,
+                r1: 110.0,
+                r2: 120.0,
+                r3: 130.0,
+                s1: 90.0,
+                s2: 80.0,
+                s3: 70.0,
+            }
+        );
+    }
+
+    #[test]
+    fn pivots_come_from_the_last_whole_session() {
+        let at = |day, hour| {
+            Local
+                .with_ymd_and_hms(2024, 3, day, hour, 0, 0)
+                .single()
+                .unwrap()
+                .timestamp()
+        };
+        let mut tracker = SessionTracker::new(NaiveTime::MIN);
+
+        // Joined partway through the 4th, so it has no whole session yet
+        tracker.update(&bar(at(4, 12), 500.0, 1.0, 250.0));
+        tracker.update(&bar(at(5, 0), 110.0, 95.0, 100.0));
+        tracker.update(&bar(at(5, 12), 105.0, 90.0, 104.0));
+        assert_eq!(tracker.pivots(), None);
+
+        // A newer version of the 5th's last candle, then the 6th begins
+        tracker.update(&bar(at(5, 12), 105.0, 90.0, 100.0));
+        tracker.update(&bar(at(6, 0), 120.0, 119.0, 119.5));
+        assert_eq!(tracker.pivots(), Some(Pivots::classic(110.0, 90.0, 100.0)));
+
+        // Late candles from a finished session change nothing
+        tracker.update(&bar(at(5, 13), 300.0, 1.0, 2.0));
+        assert_eq!(tracker.pivots(), Some(Pivots::classic(110.0, 90.0, 100.0)));
+    }
+
+    #[test]
+    fn retracements_run_from_the_end_of_the_move_back_to_its_start() {
+        let levels = retracement(100.0, 200.0);
+
+        assert_eq!(levels[0], (0.0, 200.0));
+        assert_eq!(levels[3], (0.5, 150.0));
+        assert_eq!(levels[6], (1.0, 100.0));
+        assert!((levels[4].1 - 138.2).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,44 @@
+//! Candlestick and volume panels for ratatui, plus the formatting helpers
+//! behind the `crypto_tracking` terminal app.
+
+pub mod chart;
+pub mod format;
+pub mod indicators;
+pub mod levels;
+pub mod patterns;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candle {
+    pub time: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+/// Which side took liquidity in a trade
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+/// One executed trade
+#[derive(Debug, Clone)]
+pub struct Trade {
+    pub time: i64,
+    pub price: f64,
+    pub size: f64,
+    pub side: Side,
+}
+
+/// A snapshot of resting orders around the current price, as `(price, size)`
+/// levels
+#[derive(Debug, Clone, Default)]
+pub struct OrderBook {
+    /// Best (highest) bid first
+    pub bids: Vec<(f64, f64)>,
+    /// Best (lowest) ask first
+    pub asks: Vec<(f64, f64)>,
+}
@@ -1,499 +1,3052 @@
-use chrono::{DateTime, Local, TimeZone};
+mod binance;
+mod browser;
+mod cache;
+mod coinbase;
+mod composite;
+mod controls;
+mod demo;
+mod derivatives;
+mod form;
+mod indicator_set;
+mod indodax;
+mod net;
+mod plugin;
+mod recorder;
+mod replay;
+mod scripts;
+mod settings;
+mod simulator;
+mod source;
+mod supervisor;
+mod view;
+
+use browser::MarketBrowser;
+use cache::{Cached, cache_dir};
+use composite::Composite;
+use controls::{Panels, Popups, Selection};
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
     execute,
+    style::{self, Stylize},
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
-use rand::Rng;
+use crypto_tracking::{
+    Candle, OrderBook, Trade,
+    chart::{
+        Band, CandleStyle, CandlestickOptions, CompactCandles, Comparison, Crosshair, Marker,
+        Overlay, PriceLine, SeriesPane, Theme, comparison_series, ratio_series, render_atr_chart,
+        render_candlestick_chart, render_depth_chart, render_macd_chart, render_pattern_log,
+        render_ratio_chart, render_roc_chart, render_rsi_chart, render_series_pane,
+        render_signal_log, render_stochastic_chart, render_trade_tape, render_volume_chart,
+        snapped_price_bounds,
+    },
+    format::{Currency, NumberFormat, format_clock, format_idr, format_time, format_usd},
+    indicators::{
+        Cross, HeikinAshi, Macd, MacdSeries, StochasticSeries, crossing, realized_vol, roc,
+        session_points,
+    },
+    levels::{SessionTracker, retracement, support_resistance},
+    patterns::{Pattern, detect},
+};
+use demo::{Action, DemoScript};
+use derivatives::{PerpStats, WithDerivatives};
+use form::Form;
+use indicator_set::{IndicatorSet, Study};
+use net::Network;
+use plugin::FeedPlugin;
 use ratatui::{
-    Terminal,
+    Frame, Terminal,
     backend::CrosstermBackend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    symbols,
     text::{Line, Span},
-    widgets::{
-        Axis, Block, Borders, Chart, Dataset, GraphType, Paragraph,
-        canvas::{Canvas, Line as CanvasLine, Rectangle},
-    },
+    widgets::{Block, Borders, Clear, GraphType, List, ListItem, ListState, Paragraph, Wrap},
 };
+use recorder::Recorder;
+use replay::CsvReplay;
+use scripts::{ScriptOutput, Scripts};
+use settings::{Settings, SettingsFile};
+use simulator::{PriceModel, SimulatorConfig};
+use source::{FeedStatus, MarketDataSource, Message, Routed, bounded};
 use std::{
     collections::HashMap,
-    io,
+    io::{self, Write},
     sync::mpsc,
     thread,
     time::{Duration, Instant},
 };
+use supervisor::Supervised;
+use view::{ChartView, View};
 
-#[derive(Debug, Clone)]
-struct Candle {
-    time: i64,
-    open: f64,
-    high: f64,
-    low: f64,
-    close: f64,
-    volume: f64,
+/// Overall screen layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ViewMode {
+    /// Markets sidebar next to the charts
+    Normal,
+    /// Charts for the selected market fill the screen, sidebar hidden
+    Detail,
 }
 
-enum Message {
-    NewCandle(String, Candle),
-    Quit,
+impl ViewMode {
+    fn toggle(self) -> Self {
+        match self {
+            ViewMode::Normal => ViewMode::Detail,
+            ViewMode::Detail => ViewMode::Normal,
+        }
+    }
 }
 
-fn main() -> Result<(), io::Error> {
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+/// Optional studies drawn on or under the candlestick chart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Indicators {
+    roc: bool,
+    /// Oscillator pane, RSI or stochastic, cycled through with `I`
+    oscillator: Option<SubPanel>,
+    /// Average true range pane
+    atr: bool,
+    /// Simple moving average over the candles
+    sma: bool,
+    /// The configured EMAs over the candles
+    ema: bool,
+    bollinger: bool,
+    /// Session VWAP over the candles
+    vwap: bool,
+    volume_shading: bool,
+    pin_bars: bool,
+    /// Candlestick pattern markers, and their log beside the charts
+    patterns: bool,
+    /// Golden and death cross arrows, and their log beside the charts
+    signals: bool,
+    /// Support and resistance levels across the candles
+    levels: bool,
+    /// Pivot points from the previous session
+    pivots: bool,
+    /// Series from the indicator scripts
+    scripts: bool,
+}
 
-    let (tx, rx) = mpsc::channel();
-    let tx_clone = tx.clone();
+impl Indicators {
+    const NONE: Indicators = Indicators {
+        roc: false,
+        oscillator: None,
+        atr: false,
+        sma: false,
+        ema: false,
+        bollinger: false,
+        vwap: false,
+        volume_shading: false,
+        pin_bars: false,
+        patterns: false,
+        signals: false,
+        levels: false,
+        pivots: false,
+        scripts: false,
+    };
+}
 
-    let markets: Vec<String> = vec![
-        "USD/BTC".to_string(),
-        "USD/ETH".to_string(),
-        "IDR/BTC".to_string(),
-        "IDR/ETH".to_string(),
-    ];
+/// Fields of the Bollinger Bands popup, top to bottom
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BandField {
+    Period,
+    Width,
+}
 
-    let thread_markets = markets.clone();
-
-    thread::spawn(move || {
-        let mut rng = rand::thread_rng();
-        
-        // Initialize with realistic prices based on provided values
-        let mut prices: HashMap<String, f64> = HashMap::new();
-        prices.insert("USD/BTC".to_string(), 103879.0);
-        prices.insert("USD/ETH".to_string(), 2548.64);
-        prices.insert("IDR/BTC".to_string(), 1729998000.0);
-        prices.insert("IDR/ETH".to_string(), 42679530.0);
-        
-        let mut time = Local::now().timestamp();
-
-        loop {
-            for market in &thread_markets {
-                let price = prices.get_mut(market).unwrap();
-                let open = *price;
-                
-                // Scale the volatility based on price magnitude
-                let volatility_factor = match market.as_str() {
-                    "USD/BTC" => 100.0,
-                    "USD/ETH" => 10.0,
-                    "IDR/BTC" => 1000000.0,
-                    "IDR/ETH" => 100000.0,
-                    _ => 1.0,
-                };
-                
-                let movement = rng.random_range(-1.0..1.0) * volatility_factor;
-                *price += movement;
-
-                let high = open.max(*price) + rng.random_range(0.0..volatility_factor * 0.2);
-                let low = open.min(*price) - rng.random_range(0.0..volatility_factor * 0.2);
-                let close = *price;
-                
-                // Scale volume based on the market
-                let volume_factor = match market.as_str() {
-                    "USD/BTC" | "IDR/BTC" => 5.0,
-                    "USD/ETH" | "IDR/ETH" => 20.0,
-                    _ => 1.0,
-                };
-                let volume = rng.random_range(100.0..1000.0) * volume_factor;
-
-                let candle = Candle {
-                    time,
-                    open,
-                    high,
-                    low,
-                    close,
-                    volume,
-                };
+impl BandField {
+    fn other(self) -> Self {
+        match self {
+            BandField::Period => BandField::Width,
+            BandField::Width => BandField::Period,
+        }
+    }
+}
 
-                if tx_clone
-                    .send(Message::NewCandle(market.clone(), candle))
-                    .is_err()
-                {
-                    return;
-                }
-            }
+/// The indicator parameter form, open on one market
+#[derive(Debug)]
+struct ParamEditor {
+    market: String,
+    /// The market's settings before the form opened, restored on cancel
+    original: Settings,
+    form: Form,
+}
 
-            thread::sleep(Duration::from_secs(1));
-            time += 60;
-        }
-    });
+/// A Fibonacci retracement being marked out on the selected market's
+/// candles, swing high first
+#[derive(Debug, Clone, Copy)]
+struct FibPlacement {
+    /// How many candles back from the newest the cursor is
+    cursor: usize,
+    /// The swing high once marked, as its candle time and price
+    high: Option<(i64, f64)>,
+}
 
-    let mut data: HashMap<String, Vec<Candle>> = HashMap::new();
-    let mut price_changes: HashMap<String, f64> = HashMap::new();
-    let mut latest_price_map: HashMap<String, f64> = HashMap::new();
+/// A marked swing retracement levels are drawn for, each end as its candle
+/// time and price
+#[derive(Debug, Clone, Copy)]
+struct Fib {
+    high: (i64, f64),
+    low: (i64, f64),
+}
+
+/// Panels that can be shown under the candlestick chart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SubPanel {
+    Volume,
+    /// Order book of the selected market, in place of volume
+    Depth,
+    /// Selected market against the pinned benchmark
+    Ratio,
+    /// Rate of change of the selected market
+    Roc,
+    Rsi,
+    Stochastic,
+    /// Average true range of the selected market
+    Atr,
+    /// MACD of the selected market, in place of volume
+    Macd,
+    /// On-balance volume of the selected market, in place of volume
+    Obv,
+    /// The indicator script at this index, for scripts with a panel
+    Script(usize),
+}
+
+/// What the info line under the volume chart shows for the selected market
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InfoMode {
+    Price,
+    Change,
+    Both,
+}
 
-    for m in markets.iter() {
-        data.insert(m.clone(), Vec::new());
-        price_changes.insert(m.clone(), 0.0);
+impl InfoMode {
+    fn next(self) -> Self {
+        match self {
+            InfoMode::Price => InfoMode::Change,
+            InfoMode::Change => InfoMode::Both,
+            InfoMode::Both => InfoMode::Price,
+        }
     }
+}
 
-    let mut selected_market = 0;
-    let mut should_quit = false;
-    let mut last_update = Instant::now();
+/// Bound on how long the app runs before shutting down on its own
+#[derive(Debug, Clone, Copy)]
+enum ExitAfter {
+    /// Total candles received across all markets
+    Candles(usize),
+    Duration(Duration),
+}
 
-    while !should_quit {
-        if let Ok(message) = rx.try_recv() {
-            match message {
-                Message::NewCandle(market, candle) => {
-                    if let Some(candles) = data.get_mut(&market) {
-                        if let Some(last_candle) = candles.last() {
-                            let change = candle.close - last_candle.close;
-                            if let Some(price_change) = price_changes.get_mut(&market) {
-                                *price_change = change;
-                            }
-                        }
+impl ExitAfter {
+    /// Parses `100` as a candle count and `60s` as a duration in seconds
+    fn parse(value: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid --exit-after value: {}", value);
 
-                        candles.push(candle.clone());
-                        if candles.len() > 30 {
-                            candles.remove(0);
-                        }
-                    }
-                    latest_price_map.insert(market.clone(), candle.close);
-                }
-                Message::Quit => should_quit = true,
-            }
+        match value.strip_suffix('s') {
+            Some(secs) => secs
+                .parse()
+                .map(|secs| ExitAfter::Duration(Duration::from_secs(secs)))
+                .map_err(|_| invalid()),
+            None => value.parse().map(ExitAfter::Candles).map_err(|_| invalid()),
         }
+    }
 
-        if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Char('q') => {
-                        tx.send(Message::Quit).unwrap();
-                        should_quit = true;
-                    }
-                    KeyCode::Down => {
-                        selected_market = (selected_market + 1) % markets.len();
-                    }
-                    KeyCode::Up => {
-                        selected_market = if selected_market == 0 {
-                            markets.len() - 1
-                        } else {
-                            selected_market - 1
-                        };
-                    }
-                    _ => {}
-                }
-            }
+    fn reached(self, candles_received: usize, elapsed: Duration) -> bool {
+        match self {
+            ExitAfter::Candles(limit) => candles_received >= limit,
+            ExitAfter::Duration(limit) => elapsed >= limit,
         }
+    }
+}
 
-        terminal.draw(|f| {
-            let size = f.area();
-            let chunks = Layout::default()
-                .direction(Direction::Horizontal)
-                .margin(1)
-                .constraints([Constraint::Length(20), Constraint::Min(10)].as_ref())
-                .split(size);
+/// How many candles each market keeps in memory.
+///
+/// The charts can zoom out to every retained candle and pan back through
+/// them, so with `Age` how far back they reach depends on the candle interval
+/// rather than being fixed. The default keeps several hundred. Anything
+/// computed from the retained window (e.g. a moving average) needs its full
+/// period inside that window before it produces a value, so keep the window
+/// comfortably longer than the longest lookback.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RetentionPolicy {
+    Count(usize),
+    /// Keep candles whose time is within this span of the newest candle
+    Age(Duration),
+}
 
-            let chart_chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([Constraint::Percentage(80), Constraint::Percentage(20)].as_ref())
-                .split(chunks[1]);
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy::Count(500)
+    }
+}
 
-            let items: Vec<Line> = markets
-                .iter()
-                .enumerate()
-                .map(|(i, m)| {
-                    let change = price_changes.get(m).unwrap_or(&0.0);
-                    let (icon, color) = if *change > 0.0 {
-                        ("🔼", Color::Green)
-                    } else if *change < 0.0 {
-                        ("🔽", Color::Red)
-                    } else {
-                        (" ", Color::Gray)
-                    };
+impl RetentionPolicy {
+    /// Parses `30` as a candle count and `90s`, `45m` or `2h` as an age.
+    /// Zero is rejected either way, since it would keep nothing to draw.
+    fn parse(value: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid --retain value: {}", value);
 
-                    // Format change text appropriately based on market
-                    let change_text = if *change != 0.0 {
-                        match m.as_str() {
-                            "USD/BTC" | "USD/ETH" => format!("({:.2})", change),
-                            "IDR/BTC" | "IDR/ETH" => format!("({:.0})", change),
-                            _ => format!("({:.2})", change),
-                        }
-                    } else {
-                        String::new()
-                    };
+        let (number, unit_secs) = match value.char_indices().last() {
+            Some((i, 's')) => (&value[..i], 1),
+            Some((i, 'm')) => (&value[..i], 60),
+            Some((i, 'h')) => (&value[..i], 3600),
+            _ => {
+                return value
+                    .parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .map(RetentionPolicy::Count)
+                    .ok_or_else(invalid);
+            }
+        };
 
-                    let market_text = format!("{} {} {}", icon, m, change_text);
+        number
+            .parse::<u64>()
+            .ok()
+            .filter(|&n| n > 0)
+            .and_then(|n| n.checked_mul(unit_secs))
+            .map(|secs| RetentionPolicy::Age(Duration::from_secs(secs)))
+            .ok_or_else(invalid)
+    }
 
-                    if i == selected_market {
-                        Line::from(Span::styled(
-                            market_text,
-                            Style::default()
-                                .fg(Color::Yellow)
-                                .add_modifier(Modifier::BOLD),
-                        ))
-                    } else {
-                        Line::from(Span::styled(market_text, Style::default().fg(color)))
-                    }
+    fn apply(self, candles: &mut Vec<Candle>) {
+        match self {
+            RetentionPolicy::Count(limit) => {
+                if candles.len() > limit {
+                    candles.drain(..candles.len() - limit);
+                }
+            }
+            RetentionPolicy::Age(age) => {
+                if let Some(newest) = candles.last() {
+                    let cutoff = newest.time - age.as_secs() as i64;
+                    candles.retain(|c| c.time >= cutoff);
+                }
+            }
+        }
+    }
+}
+
+/// Sidebar color for changes of at least `min_pct` percent, or with a
+/// negative `min_pct`, for losses at least that deep
+#[derive(Debug, Clone, Copy)]
+struct ChangeThreshold {
+    min_pct: f64,
+    color: Color,
+}
+
+impl ChangeThreshold {
+    /// Parses a comma-separated list of `PCT=COLOR`, e.g. `2=lightgreen,0=green`
+    fn parse_list(value: &str) -> Result<Vec<Self>, String> {
+        let mut thresholds = value
+            .split(',')
+            .map(|entry| {
+                let (pct, color) = entry.split_once('=')?;
+                Some(ChangeThreshold {
+                    min_pct: pct.trim().parse().ok()?,
+                    color: color.trim().parse().ok()?,
                 })
-                .collect();
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or(format!("invalid --change-colors value: {}", value))?;
 
-            let block = Block::default()
-                .title(" Markets ")
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::DarkGray));
+        thresholds.sort_by(|a, b| b.min_pct.total_cmp(&a.min_pct));
+        Ok(thresholds)
+    }
+}
 
-            let paragraph = Paragraph::new(items)
-                .block(block)
-                .alignment(Alignment::Left);
+#[derive(Debug, Default)]
+struct Args {
+    /// Friendly names shown in place of the market symbol, keyed by symbol
+    display_names: HashMap<String, String>,
+    exit_after: Option<ExitAfter>,
+    /// Minimum wick-to-body ratio for a candle to be marked as a pin bar;
+    /// zero or less turns the markers off
+    pin_bar_ratio: f64,
+    theme: Theme,
+    retention: RetentionPolicy,
+    /// Print a one-line market summary per update instead of running the TUI
+    summary: bool,
+    number_format: NumberFormat,
+    simulator: SimulatorConfig,
+    /// Ask for confirmation before `q` quits
+    confirm_quit: bool,
+    /// Sorted highest first; empty keeps plain green/red
+    change_thresholds: Vec<ChangeThreshold>,
+    demo_script: Option<String>,
+    roc_period: usize,
+    /// Show diagnostics such as realized vs configured volatility
+    debug: bool,
+    /// Labels shown as colored chips next to each market, keyed by symbol.
+    /// They're added to the market's tags from the settings file, so they're
+    /// saved along with those the next time settings are.
+    tags: HashMap<String, Vec<String>>,
+    /// How long each loop iteration waits for input, which also paces redraws
+    /// and candle consumption. Lower values react faster to input and drain
+    /// fast feeds sooner at the cost of more wakeups and redraws; higher values
+    /// save CPU but delay both.
+    poll_timeout: Duration,
+    /// Quote currency per market, for markets whose symbol doesn't start
+    /// with it
+    currencies: HashMap<String, Currency>,
+    compact_candles: CompactCandles,
+    /// Accent colors keyed by symbol; other markets take one from
+    /// `MARKET_PALETTE`
+    market_colors: HashMap<String, Color>,
+    /// How long without any new candle before the stall banner shows; by
+    /// default five updates of the slowest feed in use
+    stall_timeout: Option<Duration>,
+    /// Name of the registered feed candles come from
+    feed: String,
+    /// Feeds merged into one composite price; empty uses `feed` alone
+    composite: Vec<String>,
+    coinbase_interval: Duration,
+    /// Skip the on-disk history cache for exchange feeds
+    no_cache: bool,
+    /// Proxy URL for the exchange feeds; `None` uses the environment's
+    proxy: Option<String>,
+    /// Poll funding rate and open interest for markets with a perpetual
+    derivatives: bool,
+    /// Connect exchange feeds to their testnets
+    sandbox: bool,
+    /// Candles per market loaded before the first frame; 0 starts empty
+    backfill: usize,
+    /// CSV file or `.jsonl` session recording played back instead of any
+    /// feed
+    replay: Option<String>,
+    /// Replayed candles per second
+    replay_speed: f64,
+    /// Record received candles from the start instead of waiting for `R`
+    record: bool,
+    /// JSON-lines file recordings are appended to
+    record_file: String,
+    /// Indicator settings from the settings file
+    settings: SettingsFile,
+    /// Directory indicator scripts are loaded from, instead of the default
+    scripts: Option<String>,
+}
 
-            f.render_widget(paragraph, chunks[0]);
+impl Args {
+    fn parse() -> Result<Self, String> {
+        Args::parse_from(std::env::args().skip(1))
+    }
 
-            let selected = &markets[selected_market];
-            if let Some(candles) = data.get(selected) {
-                render_candlestick_chart(f, chart_chunks[0], candles);
-                render_volume_chart(f, chart_chunks[1], candles);
+    /// Parses `arguments`, without the program name
+    fn parse_from(arguments: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut args = Args {
+            pin_bar_ratio: 2.0,
+            roc_period: 10,
+            poll_timeout: Duration::from_millis(100),
+            backfill: 30,
+            feed: "simulator".to_string(),
+            coinbase_interval: Duration::from_secs(5),
+            replay_speed: 1.0,
+            record_file: "session.jsonl".to_string(),
+            ..Args::default()
+        };
+        let mut iter = arguments.into_iter();
+        let mut settings_file = None;
+        let mut sma_period = None;
 
-                if let Some(latest_price) = latest_price_map.get(selected) {
-                    let currency = if selected.starts_with("USD") {
-                        "USD"
-                    } else if selected.starts_with("IDR") {
-                        "IDR"
-                    } else {
-                        ""
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--alias" => {
+                    let value = iter.next().ok_or("--alias expects SYMBOL=NAME")?;
+                    let (symbol, name) = value
+                        .split_once('=')
+                        .ok_or(format!("invalid --alias value: {}", value))?;
+                    args.display_names
+                        .insert(symbol.to_string(), name.to_string());
+                }
+                "--currency" => {
+                    let value = iter.next().ok_or("--currency expects SYMBOL=CODE")?;
+                    let (symbol, code) = value
+                        .split_once('=')
+                        .filter(|(_, code)| !code.is_empty())
+                        .ok_or(format!("invalid --currency value: {}", value))?;
+                    args.currencies
+                        .insert(symbol.to_string(), Currency::from_code(code));
+                }
+                "--market-color" => {
+                    let value = iter.next().ok_or("--market-color expects SYMBOL=COLOR")?;
+                    let (symbol, color) = value
+                        .split_once('=')
+                        .ok_or(format!("invalid --market-color value: {}", value))?;
+                    let color = color
+                        .parse()
+                        .map_err(|_| format!("invalid --market-color color: {}", color))?;
+                    args.market_colors.insert(symbol.to_string(), color);
+                }
+                "--exit-after" => {
+                    let value = iter.next().ok_or("--exit-after expects a value")?;
+                    args.exit_after = Some(ExitAfter::parse(&value)?);
+                }
+                "--pin-bar-ratio" => {
+                    let value = iter.next().ok_or("--pin-bar-ratio expects a number")?;
+                    args.pin_bar_ratio = value
+                        .parse()
+                        .map_err(|_| format!("invalid --pin-bar-ratio value: {}", value))?;
+                }
+                "--chart-bg" => {
+                    let value = iter.next().ok_or("--chart-bg expects a color")?;
+                    let color = value
+                        .parse()
+                        .map_err(|_| format!("invalid --chart-bg color: {}", value))?;
+                    args.theme.chart_background = Some(color);
+                }
+                "--grid-color" => {
+                    let value = iter.next().ok_or("--grid-color expects a color")?;
+                    args.theme.grid_color = value
+                        .parse()
+                        .map_err(|_| format!("invalid --grid-color color: {}", value))?;
+                }
+                "--title-align" => {
+                    let value = iter
+                        .next()
+                        .ok_or("--title-align expects left, center or right")?;
+                    args.theme.title_alignment = match value.as_str() {
+                        "left" => Alignment::Left,
+                        "center" => Alignment::Center,
+                        "right" => Alignment::Right,
+                        _ => return Err(format!("invalid --title-align value: {}", value)),
                     };
-
-                    let price_text = match currency {
-                        "USD" => format!("USD{:>15}", format_usd(*latest_price)),
-                        "IDR" => format!("Rp{:>16}", format_idr(*latest_price)),
-                        _ => format!("{} {:.2}", currency, latest_price),
+                }
+                "--chart-padding" => {
+                    let value = iter.next().ok_or("--chart-padding expects a number")?;
+                    args.theme.chart_padding = value
+                        .parse()
+                        .map_err(|_| format!("invalid --chart-padding value: {}", value))?;
+                }
+                "--compact-candles" => {
+                    let value = iter
+                        .next()
+                        .ok_or("--compact-candles expects auto, on or off")?;
+                    args.compact_candles = match value.as_str() {
+                        "auto" => CompactCandles::Auto,
+                        "on" => CompactCandles::Always,
+                        "off" => CompactCandles::Never,
+                        _ => return Err(format!("invalid --compact-candles value: {}", value)),
                     };
-
-                    let info_block = Paragraph::new(Span::styled(
-                        price_text,
-                        Style::default()
-                            .fg(Color::Cyan)
-                            .add_modifier(Modifier::BOLD),
-                    ))
-                    .alignment(Alignment::Right);
-
-                    let info_area = Rect {
-                        x: chart_chunks[1].x,
-                        y: chart_chunks[1].y + chart_chunks[1].height.saturating_sub(1),
-                        width: chart_chunks[1].width,
-                        height: 1,
+                }
+                "--retain" => {
+                    let value = iter.next().ok_or("--retain expects a count or age")?;
+                    args.retention = RetentionPolicy::parse(&value)?;
+                }
+                "--start-price" => {
+                    let value = iter.next().ok_or("--start-price expects SYMBOL=PRICE")?;
+                    // The drift band is measured relative to this price, so
+                    // it has to be a real, positive one
+                    let (symbol, price) = value
+                        .split_once('=')
+                        .and_then(|(symbol, price)| {
+                            let price: f64 = price.parse().ok()?;
+                            (price.is_finite() && price > 0.0).then_some((symbol, price))
+                        })
+                        .ok_or(format!("invalid --start-price value: {}", value))?;
+                    args.simulator
+                        .start_prices
+                        .insert(symbol.to_string(), price);
+                }
+                "--mean-reversion" => {
+                    let value = iter.next().ok_or("--mean-reversion expects a number")?;
+                    args.simulator.mean_reversion = value
+                        .parse()
+                        .map_err(|_| format!("invalid --mean-reversion value: {}", value))?;
+                }
+                "--reset-band" => {
+                    let value = iter.next().ok_or("--reset-band expects a percentage")?;
+                    args.simulator.reset_band = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid --reset-band value: {}", value))?,
+                    );
+                }
+                "--model" => {
+                    let value = iter.next().ok_or("--model expects walk or gbm")?;
+                    args.simulator.model = match value.as_str() {
+                        "walk" => PriceModel::RandomWalk,
+                        "gbm" => PriceModel::Gbm,
+                        _ => return Err(format!("invalid --model value: {}", value)),
                     };
-
-                    f.render_widget(info_block, info_area);
                 }
+                "--volatility" => {
+                    let value = iter.next().ok_or("--volatility expects SYMBOL=PCT")?;
+                    let (symbol, pct) = value
+                        .split_once('=')
+                        .and_then(|(symbol, pct)| Some((symbol, pct.parse::<f64>().ok()?)))
+                        .filter(|(_, pct)| pct.is_finite() && *pct >= 0.0)
+                        .ok_or(format!("invalid --volatility value: {}", value))?;
+                    args.simulator.volatility.insert(symbol.to_string(), pct);
+                }
+                "--drift" => {
+                    let value = iter.next().ok_or("--drift expects a percentage")?;
+                    args.simulator.drift = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|pct| pct.is_finite())
+                        .ok_or(format!("invalid --drift value: {}", value))?;
+                }
+                "--seed" => {
+                    let value = iter.next().ok_or("--seed expects a number")?;
+                    args.simulator.seed = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid --seed value: {}", value))?,
+                    );
+                }
+                "--tick-ms" => {
+                    let value = iter.next().ok_or("--tick-ms expects milliseconds")?;
+                    args.simulator.tick_interval = value
+                        .parse()
+                        .ok()
+                        .filter(|&ms| ms > 0)
+                        .map(Duration::from_millis)
+                        .ok_or(format!("invalid --tick-ms value: {}", value))?;
+                }
+                "--change-colors" => {
+                    let value = iter.next().ok_or("--change-colors expects PCT=COLOR,...")?;
+                    args.change_thresholds = ChangeThreshold::parse_list(&value)?;
+                }
+                "--demo-script" => {
+                    args.demo_script = Some(iter.next().ok_or("--demo-script expects a file")?);
+                }
+                "--sma-period" => {
+                    let value = iter.next().ok_or("--sma-period expects a number")?;
+                    sma_period = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid --sma-period value: {}", value))?,
+                    );
+                }
+                "--roc-period" => {
+                    let value = iter.next().ok_or("--roc-period expects a number")?;
+                    args.roc_period = value
+                        .parse()
+                        .map_err(|_| format!("invalid --roc-period value: {}", value))?;
+                }
+                "--poll-ms" => {
+                    let value = iter.next().ok_or("--poll-ms expects milliseconds")?;
+                    args.poll_timeout = value
+                        .parse()
+                        .ok()
+                        .filter(|&ms| ms > 0)
+                        .map(Duration::from_millis)
+                        .ok_or(format!("invalid --poll-ms value: {}", value))?;
+                }
+                "--stall-timeout" => {
+                    let value = iter.next().ok_or("--stall-timeout expects seconds")?;
+                    args.stall_timeout = Some(
+                        value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|secs| secs.is_finite() && *secs > 0.0)
+                            .map(Duration::from_secs_f64)
+                            .ok_or(format!("invalid --stall-timeout value: {}", value))?,
+                    );
+                }
+                "--record" => args.record = true,
+                "--record-file" => {
+                    args.record_file = iter.next().ok_or("--record-file expects a path")?;
+                }
+                "--backfill" => {
+                    let value = iter.next().ok_or("--backfill expects a candle count")?;
+                    args.backfill = value
+                        .parse()
+                        .map_err(|_| format!("invalid --backfill value: {}", value))?;
+                }
+                "--tag" => {
+                    let value = iter.next().ok_or("--tag expects SYMBOL=TAG[,TAG...]")?;
+                    let (symbol, tags) = value
+                        .split_once('=')
+                        .ok_or(format!("invalid --tag value: {}", value))?;
+                    args.tags
+                        .entry(symbol.to_string())
+                        .or_default()
+                        .extend(tags.split(',').map(str::to_string));
+                }
+                "--feed" => {
+                    args.feed = iter.next().ok_or("--feed expects a feed name")?;
+                }
+                "--composite" => {
+                    let value = iter.next().ok_or("--composite expects FEED,FEED[,...]")?;
+                    args.composite = value.split(',').map(str::to_string).collect();
+                }
+                "--coinbase-interval" => {
+                    let value = iter.next().ok_or("--coinbase-interval expects seconds")?;
+                    args.coinbase_interval = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|secs| secs.is_finite() && *secs >= 1.0)
+                        .map(Duration::from_secs_f64)
+                        .ok_or(format!("invalid --coinbase-interval value: {}", value))?;
+                    args.feed = "coinbase".to_string();
+                }
+                "--live" => args.feed = "binance".to_string(),
+                "--replay" => {
+                    args.replay = Some(iter.next().ok_or("--replay expects a CSV file")?);
+                }
+                "--replay-speed" => {
+                    let value = iter
+                        .next()
+                        .ok_or("--replay-speed expects candles per second")?;
+                    args.replay_speed = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|speed| speed.is_finite() && *speed > 0.0)
+                        .ok_or(format!("invalid --replay-speed value: {}", value))?;
+                }
+                "--no-cache" => args.no_cache = true,
+                "--derivatives" => args.derivatives = true,
+                "--sandbox" => args.sandbox = true,
+                "--proxy" => {
+                    args.proxy = Some(iter.next().ok_or("--proxy expects a proxy URL")?);
+                }
+                "--settings" => {
+                    settings_file = Some(iter.next().ok_or("--settings expects a file")?);
+                }
+                "--scripts" => {
+                    args.scripts = Some(iter.next().ok_or("--scripts expects a directory")?);
+                }
+                "--summary" => args.summary = true,
+                "--debug" => args.debug = true,
+                "--confirm-quit" => args.confirm_quit = true,
+                "--full-numbers" => args.number_format = NumberFormat::Full,
+                other => return Err(format!("unknown argument: {}", other)),
             }
-        })?;
-
-        let elapsed = last_update.elapsed();
-        if elapsed < Duration::from_millis(100) {
-            thread::sleep(Duration::from_millis(100) - elapsed);
         }
-        last_update = Instant::now();
+        args.settings = SettingsFile::load(settings_file.as_deref())?;
+        if let Some(period) = sma_period {
+            args.settings.defaults.sma_period = period;
+        }
+
+        Ok(args)
     }
+}
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+/// Per-market data fed by the candle channel, independent of any rendering,
+/// plus the formatting preferences shared by the TUI and headless output
+#[derive(Debug, Default)]
+struct AppState {
+    markets: Vec<String>,
+    data: HashMap<String, Vec<Candle>>,
+    price_changes: HashMap<String, f64>,
+    latest_price_map: HashMap<String, f64>,
+    session_open: HashMap<String, f64>,
+    number_format: NumberFormat,
+    /// Configured quote currencies; other markets use their symbol's first part
+    currencies: HashMap<String, Currency>,
+    /// Latest connection state reported for each market's feed
+    feed_status: HashMap<String, FeedStatus>,
+    /// Latest close per exchange for composite markets
+    quotes: HashMap<String, Vec<(String, f64)>>,
+    /// Latest order book per market, for feeds that send one
+    books: HashMap<String, OrderBook>,
+    /// Recent trades per market, oldest first, up to `MAX_TAPE_TRADES`
+    trades: HashMap<String, Vec<Trade>>,
+    /// Latest perpetual funding and open interest per market
+    derivatives: HashMap<String, PerpStats>,
+    /// When each market's feed was last restarted by its supervisor
+    restarted: HashMap<String, Instant>,
+    /// Indicator parameters the indicator sets are built with, per market
+    settings: SettingsFile,
+    /// Indicators over each market's candles, updated as they arrive
+    indicator_sets: HashMap<String, IndicatorSet>,
+    /// Patterns formed by each market's closed candles still in its window,
+    /// by candle time, oldest first
+    patterns: HashMap<String, Vec<(i64, Pattern)>>,
+    /// Patterns across every market as `(time, market, pattern)`, oldest
+    /// first, up to `MAX_LOGGED_PATTERNS`
+    pattern_log: Vec<(i64, String, Pattern)>,
+    /// Golden and death crosses of each market's closed candles, oldest
+    /// first, up to `MAX_SIGNALS`
+    signals: HashMap<String, Vec<Signal>>,
+    /// Each market's sessions, for pivot points
+    sessions: HashMap<String, SessionTracker>,
+    /// Each market's candles as Heikin-Ashi candles, for the Heikin-Ashi mode
+    heikin_ashi: HashMap<String, HeikinAshi>,
+}
 
-    Ok(())
+/// The fast SMA crossing the slow one at a candle's close
+#[derive(Debug, Clone, Copy)]
+struct Signal {
+    time: i64,
+    price: f64,
+    cross: Cross,
 }
 
-fn render_candlestick_chart(f: &mut ratatui::Frame, area: Rect, candles: &[Candle]) {
-    if candles.is_empty() {
-        f.render_widget(
-            Block::default()
-                .title("Candlestick Chart")
-                .borders(Borders::ALL),
-            area,
-        );
-        return;
-    }
+impl AppState {
+    fn new(markets: Vec<String>) -> Self {
+        let mut state = AppState::default();
 
-    let (min_price, max_price) = candles.iter().fold((f64::MAX, f64::MIN), |(min, max), c| {
-        (min.min(c.low), max.max(c.high))
-    });
+        for m in markets.iter() {
+            state.data.insert(m.clone(), Vec::new());
+            state.price_changes.insert(m.clone(), 0.0);
+        }
 
-    let y_padding = (max_price - min_price) * 0.1;
-    let y_min = min_price - y_padding;
-    let y_max = max_price + y_padding;
+        state.markets = markets;
+        state
+    }
 
-    let canvas = Canvas::default()
-        .block(
-            Block::default()
-                .title("Candlestick Chart")
-                .borders(Borders::ALL),
-        )
-        .x_bounds([0.0, candles.len() as f64])
-        .y_bounds([y_min, y_max])
-        .paint(|ctx| {
-            for (i, candle) in candles.iter().enumerate() {
-                let x = i as f64 + 0.5;
-
-                ctx.draw(&CanvasLine {
-                    x1: x,
-                    y1: candle.low,
-                    x2: x,
-                    y2: candle.high,
-                    color: Color::White,
-                });
+    fn push_trade(&mut self, market: &str, trade: Trade) {
+        let trades = self.trades.entry(market.to_string()).or_default();
+        trades.push(trade);
+        if trades.len() > MAX_TAPE_TRADES {
+            trades.drain(..trades.len() - MAX_TAPE_TRADES);
+        }
+    }
 
-                let (body_bottom, body_top) = if candle.close >= candle.open {
-                    (candle.open, candle.close)
-                } else {
-                    (candle.close, candle.open)
-                };
+    fn push_candle(&mut self, market: &str, candle: Candle, retention: RetentionPolicy) {
+        if let Some(candles) = self.data.get_mut(market) {
+            // Live feeds resend the open candle as it updates; keep only the
+            // latest version of it
+            if candles.last().is_some_and(|last| last.time == candle.time) {
+                candles.pop();
+            } else if let Some(closed) = candles.last() {
+                // A new candle means the one before it is final
+                let previous = candles.len().checked_sub(2).map(|i| &candles[i]);
+                if let Some(pattern) = detect(previous, closed) {
+                    self.patterns
+                        .entry(market.to_string())
+                        .or_default()
+                        .push((closed.time, pattern));
+                    self.pattern_log
+                        .push((closed.time, market.to_string(), pattern));
+                    if self.pattern_log.len() > MAX_LOGGED_PATTERNS {
+                        self.pattern_log
+                            .drain(..self.pattern_log.len() - MAX_LOGGED_PATTERNS);
+                    }
+                }
 
-                let color = if candle.close >= candle.open {
-                    Color::Green
-                } else {
-                    Color::Red
-                };
+                // The indicators haven't seen the new candle yet, so their
+                // newest values are the closed candle's
+                if let Some(cross) = self.indicator_sets.get(market).and_then(|set| {
+                    crossing(set.series(Study::CrossFast), set.series(Study::CrossSlow))
+                }) {
+                    let signals = self.signals.entry(market.to_string()).or_default();
+                    signals.push(Signal {
+                        time: closed.time,
+                        price: closed.close,
+                        cross,
+                    });
+                    if signals.len() > MAX_SIGNALS {
+                        signals.drain(..signals.len() - MAX_SIGNALS);
+                    }
+                }
+            }
 
-                ctx.draw(&Rectangle {
-                    x: x - 0.3,
-                    y: body_bottom,
-                    width: 0.6,
-                    height: body_top - body_bottom,
-                    color,
-                });
+            if let Some(last_candle) = candles.last() {
+                let change = candle.close - last_candle.close;
+                if let Some(price_change) = self.price_changes.get_mut(market) {
+                    *price_change = change;
+                }
             }
-        });
 
-    f.render_widget(canvas, area);
-}
+            candles.push(candle.clone());
+            retention.apply(candles);
+            if let (Some(patterns), Some(first)) = (self.patterns.get_mut(market), candles.first())
+            {
+                patterns.retain(|&(time, _)| time >= first.time);
+            }
 
-fn render_volume_chart(f: &mut ratatui::Frame, area: Rect, candles: &[Candle]) {
-    if candles.is_empty() {
-        f.render_widget(Block::default().title("Volume").borders(Borders::ALL), area);
-        return;
+            self.indicator_sets
+                .entry(market.to_string())
+                .or_insert_with(|| IndicatorSet::new(self.settings.for_market(market)))
+                .update(&candle, candles.len());
+            self.heikin_ashi
+                .entry(market.to_string())
+                .or_default()
+                .update(&candle, candles.len());
+        }
+        self.sessions
+            .entry(market.to_string())
+            .or_insert_with(|| SessionTracker::new(self.settings.for_market(market).session_start))
+            .update(&candle);
+        self.session_open
+            .entry(market.to_string())
+            .or_insert(candle.open);
+        self.latest_price_map
+            .insert(market.to_string(), candle.close);
     }
 
-    let max_volume = candles.iter().map(|c| c.volume).fold(0.0, f64::max) * 1.1;
-
-    let volumes: Vec<(f64, f64)> = candles
-        .iter()
-        .enumerate()
-        .map(|(i, c)| (i as f64, c.volume))
-        .collect();
-
-    let datasets = vec![
-        Dataset::default()
-            .name("Volume")
-            .marker(symbols::Marker::Braille)
-            .graph_type(GraphType::Bar)
-            .style(Style::default().fg(Color::Blue))
-            .data(&volumes),
-    ];
-
-    let x_labels = if candles.len() > 5 {
-        vec![
-            Span::from(format_time(candles.first().unwrap().time)),
-            Span::from(format_time(candles.last().unwrap().time)),
-        ]
-    } else {
-        candles
-            .iter()
-            .map(|c| Span::from(format_time(c.time)))
-            .collect()
-    };
+    /// `study` over `market`'s candles as chart points
+    fn indicator_points(&self, market: &str, study: Study) -> Vec<(f64, f64)> {
+        self.indicator_sets
+            .get(market)
+            .map_or_else(Vec::new, |set| set.points(study))
+    }
 
-    let y_labels = vec![
-        Span::from("0"),
-        Span::from(format!("{:.0}", max_volume / 2.0)),
-        Span::from(format!("{:.0}", max_volume)),
-    ];
+    /// Changes `market`'s indicator settings, recomputing its indicators
+    /// over the candles it has
+    fn update_settings(&mut self, market: &str, change: impl FnOnce(&mut Settings)) {
+        let settings = self.settings.for_market_mut(market);
+        let session_start = settings.session_start;
+        change(settings);
 
-    let chart = Chart::new(datasets)
-        .block(Block::default().title("Volume").borders(Borders::ALL))
-        .x_axis(
-            Axis::default()
-                .title(Line::from("Time"))
-                .style(Style::default().fg(Color::Gray))
-                .bounds([0.0, candles.len() as f64 - 1.0])
-                .labels(x_labels),
-        )
-        .y_axis(
-            Axis::default()
-                .title(Line::from("Volume"))
-                .style(Style::default().fg(Color::Gray))
-                .bounds([0.0, max_volume])
-                .labels(y_labels),
+        let settings = self.settings.for_market(market);
+        let candles = self.data.get(market).map_or(&[][..], Vec::as_slice);
+        self.indicator_sets.insert(
+            market.to_string(),
+            IndicatorSet::with_history(settings, candles),
         );
-
-    f.render_widget(chart, area);
-}
-
-fn format_time(timestamp: i64) -> String {
-    match DateTime::from_timestamp(timestamp, 0) {
-        Some(dt) => {
-            let local_dt = Local.from_utc_datetime(&dt.naive_utc());
-            local_dt.format("%H:%M").to_string()
+        // Sessions before the window are lost, so pivots wait for the next
+        // whole session
+        if settings.session_start != session_start {
+            let mut sessions = SessionTracker::new(settings.session_start);
+            for candle in candles {
+                sessions.update(candle);
+            }
+            self.sessions.insert(market.to_string(), sessions);
         }
-        None => {
-            eprintln!("Warning: Invalid timestamp {}", timestamp);
-            "Invalid Time".to_string()
+    }
+
+    /// The currency `market` is quoted in, so `USD/BTC` is priced in USD
+    fn currency(&self, market: &str) -> Currency {
+        match self.currencies.get(market) {
+            Some(currency) => currency.clone(),
+            None => Currency::from_code(market.split('/').next().unwrap_or(market)),
         }
     }
 }
 
-fn format_usd(price: f64) -> String {
-    if !price.is_finite() {
-        return "Invalid".to_string();
-    }
+fn main() -> Result<(), io::Error> {
+    let args = Args::parse().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
 
-    if price == 0.0 {
-        return "$0.00".to_string();
-    }
+    let mut demo_script = match &args.demo_script {
+        Some(path) => Some(
+            DemoScript::parse(&std::fs::read_to_string(path)?)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+        ),
+        None => None,
+    };
 
-    let abs_price = price.abs();
-    let sign = if price < 0.0 { "-" } else { "" };
+    let mut scripts = Scripts::load(args.scripts.as_deref())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
 
-    let formatted = if abs_price >= 1_000_000_000.0 {
-        format!("{}{:.2}B", sign, abs_price / 1_000_000_000.0)
-    } else if abs_price >= 1_000_000.0 {
-        format!("{}{:.2}M", sign, abs_price / 1_000_000.0)
-    } else if abs_price >= 1_000.0 {
-        format!("{}{:.2}K", sign, abs_price / 1_000.0)
-    } else if abs_price >= 0.10 {
-        format!("{}{:.2}", sign, abs_price)
+    let network = Network::new(args.proxy.as_deref())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+        .with_sandbox(args.sandbox);
+    let registry = plugin::builtin(&args.simulator, args.coinbase_interval, &network);
+    let feed_names = if args.composite.is_empty() {
+        vec![args.feed.clone()]
+    } else {
+        args.composite.clone()
+    };
+    let plugins = feed_names
+        .iter()
+        .map(|name| registry.get(name))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    // The exchanges only carry USD pairs, so IDR markets come from Indodax
+    // alongside them, or from the simulator when it's unreachable
+    let live = plugins.iter().any(|plugin| !plugin.simulated());
+    let indodax = if live {
+        Some(
+            registry
+                .get("indodax")
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?,
+        )
     } else {
-        format!("{}{:.4}", sign, abs_price) 
+        None
     };
 
-    if abs_price < 1_000.0 && abs_price >= 0.10 {
-        let parts: Vec<&str> = formatted.split('.').collect();
-        let integer_part = parts[0]
-            .chars()
-            .rev()
-            .collect::<String>()
-            .as_bytes()
-            .chunks(3)
-            .map(|chunk| std::str::from_utf8(chunk).unwrap())
-            .collect::<Vec<&str>>()
-            .join(",")
-            .chars()
-            .rev()
-            .collect::<String>();
+    // The watchlist is every market the feeds in use have pairs for
+    let mut markets: Vec<String> = Vec::new();
+    for market in plugins.iter().chain(&indodax).flat_map(|p| p.markets()) {
+        if !markets.contains(&market) {
+            markets.push(market);
+        }
+    }
+
+    // A replay brings its own markets and replaces the feed entirely
+    let replay = match &args.replay {
+        Some(path) => {
+            let source = std::fs::read_to_string(path)?;
+            let replay = if path.ends_with(".jsonl") {
+                CsvReplay::parse_recording(&source, args.replay_speed)
+            } else {
+                CsvReplay::parse(&source, &markets[0], args.replay_speed)
+            }
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            markets = replay.markets();
+            if markets.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "replay file has no candles",
+                ));
+            }
+            Some(replay)
+        }
+        None => None,
+    };
+    let replay_control = replay.as_ref().map(CsvReplay::control);
+
+    // Polling feeds are quiet between rounds, so how long counts as stalled
+    // or hung follows the slowest one in use
+    let slowest_update = plugins
+        .iter()
+        .chain(&indodax)
+        .filter_map(|plugin| plugin.update_interval())
+        .fold(args.simulator.tick_interval, Duration::max);
+    let stall_timeout = args.stall_timeout.unwrap_or(slowest_update * 5);
 
-        format!("${}.{}", integer_part, parts[1])
+    // Each feed's producer is restarted on its own if it dies or hangs; the
+    // timeout leaves the slowest feed in use a few rounds to check in
+    let heartbeat_timeout = HEARTBEAT_TIMEOUT.max(slowest_update * 3);
+    let supervised = |plugin: &dyn FeedPlugin| -> Box<dyn MarketDataSource> {
+        Box::new(Supervised::new(plugin.source(), heartbeat_timeout))
+    };
+    let feed: Box<dyn MarketDataSource> = match plugins.as_slice() {
+        [plugin] => supervised(*plugin),
+        _ => Box::new(
+            plugins
+                .iter()
+                .fold(Composite::default(), |composite, plugin| {
+                    composite.source(plugin.name(), supervised(*plugin))
+                }),
+        ),
+    };
+    // Only exchange history is cached, as simulated history is made up on the
+    // spot, and testnet history goes in a cache of its own since its prices
+    // are unrelated to production's
+    let cache_name = format!(
+        "{}{}",
+        feed_names.join("+"),
+        if args.sandbox { "-sandbox" } else { "" }
+    );
+    let feed: Box<dyn MarketDataSource> = match cache_dir(&cache_name) {
+        Some(dir) if !args.no_cache && !plugins.iter().any(|p| p.simulated()) => {
+            Box::new(Cached::new(dir, feed))
+        }
+        _ => feed,
+    };
+    let source: Box<dyn MarketDataSource> = match (replay, indodax) {
+        (Some(replay), _) => Box::new(replay),
+        (None, None) => feed,
+        (None, Some(indodax)) => Box::new(
+            Routed::default()
+                .route(|m| m.starts_with("IDR/"), supervised(indodax))
+                .route(|_| true, feed),
+        ),
+    };
+    let source: Box<dyn MarketDataSource> = if args.derivatives {
+        Box::new(WithDerivatives::new(source, network.clone()))
     } else {
-        format!("${}", formatted)
+        source
+    };
+    let rx = bounded(source.subscribe(&markets), CHANNEL_CAPACITY);
+
+    let mut state = AppState::new(markets);
+    state.settings = args.settings.clone();
+    for (market, tags) in &args.tags {
+        let market_tags = &mut state.settings.for_market_mut(market).tags;
+        for tag in tags {
+            if !market_tags.contains(tag) {
+                market_tags.push(tag.clone());
+            }
+        }
     }
-}
+    state.number_format = args.number_format;
+    state.currencies = args.currencies.clone();
 
-fn format_idr(price: f64) -> String {
-    if price.is_nan() || price.is_infinite() {
-        return "Invalid".to_string();
+    // Recent history fills the chart before the first frame instead of it
+    // building up one tick at a time. Failures are told in the status line
+    // rather than printed, which the app's screen would hide.
+    let mut backfill_errors = Vec::new();
+    if args.backfill > 0 {
+        for market in state.markets.clone() {
+            match source.fetch_history(&market, args.backfill) {
+                Ok(candles) => {
+                    for candle in candles {
+                        state.push_candle(&market, candle, args.retention);
+                    }
+                    // Changes are measured from when the app started, not
+                    // from the start of the history
+                    state.session_open.remove(&market);
+                }
+                Err(e) => backfill_errors.push(e.to_string()),
+            }
+        }
     }
-    
-    let rounded = price.round() as i64;
-    let mut s = rounded.to_string();
-    let mut result = String::new();
 
-    while s.len() > 3 {
-        let len = s.len();
-        result = format!(".{}{}", &s[len - 3..], result);
-        s.truncate(len - 3);
+    let mut recorder = if args.record {
+        Some(Recorder::open(&args.record_file)?)
+    } else {
+        None
+    };
+
+    if args.summary {
+        for error in &backfill_errors {
+            eprintln!("Warning: {}", error);
+        }
+        return run_summary(rx, &mut state, args.retention, recorder, args.exit_after);
     }
 
-    format!("{}{}", s, result)
-}
\ No newline at end of file
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let backfill_warning = (!backfill_errors.is_empty()).then(|| {
+        StatusMessage::lasting(
+            format!("Warning: {}", backfill_errors.join("; ")),
+            WARNING_DURATION,
+        )
+    });
+
+    let mut selection = Selection::default();
+    let mut market_list_state = ListState::default();
+    let mut panels = Panels::default();
+    let mut chart_view = ChartView::default();
+    let mut popups = Popups::default();
+    // Retracements by market, kept until cleared
+    let mut fibs: HashMap<String, Fib> = HashMap::new();
+    let mut status_message: Option<StatusMessage> = backfill_warning;
+    let mut should_quit = false;
+    let mut last_update = Instant::now();
+    let started = Instant::now();
+    let mut last_candle_at = started;
+    let mut candles_received = 0;
+    // Set when --exit-after ended the session, which prints a summary
+    let mut timed_out = false;
+
+    while !should_quit {
+        // Everything that arrived since the last frame, so a fast feed
+        // doesn't fall behind the screen
+        for message in rx.try_iter().take(MAX_MESSAGES_PER_FRAME) {
+            match message {
+                Message::NewCandle(market, candle) => {
+                    candles_received += 1;
+                    last_candle_at = Instant::now();
+                    if let Some(r) = recorder.as_mut()
+                        && r.record(&market, &candle).is_err()
+                    {
+                        recorder = None;
+                        status_message =
+                            Some(StatusMessage::new("recording stopped: write failed"));
+                    }
+                    // A new candle for the selected market, rather than an
+                    // update to its newest
+                    let newer = market == state.markets[selection.market]
+                        && state
+                            .data
+                            .get(&market)
+                            .and_then(|candles| candles.last())
+                            .is_some_and(|last| last.time != candle.time);
+                    state.push_candle(&market, candle, args.retention);
+                    if newer {
+                        chart_view.candle_arrived();
+                    }
+
+                    if selection.auto_follow {
+                        selection.market = most_active_market(&state, selection.market, 10);
+                    }
+                }
+                Message::FeedStatus(markets, status) => {
+                    for market in markets {
+                        state.feed_status.insert(market, status);
+                    }
+                }
+                Message::Derivatives(market, stats) => {
+                    state.derivatives.insert(market, stats);
+                }
+                Message::Trade(market, trade) => {
+                    state.push_trade(&market, trade);
+                }
+                Message::Depth(market, book) => {
+                    state.books.insert(market, book);
+                }
+                Message::Quotes(market, quotes) => {
+                    state.quotes.insert(market, quotes);
+                }
+                Message::Restarted(markets) => {
+                    for market in markets {
+                        state.restarted.insert(market, Instant::now());
+                    }
+                }
+                Message::Heartbeat => {}
+            }
+        }
+
+        let mut key_code = None;
+        if let Some(script) = demo_script.as_mut() {
+            match script.next_due(started.elapsed()) {
+                Some(Action::Key(code)) => key_code = Some(code),
+                Some(Action::Select(market)) => selection.select(&state.markets, &market),
+                None => {}
+            }
+        }
+
+        // Candles stored for the selected market, which zooming and
+        // panning are bounded by
+        let stored = state
+            .data
+            .get(&state.markets[selection.market])
+            .map_or(0, Vec::len);
+
+        if key_code.is_none() && event::poll(args.poll_timeout)? {
+            match event::read()? {
+                Event::Key(key) => key_code = Some(key.code),
+                // The wheel zooms like + and -, unless a popup is open
+                Event::Mouse(mouse) if !popups.blocks_mouse() => {
+                    chart_view.handle_mouse(mouse.kind, stored);
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(code) = key_code
+            && !popups.handle_key(
+                code,
+                &mut state,
+                &mut selection,
+                &mut fibs,
+                &mut status_message,
+                &args.display_names,
+            )
+        {
+            let market = &state.markets[selection.market];
+            let signals = state.signals.get(market).map_or(0, Vec::len);
+            if chart_view.crosshair.is_some() {
+                chart_view.handle_crosshair_key(code, stored);
+            } else if popups.confirming_quit {
+                match code {
+                    KeyCode::Char('y') | KeyCode::Enter => should_quit = true,
+                    KeyCode::Char('n') | KeyCode::Esc => popups.confirming_quit = false,
+                    _ => {}
+                }
+            } else if !selection.handle_key(code, state.markets.len())
+                && !chart_view.handle_key(code, stored)
+                && !panels.handle_key(code, signals, !scripts.is_empty(), &mut status_message)
+            {
+                match code {
+                    KeyCode::Char('q') if args.confirm_quit => {
+                        popups.confirming_quit = true;
+                    }
+                    KeyCode::Char('q') => {
+                        should_quit = true;
+                    }
+                    KeyCode::Char('e') => {
+                        let market = market.clone();
+                        popups.edit_settings(
+                            &state,
+                            &market,
+                            display_name(&args.display_names, &market),
+                        );
+                    }
+                    // Shows the bands too, so changes can be seen as they're made
+                    KeyCode::Char(',') => {
+                        panels.indicators.bollinger = true;
+                        popups.bands = Some(BandField::Period);
+                    }
+                    KeyCode::Char('n') => {
+                        state.number_format = state.number_format.toggle();
+                    }
+                    // Adds the named tag to the selected market, or removes it
+                    // when the market has it already
+                    KeyCode::Char('t') => {
+                        popups.name_tag(display_name(&args.display_names, market));
+                    }
+                    // Switches the selected market between candles and OHLC
+                    // bars, saving the choice with its settings
+                    KeyCode::Char('C') => {
+                        let market = market.clone();
+                        let settings = state.settings.for_market_mut(&market);
+                        settings.style = match settings.style {
+                            CandleStyle::Candles => CandleStyle::Bars,
+                            CandleStyle::Bars => CandleStyle::Candles,
+                        };
+                        status_message = Some(save_settings(&state.settings));
+                    }
+                    // Clears the selected market's retracement, or starts
+                    // marking one out
+                    KeyCode::Char('F') => {
+                        if fibs.remove(market).is_some() {
+                            status_message = Some(StatusMessage::new("retracement cleared"));
+                        } else {
+                            popups.fib_placement = Some(FibPlacement {
+                                cursor: 0,
+                                high: None,
+                            });
+                        }
+                    }
+                    KeyCode::Char('m') => {
+                        popups.browser = Some(MarketBrowser::new(market));
+                    }
+                    KeyCode::Char('R') => {
+                        recorder = match recorder.take() {
+                            Some(_) => {
+                                status_message = Some(StatusMessage::new("recording stopped"));
+                                None
+                            }
+                            None => match Recorder::open(&args.record_file) {
+                                Ok(r) => {
+                                    status_message = Some(StatusMessage::new("recording started"));
+                                    Some(r)
+                                }
+                                Err(_) => {
+                                    status_message = Some(StatusMessage::new(
+                                        "could not open the recording file",
+                                    ));
+                                    None
+                                }
+                            },
+                        };
+                    }
+                    KeyCode::Char(' ') => {
+                        if let Some(control) = &replay_control {
+                            control.toggle_pause();
+                            status_message = Some(StatusMessage::new(if control.is_paused() {
+                                "replay paused, . steps"
+                            } else {
+                                "replay resumed"
+                            }));
+                        }
+                    }
+                    KeyCode::Char('.') => {
+                        if let Some(control) = replay_control.as_ref().filter(|c| c.is_paused()) {
+                            control.step();
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if let Some(exit_after) = args.exit_after
+            && exit_after.reached(candles_received, started.elapsed())
+        {
+            should_quit = true;
+            timed_out = true;
+        }
+
+        // Scripts run here rather than while drawing, and only when the
+        // selected market's candles changed
+        if panels.indicators.scripts {
+            let market = &state.markets[selection.market];
+            if let Some(candles) = state.data.get(market) {
+                scripts.refresh(market, candles);
+            }
+        }
+
+        terminal.draw(|f| {
+            let size = f.area();
+
+            if let Some(open_browser) = &popups.browser {
+                open_browser.render(
+                    f,
+                    size,
+                    &state,
+                    &args.display_names,
+                    selection.benchmark.map(|b| state.markets[b].as_str()),
+                    &args.change_thresholds,
+                );
+                return;
+            }
+            let sidebar_width = match panels.view_mode {
+                ViewMode::Normal => 20,
+                ViewMode::Detail => 0,
+            };
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .margin(1)
+                .constraints([Constraint::Length(sidebar_width), Constraint::Min(10)].as_ref())
+                .split(size);
+
+            // The ratio panel only makes sense against a different market
+            let benchmark_market = selection
+                .benchmark
+                .filter(|&b| b != selection.market)
+                .map(|b| &state.markets[b]);
+
+            // Sub-panels stack under the candles when there's room; on short
+            // terminals only the active one is shown and `p` cycles them
+            let mut subpanels = vec![panels.lower_panel];
+            if benchmark_market.is_some() {
+                subpanels.push(SubPanel::Ratio);
+            }
+            if panels.indicators.roc {
+                subpanels.push(SubPanel::Roc);
+            }
+            subpanels.extend(panels.indicators.oscillator);
+            if panels.indicators.atr {
+                subpanels.push(SubPanel::Atr);
+            }
+            if panels.indicators.scripts {
+                let outputs = scripts.outputs(&state.markets[selection.market]);
+                subpanels.extend(
+                    (0..outputs.len())
+                        .filter(|&i| outputs[i].pane)
+                        .map(SubPanel::Script),
+                );
+            }
+            if chunks[1].height < 30 {
+                subpanels = vec![subpanels[panels.active_subpanel % subpanels.len()]];
+            }
+
+            let mut chart_constraints = vec![Constraint::Min(0)];
+            chart_constraints.extend(subpanels.iter().map(|_| Constraint::Percentage(20)));
+
+            // The trade tape, pattern log and signals take a column on the
+            // right of the charts, stacked when more than one is shown
+            let side_panels = [
+                panels.show_tape,
+                panels.indicators.patterns,
+                panels.indicators.signals,
+            ];
+            let shown = side_panels.iter().filter(|&&shown| shown).count();
+            let (chart_area, side_areas) = if shown > 0 {
+                let columns = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Min(10), Constraint::Length(34)])
+                    .split(chunks[1]);
+                let rows = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints(vec![Constraint::Ratio(1, shown as u32); shown])
+                    .split(columns[1]);
+                (columns[0], rows.to_vec())
+            } else {
+                (chunks[1], Vec::new())
+            };
+            let mut side_areas = side_areas.into_iter();
+            let [tape_area, pattern_area, signal_area] =
+                side_panels.map(|shown| if shown { side_areas.next() } else { None });
+
+            let chart_chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(chart_constraints)
+                .split(chart_area);
+
+            let items: Vec<ListItem> = state
+                .markets
+                .iter()
+                .enumerate()
+                .map(|(i, m)| {
+                    let change = state.price_changes.get(m).unwrap_or(&0.0);
+                    let icon = if *change > 0.0 {
+                        "🔼"
+                    } else if *change < 0.0 {
+                        "🔽"
+                    } else {
+                        " "
+                    };
+                    let change_pct = state
+                        .latest_price_map
+                        .get(m)
+                        .and_then(|latest| percent_change(latest - change, *latest))
+                        .unwrap_or(0.0);
+                    let color = change_color(change_pct, &args.change_thresholds);
+
+                    // Format change text appropriately based on market
+                    // Rupiah prices are whole numbers, so their changes are too
+                    let change_text = if *change != 0.0 {
+                        let decimals = if state.currency(m) == Currency::Idr {
+                            0
+                        } else {
+                            2
+                        };
+                        format!("({:.*})", decimals, change)
+                    } else {
+                        String::new()
+                    };
+
+                    let style = if i == selection.market {
+                        Style::default()
+                            .fg(Color::Yellow)
+                            .add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default().fg(color)
+                    };
+
+                    // Tag chips sit between the name and the change so the
+                    // narrow sidebar clips the change before the tags
+                    let name_style = if i == selection.market {
+                        style
+                    } else {
+                        Style::default().fg(market_color(&args.market_colors, &state.markets, m))
+                    };
+                    let mut spans = vec![Span::styled(
+                        format!("{} {}", icon, display_name(&args.display_names, m)),
+                        name_style,
+                    )];
+                    for tag in &state.settings.for_market(m).tags {
+                        spans.push(Span::raw(" "));
+                        spans.push(tag_chip(tag));
+                    }
+                    spans.push(Span::styled(format!(" {}", change_text), style));
+
+                    // A second line keeps the notice readable in the narrow
+                    // sidebar
+                    let mut lines = vec![Line::from(spans)];
+                    if let Some(FeedStatus::Reconnecting { .. }) = state.feed_status.get(m) {
+                        lines.push(Line::styled(
+                            "   reconnecting…",
+                            Style::default().fg(Color::DarkGray),
+                        ));
+                    }
+                    if let Some(quotes) = state.quotes.get(m).filter(|q| q.len() > 1) {
+                        let closes = quotes.iter().map(|(_, close)| *close);
+                        let low = closes.clone().fold(f64::INFINITY, f64::min);
+                        let high = closes.fold(f64::NEG_INFINITY, f64::max);
+                        if let Some(spread) = percent_change(low, high) {
+                            lines.push(Line::styled(
+                                format!("   spread {:.2}%", spread),
+                                Style::default().fg(Color::DarkGray),
+                            ));
+                        }
+
+                        // Each exchange against the composite, for the
+                        // selected market only to keep the list short
+                        if i == selection.market
+                            && let Some(composite) = state.latest_price_map.get(m)
+                        {
+                            for (name, close) in quotes {
+                                let diff = percent_change(*composite, *close).unwrap_or(0.0);
+                                lines.push(Line::styled(
+                                    format!("   {} {:+.2}%", name, diff),
+                                    Style::default().fg(Color::DarkGray),
+                                ));
+                            }
+                        }
+                    }
+
+                    ListItem::new(lines)
+                })
+                .collect();
+
+            let block = Block::default()
+                .title(if selection.auto_follow {
+                    " Markets (auto) "
+                } else {
+                    " Markets "
+                })
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::DarkGray));
+
+            // The list state keeps its scroll offset between frames so the
+            // selected market stays visible when the watchlist overflows
+            market_list_state.select(Some(selection.market));
+            let list = List::new(items).block(block);
+
+            if panels.view_mode == ViewMode::Normal {
+                f.render_stateful_widget(list, chunks[0], &mut market_list_state);
+            }
+
+            let selected = &state.markets[selection.market];
+            let selected_name = display_name(&args.display_names, selected);
+            if let Some(area) = tape_area {
+                render_trade_tape(
+                    f,
+                    area,
+                    state.trades.get(selected).map_or(&[], Vec::as_slice),
+                    |price| {
+                        state
+                            .currency(selected)
+                            .format_price(price, state.number_format)
+                    },
+                    &args.theme,
+                );
+            }
+            if let Some(area) = pattern_area {
+                let events: Vec<(i64, &str, Pattern)> = state
+                    .pattern_log
+                    .iter()
+                    .map(|(time, market, pattern)| {
+                        (*time, display_name(&args.display_names, market), *pattern)
+                    })
+                    .collect();
+                render_pattern_log(f, area, &events, &args.theme);
+            }
+            if let Some(area) = signal_area {
+                let signals: Vec<(i64, Cross, String)> = state
+                    .signals
+                    .get(selected)
+                    .map_or(&[][..], Vec::as_slice)
+                    .iter()
+                    .map(|signal| {
+                        let price = state
+                            .currency(selected)
+                            .format_price(signal.price, state.number_format);
+                        (signal.time, signal.cross, price)
+                    })
+                    .collect();
+                render_signal_log(f, area, &signals, panels.signal_scroll, &args.theme);
+            }
+            if panels.market_grid {
+                let page = selection.market / GRID_MARKETS * GRID_MARKETS;
+                let markets = &state.markets[page..(page + GRID_MARKETS).min(state.markets.len())];
+                for (i, (market, cell)) in markets
+                    .iter()
+                    .zip(grid_cells(chart_area, markets.len()))
+                    .enumerate()
+                {
+                    let candles = state.data.get(market).map_or(&[][..], Vec::as_slice);
+                    let candles = match state.heikin_ashi.get(market) {
+                        Some(transformed) if panels.heikin_ashi => transformed.candles(),
+                        _ => candles,
+                    };
+                    // A candle per column of the cell at most, which still
+                    // leaves each its own dot column beside the price labels
+                    let shown = chart_view
+                        .zoom
+                        .unwrap_or(candles.len())
+                        .min(cell.width.saturating_sub(2) as usize);
+                    let candles = &candles[candles.len() - shown.min(candles.len())..];
+
+                    let mut title = display_name(&args.display_names, market).to_string();
+                    if let Some(price) = state.latest_price_map.get(market) {
+                        title.push(' ');
+                        title.push_str(
+                            &state
+                                .currency(market)
+                                .format_price(*price, state.number_format),
+                        );
+                        if let Some(pct) = state
+                            .session_open
+                            .get(market)
+                            .and_then(|open| percent_change(*open, *price))
+                        {
+                            title.push_str(&format!(" {:+.2}%", pct));
+                        }
+                    }
+                    let options = CandlestickOptions {
+                        title,
+                        title_color: Some(market_color(
+                            &args.market_colors,
+                            &state.markets,
+                            market,
+                        )),
+                        border_color: (page + i == selection.market).then_some(Color::Yellow),
+                        pin_bar_ratio: if panels.indicators.pin_bars {
+                            args.pin_bar_ratio
+                        } else {
+                            0.0
+                        },
+                        volume_shading: panels.indicators.volume_shading,
+                        compact: args.compact_candles,
+                        style: state.settings.for_market(market).style,
+                        price_axis: Some((state.currency(market), state.number_format)),
+                        time_axis: true,
+                        grid: panels.grid,
+                        ..CandlestickOptions::default()
+                    };
+                    render_candlestick_chart(f, cell, candles, &options, &args.theme);
+                }
+            } else if let Some(candles) = state.data.get(selected) {
+                let view = chart_view.view(candles.len());
+                let (mut overlays, mut bands) =
+                    chart_overlays(&state, selected, &panels.indicators);
+                if panels.indicators.scripts {
+                    overlays.extend(script_overlays(scripts.outputs(selected)));
+                }
+                for overlay in &mut overlays {
+                    overlay.points = view.points(&overlay.points);
+                }
+                for band in &mut bands {
+                    band.lower = view.points(&band.lower);
+                    band.upper = view.points(&band.upper);
+                }
+                let mut markers = if panels.indicators.patterns {
+                    pattern_markers(
+                        candles,
+                        state.patterns.get(selected).map_or(&[], Vec::as_slice),
+                    )
+                } else {
+                    Vec::new()
+                };
+                if panels.indicators.signals {
+                    markers.extend(signal_markers(
+                        candles,
+                        state.signals.get(selected).map_or(&[], Vec::as_slice),
+                    ));
+                }
+                if let Some(placement) = popups.fib_placement {
+                    markers.extend(fib_cursor_markers(candles, placement));
+                }
+                let mut price_lines = price_lines(&state, selected, &panels.indicators);
+                if let Some(fib) = fibs.get(selected) {
+                    price_lines.extend(fib_lines(fib));
+                }
+                // Heikin-Ashi candles have the same times, so everything
+                // placed by candle still lines up
+                let chart_candles = view.candles(match state.heikin_ashi.get(selected) {
+                    Some(transformed) if panels.heikin_ashi => transformed.candles(),
+                    _ => candles,
+                });
+                let (stored, candles) = (candles, view.candles(candles));
+                let comparison = selection
+                    .compared
+                    .filter(|&c| c != selection.market)
+                    .and_then(|c| {
+                        let other = &state.markets[c];
+                        let other_candles = state.data.get(other)?;
+                        // Ends as far back from its newest candle as the view
+                        let other_candles = &other_candles
+                            [..other_candles.len().saturating_sub(stored.len() - view.end)];
+                        let points = comparison_series(candles, other_candles);
+                        let (first, last) = (points.first()?, points.last()?);
+                        let window_change = |from: f64, to: f64| {
+                            percent_change(from, to)
+                                .map(|pct| format!(" {:+.2}%", pct))
+                                .unwrap_or_default()
+                        };
+                        // Points sit at x = i + 0.5 of the candle they match
+                        let change = window_change(
+                            candles[first.0 as usize].close,
+                            candles[last.0 as usize].close,
+                        );
+                        Some(Comparison {
+                            label: format!("{}{}", selected_name, change),
+                            color: market_color(&args.market_colors, &state.markets, selected),
+                            line: Overlay {
+                                label: format!(
+                                    "{}{}",
+                                    display_name(&args.display_names, other),
+                                    window_change(first.1, last.1)
+                                ),
+                                color: market_color(&args.market_colors, &state.markets, other),
+                                points,
+                            },
+                        })
+                    });
+                let candlestick_options = CandlestickOptions {
+                    title: format!(
+                        "{} {} Chart{}{}",
+                        selected_name,
+                        if panels.heikin_ashi {
+                            "Heikin-Ashi"
+                        } else {
+                            "Candlestick"
+                        },
+                        match stored.len() - view.end {
+                            0 => String::new(),
+                            back => format!(" [{} back, f to follow]", back),
+                        },
+                        if args.sandbox { " [SANDBOX]" } else { "" }
+                    ),
+                    title_color: Some(market_color(&args.market_colors, &state.markets, selected)),
+                    border_color: None,
+                    pin_bar_ratio: if panels.indicators.pin_bars {
+                        args.pin_bar_ratio
+                    } else {
+                        0.0
+                    },
+                    volume_shading: panels.indicators.volume_shading,
+                    compact: args.compact_candles,
+                    style: state.settings.for_market(selected).style,
+                    y_bounds: panels.snapped_bounds.as_mut().and_then(|bounds| {
+                        let snapped =
+                            snapped_price_bounds(chart_candles, bounds.get(selected).copied())?;
+                        bounds.insert(selected.clone(), snapped);
+                        Some(snapped)
+                    }),
+                    overlays,
+                    comparison,
+                    bands,
+                    markers: view.markers(markers),
+                    price_lines,
+                    price_axis: Some((state.currency(selected), state.number_format)),
+                    time_axis: true,
+                    grid: panels.grid,
+                    last_price: state.latest_price_map.get(selected).map(|&price| {
+                        let change = state.price_changes.get(selected).copied().unwrap_or(0.0);
+                        let color = if change < 0.0 {
+                            Color::Red
+                        } else {
+                            Color::Green
+                        };
+                        (price, color)
+                    }),
+                    crosshair: chart_view.crosshair.and_then(|cursor| {
+                        let index = stored
+                            .len()
+                            .checked_sub(cursor + 1)?
+                            .checked_sub(view.start)?;
+                        let candle = chart_candles.get(index)?;
+                        let previous = index.checked_sub(1).map(|i| &chart_candles[i]);
+                        Some(Crosshair {
+                            index,
+                            readout: candle_readout(&state, selected, candle, previous),
+                        })
+                    }),
+                };
+                let plot = render_candlestick_chart(
+                    f,
+                    chart_chunks[0],
+                    chart_candles,
+                    &candlestick_options,
+                    &args.theme,
+                );
+
+                for (subpanel, &area) in subpanels.iter().zip(&chart_chunks[1..]) {
+                    // Perpetual stats take the top row of the volume panel
+                    let area = match state.derivatives.get(selected) {
+                        Some(stats)
+                            if matches!(
+                                subpanel,
+                                SubPanel::Volume | SubPanel::Depth | SubPanel::Macd | SubPanel::Obv
+                            ) && area.height > 4 =>
+                        {
+                            f.render_widget(
+                                Paragraph::new(derivatives_line(stats)),
+                                Rect { height: 1, ..area },
+                            );
+                            Rect {
+                                y: area.y + 1,
+                                height: area.height - 1,
+                                ..area
+                            }
+                        }
+                        _ => area,
+                    };
+                    match subpanel {
+                        SubPanel::Volume => render_volume_chart(
+                            f,
+                            area,
+                            Some(plot),
+                            candles,
+                            panels.grid,
+                            &args.theme,
+                        ),
+                        SubPanel::Depth => render_depth_chart(
+                            f,
+                            area,
+                            state.books.get(selected).unwrap_or(&OrderBook::default()),
+                            &args.theme,
+                        ),
+                        SubPanel::Ratio => {
+                            if let Some(benchmark) = benchmark_market
+                                && let Some(benchmark_candles) = state.data.get(benchmark)
+                            {
+                                let title = format!(
+                                    "{} / {}",
+                                    selected_name,
+                                    display_name(&args.display_names, benchmark)
+                                );
+                                render_ratio_chart(
+                                    f,
+                                    area,
+                                    &ratio_series(candles, benchmark_candles),
+                                    candles.len(),
+                                    &title,
+                                    &args.theme,
+                                );
+                            }
+                        }
+                        SubPanel::Roc => render_roc_chart(
+                            f,
+                            area,
+                            &view.points(&roc(stored, args.roc_period)),
+                            candles.len(),
+                            &format!("ROC({})", args.roc_period),
+                            &args.theme,
+                        ),
+                        SubPanel::Macd => {
+                            let (fast, slow, signal) = state.settings.for_market(selected).macd;
+                            let part = |series| {
+                                view.points(&state.indicator_points(selected, Study::Macd(series)))
+                            };
+                            render_macd_chart(
+                                f,
+                                area,
+                                &Macd {
+                                    line: part(MacdSeries::Line),
+                                    signal: part(MacdSeries::Signal),
+                                    histogram: part(MacdSeries::Histogram),
+                                },
+                                candles.len(),
+                                &format!("MACD({}, {}, {})", fast, slow, signal),
+                                &args.theme,
+                            );
+                        }
+                        SubPanel::Rsi => render_rsi_chart(
+                            f,
+                            area,
+                            &view.points(&state.indicator_points(selected, Study::Rsi)),
+                            candles.len(),
+                            &format!("RSI({})", state.settings.for_market(selected).rsi_period),
+                            &args.theme,
+                        ),
+                        SubPanel::Stochastic => {
+                            let (lookback, smoothing, d) =
+                                state.settings.for_market(selected).stochastic;
+                            render_stochastic_chart(
+                                f,
+                                area,
+                                &view.points(&state.indicator_points(
+                                    selected,
+                                    Study::Stochastic(StochasticSeries::K),
+                                )),
+                                &view.points(&state.indicator_points(
+                                    selected,
+                                    Study::Stochastic(StochasticSeries::D),
+                                )),
+                                candles.len(),
+                                &format!("Stoch({}, {}, {})", lookback, smoothing, d),
+                                &args.theme,
+                            );
+                        }
+                        SubPanel::Obv => render_series_pane(
+                            f,
+                            area,
+                            candles,
+                            &SeriesPane {
+                                title: "OBV",
+                                series: vec![(
+                                    "OBV",
+                                    &view.points(&state.indicator_points(selected, Study::Obv)),
+                                    Color::LightGreen,
+                                )],
+                                graph_type: GraphType::Line,
+                                y_bounds: None,
+                                grid: panels.grid,
+                            },
+                            |v| format!("{:.0}", v),
+                            &args.theme,
+                        ),
+                        SubPanel::Script(i) => {
+                            if let Some(output) = scripts.outputs(selected).get(*i) {
+                                render_script_pane(
+                                    f,
+                                    area,
+                                    candles,
+                                    output,
+                                    view,
+                                    panels.grid,
+                                    &args.theme,
+                                );
+                            }
+                        }
+                        SubPanel::Atr => render_atr_chart(
+                            f,
+                            area,
+                            &view.points(&state.indicator_points(selected, Study::Atr)),
+                            candles.len(),
+                            &format!("ATR({})", state.settings.for_market(selected).atr_period),
+                            |price| {
+                                state
+                                    .currency(selected)
+                                    .format_price(price, state.number_format)
+                            },
+                            &args.theme,
+                        ),
+                    }
+                }
+
+                if let Some(latest_price) = state.latest_price_map.get(selected) {
+                    let price_text = match state.currency(selected) {
+                        Currency::Usd => {
+                            format!("USD{:>15}", format_usd(*latest_price, state.number_format))
+                        }
+                        Currency::Idr => {
+                            format!("Rp{:>16}", format_idr(*latest_price, state.number_format))
+                        }
+                        other => other.format_price(*latest_price, state.number_format),
+                    };
+
+                    let change_text = state
+                        .session_open
+                        .get(selected)
+                        .and_then(|open| percent_change(*open, *latest_price))
+                        .map(|pct| format!("{:+.2}%", pct))
+                        .unwrap_or_default();
+
+                    // Large IDR prices can overflow narrow panels, so the
+                    // longer variants fall back to the shorter text
+                    let width = chart_chunks[1].width;
+                    let info_text = match panels.info_mode {
+                        InfoMode::Price => price_text,
+                        InfoMode::Change => change_text,
+                        InfoMode::Both => {
+                            fit_width(format!("{} {}", change_text, price_text), price_text, width)
+                        }
+                    };
+                    let named_text = fit_width(
+                        format!("{} {}", selected_name, info_text),
+                        info_text.clone(),
+                        width,
+                    );
+                    // The typical candle range goes first when space runs out
+                    let info_text = match state.indicator_points(selected, Study::Atr).last() {
+                        Some(&(_, atr)) => fit_width(
+                            format!(
+                                "{} ATR {} {}",
+                                selected_name,
+                                state
+                                    .currency(selected)
+                                    .format_price(atr, state.number_format),
+                                info_text
+                            ),
+                            named_text,
+                            width,
+                        ),
+                        None => named_text,
+                    };
+
+                    let info_block = Paragraph::new(Span::styled(
+                        info_text,
+                        Style::default()
+                            .fg(Color::Cyan)
+                            .add_modifier(Modifier::BOLD),
+                    ))
+                    .alignment(Alignment::Right);
+
+                    let info_area = Rect {
+                        x: chart_chunks[1].x,
+                        y: chart_chunks[1].y + chart_chunks[1].height.saturating_sub(1),
+                        width: chart_chunks[1].width,
+                        height: 1,
+                    };
+
+                    f.render_widget(info_block, info_area);
+
+                    if args.debug {
+                        let debug_text = format!(
+                            " vol {:.4}% realized / {:.4}% configured ",
+                            realized_vol(stored),
+                            args.simulator.volatility_pct(selected)
+                        );
+                        let debug_area = Rect {
+                            x: chart_chunks[0].x + 1,
+                            y: chart_chunks[0].y,
+                            width: chart_chunks[0].width.saturating_sub(2),
+                            height: 1,
+                        };
+
+                        f.render_widget(
+                            Paragraph::new(debug_text)
+                                .style(Style::default().fg(Color::DarkGray))
+                                .alignment(Alignment::Right),
+                            debug_area,
+                        );
+                    }
+                }
+            }
+
+            if let Some(message) = &status_message
+                && Instant::now() < message.until
+            {
+                let status_area = Rect {
+                    x: chunks[1].x + 1,
+                    y: chunks[1].y + chunks[1].height.saturating_sub(1),
+                    width: chunks[1].width.saturating_sub(2),
+                    height: 1,
+                };
+                f.render_widget(
+                    Paragraph::new(format!(" {} ", message.text))
+                        .style(Style::default().fg(Color::Yellow)),
+                    status_area,
+                );
+            }
+
+            if let Some(placement) = popups.fib_placement {
+                let hint_area = Rect {
+                    x: chunks[1].x + 1,
+                    y: chunks[1].y + chunks[1].height.saturating_sub(1),
+                    width: chunks[1].width.saturating_sub(2),
+                    height: 1,
+                };
+                let swing = if placement.high.is_none() {
+                    "high"
+                } else {
+                    "low"
+                };
+                f.render_widget(
+                    Paragraph::new(format!(
+                        " swing {}: ←/→ move · enter mark · esc cancel ",
+                        swing
+                    ))
+                    .style(Style::default().fg(FIB).bg(Color::Black)),
+                    hint_area,
+                );
+            }
+
+            if chart_view.crosshair.is_some() {
+                let hint_area = Rect {
+                    x: chunks[1].x + 1,
+                    y: chunks[1].y + chunks[1].height.saturating_sub(1),
+                    width: chunks[1].width.saturating_sub(2),
+                    height: 1,
+                };
+                f.render_widget(
+                    Paragraph::new(" crosshair: ←/→ move · esc close ")
+                        .style(Style::default().fg(Color::Gray).bg(Color::Black)),
+                    hint_area,
+                );
+            }
+
+            if recorder.is_some() {
+                let rec_area = Rect {
+                    x: chunks[1].x + 1,
+                    y: chunks[1].y + chunks[1].height.saturating_sub(1),
+                    width: chunks[1].width.saturating_sub(2),
+                    height: 1,
+                };
+                f.render_widget(
+                    Paragraph::new(" ● REC ")
+                        .alignment(Alignment::Right)
+                        .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+                    rec_area,
+                );
+            }
+
+            // The stall banner below takes the same row when both apply
+            if let Some(at) = state
+                .restarted
+                .get(&state.markets[selection.market])
+                .filter(|at| at.elapsed() < RESTART_WARNING_DURATION)
+            {
+                let banner_area = Rect {
+                    height: 1,
+                    ..chunks[1]
+                };
+                let banner = Paragraph::new(format!(
+                    "Feed stopped responding and was restarted {}s ago",
+                    at.elapsed().as_secs()
+                ))
+                .alignment(Alignment::Center)
+                .style(
+                    Style::default()
+                        .fg(Color::Black)
+                        .bg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                );
+
+                f.render_widget(Clear, banner_area);
+                f.render_widget(banner, banner_area);
+            }
+
+            let since_last_candle = last_candle_at.elapsed();
+            // A paused replay is quiet on purpose
+            let replay_paused = replay_control.as_ref().is_some_and(|c| c.is_paused());
+            if since_last_candle >= stall_timeout && !replay_paused {
+                let banner_area = Rect {
+                    height: 1,
+                    ..chunks[1]
+                };
+                let banner = Paragraph::new(format!(
+                    "No data for {}s, the feed may be stalled",
+                    since_last_candle.as_secs()
+                ))
+                .alignment(Alignment::Center)
+                .style(
+                    Style::default()
+                        .fg(Color::White)
+                        .bg(Color::Red)
+                        .add_modifier(Modifier::BOLD),
+                );
+
+                f.render_widget(Clear, banner_area);
+                f.render_widget(banner, banner_area);
+            }
+
+            if let Some(field) = popups.bands {
+                let settings = state.settings.for_market(&state.markets[selection.market]);
+                let row = |name: &str, value: String, this: BandField| {
+                    let style = if field == this {
+                        Style::default().add_modifier(Modifier::REVERSED)
+                    } else {
+                        Style::default()
+                    };
+                    Line::from(vec![
+                        Span::raw(format!(" {:<7}", name)),
+                        Span::styled(format!("◀ {} ▶", value), style),
+                    ])
+                };
+                let popup = Paragraph::new(vec![
+                    row(
+                        "Period",
+                        settings.bollinger_period.to_string(),
+                        BandField::Period,
+                    ),
+                    row(
+                        "Width",
+                        format!("{}σ", settings.bollinger_width),
+                        BandField::Width,
+                    ),
+                ])
+                .block(
+                    Block::default()
+                        .title("Bollinger Bands")
+                        .title_bottom(" ←/→ adjust · esc ")
+                        .borders(Borders::ALL)
+                        .border_style(Style::default().fg(Color::Blue)),
+                );
+                let popup_area = centered_rect(26, 4, size);
+
+                f.render_widget(Clear, popup_area);
+                f.render_widget(popup, popup_area);
+            }
+
+            if let Some(editor) = &popups.param_editor {
+                editor.form.render(f, size);
+            }
+            if let Some(form) = &popups.tag_form {
+                form.render(f, size);
+            }
+
+            if popups.confirming_quit {
+                let prompt = Paragraph::new("Quit? y/n")
+                    .alignment(Alignment::Center)
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_style(Style::default().fg(Color::Yellow)),
+                    );
+                let prompt_area = centered_rect(20, 3, size);
+
+                f.render_widget(Clear, prompt_area);
+                f.render_widget(prompt, prompt_area);
+            }
+        })?;
+
+        let elapsed = last_update.elapsed();
+        if elapsed < args.poll_timeout {
+            thread::sleep(args.poll_timeout - elapsed);
+        }
+        last_update = Instant::now();
+    }
+
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
+    terminal.show_cursor()?;
+
+    if timed_out {
+        println!(
+            "{} candles in {}s",
+            candles_received,
+            started.elapsed().as_secs()
+        );
+        println!("{}", summary_line(&state));
+    }
+
+    Ok(())
+}
+
+/// How long summary mode waits for the rest of a burst of updates, counted
+/// from its first message
+const SUMMARY_BURST: Duration = Duration::from_millis(50);
+
+/// Headless mode: prints `summary_line` after each burst of updates, which
+/// suits status bars that tail a command (tmux, polybar). Stops once
+/// `exit_after` is reached, like the UI.
+fn run_summary(
+    rx: mpsc::Receiver<Message>,
+    state: &mut AppState,
+    retention: RetentionPolicy,
+    mut recorder: Option<Recorder>,
+    exit_after: Option<ExitAfter>,
+) -> Result<(), io::Error> {
+    let started = Instant::now();
+    let deadline = match exit_after {
+        Some(ExitAfter::Duration(limit)) => Some(started + limit),
+        _ => None,
+    };
+    let receive = |until: Option<Instant>| match until {
+        Some(until) => rx
+            .recv_timeout(until.saturating_duration_since(Instant::now()))
+            .ok(),
+        None => rx.recv().ok(),
+    };
+    let mut candles_received = 0;
+
+    while let Some(message) = receive(deadline) {
+        // Markets update back to back, so wait briefly for the rest of the
+        // burst before printing. The wait is fixed from the first message so
+        // a steady stream of trades or depth can't hold the line back.
+        let burst_end = Instant::now() + SUMMARY_BURST;
+        let mut next = Some(message);
+        while let Some(message) = next {
+            if let Message::NewCandle(market, candle) = message {
+                if let Some(r) = recorder.as_mut() {
+                    r.record(&market, &candle)?;
+                }
+                state.push_candle(&market, candle, retention);
+                candles_received += 1;
+            }
+            next = receive(Some(deadline.map_or(burst_end, |d| d.min(burst_end))));
+        }
+
+        // A status bar closing its end of the pipe is a normal way to stop
+        match writeln!(io::stdout(), "{}", summary_line(state)) {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+            result => result?,
+        }
+
+        if exit_after.is_some_and(|exit| exit.reached(candles_received, started.elapsed())) {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// One line with every market's symbol, latest price and change since the
+/// session open, the change colored by direction with ANSI escapes
+fn summary_line(state: &AppState) -> String {
+    let mut line = String::new();
+
+    for m in &state.markets {
+        let Some(price) = state.latest_price_map.get(m) else {
+            continue;
+        };
+
+        let change = state
+            .session_open
+            .get(m)
+            .and_then(|open| percent_change(*open, *price))
+            .unwrap_or(0.0);
+        let color = if change > 0.0 {
+            style::Color::Green
+        } else if change < 0.0 {
+            style::Color::Red
+        } else {
+            style::Color::Grey
+        };
+
+        if !line.is_empty() {
+            line.push_str(" | ");
+        }
+        line.push_str(&format!(
+            "{} {} {}",
+            m,
+            state.currency(m).format_price(*price, state.number_format),
+            format!("{:+.2}%", change).with(color)
+        ));
+    }
+
+    line
+}
+
+/// How long a status message such as "indicators hidden" stays on screen
+const STATUS_MESSAGE_DURATION: Duration = Duration::from_secs(2);
+
+/// How long a warning stays on screen, long enough to be read in full
+const WARNING_DURATION: Duration = Duration::from_secs(10);
+
+/// A note shown along the bottom of the charts until it expires
+#[derive(Debug, Clone)]
+struct StatusMessage {
+    text: String,
+    until: Instant,
+}
+
+impl StatusMessage {
+    /// `text` for [`STATUS_MESSAGE_DURATION`]
+    fn new(text: impl Into<String>) -> Self {
+        StatusMessage::lasting(text, STATUS_MESSAGE_DURATION)
+    }
+
+    fn lasting(text: impl Into<String>, duration: Duration) -> Self {
+        StatusMessage {
+            text: text.into(),
+            until: Instant::now() + duration,
+        }
+    }
+}
+
+/// Room in the channel between the feed and the UI; past it the feed's
+/// messages are merged or dropped (see [`bounded`])
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Longest a feed may go without sending anything before it's considered
+/// hung and restarted
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How long the restart warning stays up after a feed was restarted
+const RESTART_WARNING_DURATION: Duration = Duration::from_secs(15);
+
+/// Most messages applied before drawing a frame, so a flood can't stall
+/// input and redraws
+const MAX_MESSAGES_PER_FRAME: usize = 1000;
+
+/// Trades kept per market for the trade tape
+const MAX_TAPE_TRADES: usize = 100;
+
+/// Detected patterns kept for the pattern log
+const MAX_LOGGED_PATTERNS: usize = 100;
+
+/// Golden and death crosses kept per market for the signals panel
+const MAX_SIGNALS: usize = 100;
+
+/// Tag first offered when toggling a tag from the keyboard
+const FAVORITE_TAG: &str = "favorite";
+
+/// Funding rate, when it's next paid and open interest on one line
+fn derivatives_line(stats: &PerpStats) -> Line<'static> {
+    let funding_pct = stats.funding_rate * 100.0;
+    // Positive funding means longs pay shorts
+    let funding_color = if funding_pct >= 0.0 {
+        Color::Green
+    } else {
+        Color::Red
+    };
+    let label = Style::default().fg(Color::DarkGray);
+
+    Line::from(vec![
+        Span::styled(" Funding ", label),
+        Span::styled(
+            format!("{:+.4}%", funding_pct),
+            Style::default().fg(funding_color),
+        ),
+        Span::styled(" next ", label),
+        Span::raw(format_clock(stats.next_funding)),
+        Span::styled("  OI ", label),
+        Span::raw(format!("{:.2}", stats.open_interest)),
+    ])
+}
+
+/// A chip with the first three letters of a tag, colored by the tag name so
+/// the same tag looks the same on every market
+fn tag_chip(tag: &str) -> Span<'static> {
+    const PALETTE: [Color; 6] = [
+        Color::Yellow,
+        Color::Cyan,
+        Color::Magenta,
+        Color::LightBlue,
+        Color::LightGreen,
+        Color::LightRed,
+    ];
+
+    let color = PALETTE[tag.bytes().map(usize::from).sum::<usize>() % PALETTE.len()];
+    let mut letters: String = tag.chars().take(3).collect::<String>().to_uppercase();
+    if letters.is_empty() {
+        letters.push('?');
+    }
+
+    Span::styled(letters, Style::default().fg(Color::Black).bg(color))
+}
+
+/// Colors of the EMA lines in the order of their periods, repeating when
+/// there are more periods
+const EMA_COLORS: [Color; 4] = [
+    Color::LightYellow,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::LightGreen,
+];
+
+/// Colors of the indicator scripts' series, in the order they're drawn
+const SCRIPT_COLORS: [Color; 4] = [
+    Color::LightBlue,
+    Color::LightRed,
+    Color::White,
+    Color::Magenta,
+];
+
+/// Longest script error shown in a legend or panel title
+const MAX_SCRIPT_ERROR_CHARS: usize = 60;
+
+/// Shading between the Bollinger Bands, dim enough for the candles to stand
+/// out on top
+const BAND_FILL: Color = Color::Rgb(28, 36, 64);
+
+/// Support and resistance lines, muted so they sit behind the candles
+const LEVEL_SUPPORT: Color = Color::Rgb(46, 125, 50);
+const LEVEL_RESISTANCE: Color = Color::Rgb(183, 28, 28);
+
+/// Pivot point lines, apart from the support and resistance colors
+const PIVOT: Color = Color::Rgb(158, 158, 158);
+const PIVOT_RESISTANCE: Color = Color::Rgb(230, 120, 40);
+const PIVOT_SUPPORT: Color = Color::Rgb(0, 150, 136);
+
+/// Fibonacci retracement levels and the marks they're placed with
+const FIB: Color = Color::Rgb(212, 170, 50);
+
+/// Accents handed out to markets without a configured color, in watchlist
+/// order. Green and red are left out so accents don't read as up/down.
+const MARKET_PALETTE: [Color; 6] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::LightBlue,
+    Color::LightYellow,
+    Color::LightMagenta,
+    Color::Blue,
+];
+
+/// Lines and shaded bands to draw over `candles` for the indicators turned
+/// on
+fn chart_overlays(
+    state: &AppState,
+    market: &str,
+    indicators: &Indicators,
+) -> (Vec<Overlay>, Vec<Band>) {
+    let settings = state.settings.for_market(market);
+    let points = |study| state.indicator_points(market, study);
+    let mut overlays = Vec::new();
+    let mut bands = Vec::new();
+
+    if indicators.sma {
+        overlays.push(Overlay {
+            label: format!("SMA({})", settings.sma_period),
+            color: Color::LightBlue,
+            points: points(Study::Sma),
+        });
+    }
+    if indicators.ema {
+        for (i, (&period, &color)) in settings
+            .ema_periods
+            .iter()
+            .zip(EMA_COLORS.iter().cycle())
+            .enumerate()
+        {
+            overlays.push(Overlay {
+                label: format!("EMA({})", period),
+                color,
+                points: points(Study::Ema(i)),
+            });
+        }
+    }
+    if indicators.bollinger {
+        let lower = points(Study::BollingerLower);
+        let upper = points(Study::BollingerUpper);
+        let outline = |points| Overlay {
+            label: String::new(),
+            color: Color::Blue,
+            points,
+        };
+        overlays.push(outline(upper.clone()));
+        overlays.push(outline(lower.clone()));
+        overlays.push(Overlay {
+            label: format!(
+                "BB({}, {})",
+                settings.bollinger_period, settings.bollinger_width
+            ),
+            color: Color::Blue,
+            points: points(Study::BollingerMiddle),
+        });
+        bands.push(Band {
+            color: BAND_FILL,
+            lower,
+            upper,
+        });
+    }
+    if indicators.vwap
+        && let Some(candles) = state.data.get(market)
+    {
+        // Only the latest session's line goes in the legend
+        let series = state
+            .indicator_sets
+            .get(market)
+            .map_or(&[][..], |set| set.series(Study::Vwap));
+        let sessions = session_points(series, candles, settings.session_start);
+        let count = sessions.len();
+        for (i, points) in sessions.into_iter().enumerate() {
+            overlays.push(Overlay {
+                label: if i + 1 == count {
+                    "VWAP".to_string()
+                } else {
+                    String::new()
+                },
+                color: Color::LightRed,
+                points,
+            });
+        }
+    }
+
+    (overlays, bands)
+}
+
+/// The crosshair readout for `candle` of `market`, with its change from the
+/// close of `previous`, or from its own open without one
+fn candle_readout(
+    state: &AppState,
+    market: &str,
+    candle: &Candle,
+    previous: Option<&Candle>,
+) -> Vec<String> {
+    let price = |price| {
+        state
+            .currency(market)
+            .format_price(price, state.number_format)
+    };
+    let change = percent_change(previous.map_or(candle.open, |p| p.close), candle.close)
+        .map_or_else(|| "-".to_string(), |change| format!("{:+.2}%", change));
+
+    vec![
+        format!("Time {}", format_time(candle.time)),
+        format!("O {}", price(candle.open)),
+        format!("H {}", price(candle.high)),
+        format!("L {}", price(candle.low)),
+        format!("C {}", price(candle.close)),
+        format!("V {:.4}", candle.volume),
+        format!("Chg {}", change),
+    ]
+}
+
+/// Most markets the market grid shows at once, in two rows of two
+const GRID_MARKETS: usize = 4;
+
+/// `area` split into a cell for each of `count` markets, two to a row, left
+/// to right and then top to bottom
+fn grid_cells(area: Rect, count: usize) -> Vec<Rect> {
+    if count == 0 {
+        return Vec::new();
+    }
+    let rows = count.div_ceil(2) as u32;
+    let columns = count.min(2) as u32;
+    Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![Constraint::Ratio(1, rows); rows as usize])
+        .split(area)
+        .iter()
+        .flat_map(|&row| {
+            Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(vec![Constraint::Ratio(1, columns); columns as usize])
+                .split(row)
+                .to_vec()
+        })
+        .take(count)
+        .collect()
+}
+
+/// An arrow for each of `signals` still among `candles`: up under a golden
+/// cross, down over a death cross
+fn signal_markers(candles: &[Candle], signals: &[Signal]) -> Vec<Marker> {
+    signals
+        .iter()
+        .filter_map(|signal| {
+            let index = candles
+                .binary_search_by_key(&signal.time, |c| c.time)
+                .ok()?;
+            let (above, symbol, color) = match signal.cross {
+                Cross::Golden => (false, "▲", Color::Green),
+                Cross::Death => (true, "▼", Color::Red),
+            };
+            Some(Marker {
+                index,
+                above,
+                symbol,
+                color,
+            })
+        })
+        .collect()
+}
+
+/// A marker for each of `patterns` over the candle it formed on, bullish
+/// ones under the candle and the rest over it
+fn pattern_markers(candles: &[Candle], patterns: &[(i64, Pattern)]) -> Vec<Marker> {
+    patterns
+        .iter()
+        .filter_map(|&(time, pattern)| {
+            let index = candles.binary_search_by_key(&time, |c| c.time).ok()?;
+            let (symbol, color) = match pattern {
+                Pattern::Doji => ("◇", Color::Gray),
+                Pattern::Hammer => ("△", Color::Green),
+                Pattern::BullishEngulfing => ("⇑", Color::Green),
+                Pattern::BearishEngulfing => ("⇓", Color::Red),
+            };
+            Some(Marker {
+                index,
+                above: pattern.bullish() != Some(true),
+                symbol,
+                color,
+            })
+        })
+        .collect()
+}
+
+/// Horizontal lines `indicators` asks for over `market`'s candles: support
+/// (under the latest close) and resistance (over it), each labelled with how
+/// many times price turned there, and the previous session's pivots
+fn price_lines(state: &AppState, market: &str, indicators: &Indicators) -> Vec<PriceLine> {
+    let mut lines = Vec::new();
+    let Some(last) = state.data.get(market).and_then(|c| c.last()) else {
+        return lines;
+    };
+
+    if indicators.levels {
+        for level in support_resistance(&state.data[market]) {
+            let (label, color) = if level.price < last.close {
+                ("S", LEVEL_SUPPORT)
+            } else {
+                ("R", LEVEL_RESISTANCE)
+            };
+            lines.push(PriceLine {
+                price: level.price,
+                color,
+                label: format!("{} ×{}", label, level.touches),
+            });
+        }
+    }
+
+    if indicators.pivots
+        && let Some(pivots) = state.sessions.get(market).and_then(|s| s.pivots())
+    {
+        for (label, price) in pivots.levels() {
+            let color = match label.as_bytes()[0] {
+                b'R' => PIVOT_RESISTANCE,
+                b'S' => PIVOT_SUPPORT,
+                _ => PIVOT,
+            };
+            lines.push(PriceLine {
+                price,
+                color,
+                label: label.to_string(),
+            });
+        }
+    }
+
+    lines
+}
+
+/// The cursor of `placement` over (or, for the swing low, under) its candle,
+/// and the swing high once it's marked
+fn fib_cursor_markers(candles: &[Candle], placement: FibPlacement) -> Vec<Marker> {
+    let mut markers = Vec::new();
+    if let Some(index) = candles.len().checked_sub(placement.cursor + 1) {
+        let marking_high = placement.high.is_none();
+        markers.push(Marker {
+            index,
+            above: marking_high,
+            symbol: if marking_high { "▼" } else { "▲" },
+            color: Color::White,
+        });
+    }
+    if let Some((time, _)) = placement.high
+        && let Ok(index) = candles.binary_search_by_key(&time, |c| c.time)
+    {
+        markers.push(Marker {
+            index,
+            above: true,
+            symbol: "◆",
+            color: FIB,
+        });
+    }
+    markers
+}
+
+/// Retracement levels of `fib`'s swing, measured back from whichever end
+/// came later
+fn fib_lines(fib: &Fib) -> Vec<PriceLine> {
+    let (from, to) = if fib.low.0 < fib.high.0 {
+        (fib.low.1, fib.high.1)
+    } else {
+        (fib.high.1, fib.low.1)
+    };
+
+    retracement(from, to)
+        .into_iter()
+        .map(|(ratio, price)| PriceLine {
+            price,
+            color: FIB,
+            label: format!("{}%", (ratio * 1000.0).round() / 10.0),
+        })
+        .collect()
+}
+
+/// Lines for the indicator scripts drawn over the candles. A failed script
+/// gets a red legend entry saying why.
+fn script_overlays(outputs: &[ScriptOutput]) -> Vec<Overlay> {
+    let mut overlays = Vec::new();
+    for output in outputs.iter().filter(|output| !output.pane) {
+        match &output.series {
+            Ok(series) => {
+                for (name, points) in series {
+                    overlays.push(Overlay {
+                        label: series_label(output, name),
+                        color: SCRIPT_COLORS[overlays.len() % SCRIPT_COLORS.len()],
+                        points: points.clone(),
+                    });
+                }
+            }
+            Err(e) => overlays.push(Overlay {
+                label: script_error(output, e),
+                color: Color::Red,
+                points: Vec::new(),
+            }),
+        }
+    }
+    overlays
+}
+
+/// An indicator script's own panel, or its error as the panel title
+fn render_script_pane(
+    f: &mut Frame,
+    area: Rect,
+    candles: &[Candle],
+    output: &ScriptOutput,
+    view: View,
+    grid: bool,
+    theme: &Theme,
+) {
+    match &output.series {
+        Ok(series) => {
+            let series: Vec<(&String, Vec<(f64, f64)>)> = series
+                .iter()
+                .map(|(name, points)| (name, view.points(points)))
+                .collect();
+            let labels: Vec<String> = series
+                .iter()
+                .map(|(name, _)| series_label(output, name))
+                .collect();
+            let pane = SeriesPane {
+                title: &output.name,
+                series: series
+                    .iter()
+                    .zip(&labels)
+                    .enumerate()
+                    .map(|(i, ((_, points), label))| {
+                        (
+                            label.as_str(),
+                            points.as_slice(),
+                            SCRIPT_COLORS[i % SCRIPT_COLORS.len()],
+                        )
+                    })
+                    .collect(),
+                graph_type: GraphType::Line,
+                y_bounds: None,
+                grid,
+            };
+            render_series_pane(f, area, candles, &pane, |v| format!("{:.2}", v), theme);
+        }
+        Err(e) => f.render_widget(
+            Paragraph::new(Span::styled(e.as_str(), Style::default().fg(Color::Red)))
+                .wrap(Wrap { trim: true })
+                .block(
+                    Block::default()
+                        .title(script_error(output, "failed"))
+                        .borders(Borders::ALL),
+                ),
+            area,
+        ),
+    }
+}
+
+/// A script series' name: the script's own for a single series, otherwise
+/// the script's followed by the series key
+fn series_label(output: &ScriptOutput, name: &str) -> String {
+    if name == output.name {
+        name.to_string()
+    } else {
+        format!("{} {}", output.name, name)
+    }
+}
+
+/// A script's name and error, cut short to fit a legend or title
+fn script_error(output: &ScriptOutput, error: &str) -> String {
+    let text = format!(
+        "{}: {}",
+        output.name,
+        error.lines().next().unwrap_or_default()
+    );
+    match text.char_indices().nth(MAX_SCRIPT_ERROR_CHARS) {
+        Some((cut, _)) => format!("{}…", &text[..cut]),
+        None => text,
+    }
+}
+
+/// Saves per-market changes to the settings file, as a status line saying
+/// how that went
+fn save_settings(settings: &SettingsFile) -> StatusMessage {
+    let message = match settings.save() {
+        Ok(()) => "settings saved",
+        Err(_) => "settings not saved",
+    };
+    StatusMessage::new(message)
+}
+
+/// One step of the Bollinger `field` in the bands popup, `direction` being
+/// -1 or 1
+fn adjust_bands(settings: &mut Settings, field: BandField, direction: i32) {
+    match field {
+        BandField::Period => {
+            settings.bollinger_period =
+                (settings.bollinger_period as i32 + direction).clamp(2, 200) as usize;
+        }
+        BandField::Width => {
+            settings.bollinger_width =
+                (settings.bollinger_width + 0.5 * direction as f64).clamp(0.5, 5.0);
+        }
+    }
+}
+
+/// A market's configured accent color, or its palette color by position
+fn market_color(colors: &HashMap<String, Color>, markets: &[String], market: &str) -> Color {
+    colors.get(market).copied().unwrap_or_else(|| {
+        let i = markets.iter().position(|m| m == market).unwrap_or(0);
+        MARKET_PALETTE[i % MARKET_PALETTE.len()]
+    })
+}
+
+/// Index of the market with the largest absolute move over the last
+/// `lookback` candles. The current selection is kept unless another market's
+/// move beats it by more than 20%, so near ties don't flip back and forth.
+fn most_active_market(state: &AppState, current: usize, lookback: usize) -> usize {
+    let recent_move = |i: usize| {
+        state
+            .data
+            .get(&state.markets[i])
+            .and_then(|candles| roc(candles, lookback).last().map(|&(_, r)| r.abs()))
+            .unwrap_or(0.0)
+    };
+
+    let current_move = recent_move(current);
+    let (best, best_move) = (0..state.markets.len()).map(|i| (i, recent_move(i))).fold(
+        (current, current_move),
+        |best, candidate| {
+            if candidate.1 > best.1 {
+                candidate
+            } else {
+                best
+            }
+        },
+    );
+
+    if best_move > current_move * 1.2 {
+        best
+    } else {
+        current
+    }
+}
+
+/// Color for a percent change: the strongest threshold the change reaches,
+/// falling back to green for gains and red for losses. Gains are matched
+/// against the thresholds from zero up and losses against the negative ones,
+/// so either way a bigger move gets the stronger color. No change is always
+/// gray.
+fn change_color(pct: f64, thresholds: &[ChangeThreshold]) -> Color {
+    if pct == 0.0 {
+        return Color::Gray;
+    }
+
+    // The thresholds are sorted highest first
+    let threshold = if pct > 0.0 {
+        thresholds
+            .iter()
+            .find(|t| t.min_pct >= 0.0 && pct >= t.min_pct)
+    } else {
+        thresholds
+            .iter()
+            .rev()
+            .find(|t| t.min_pct < 0.0 && pct <= t.min_pct)
+    };
+    match threshold {
+        Some(threshold) => threshold.color,
+        None if pct > 0.0 => Color::Green,
+        None => Color::Red,
+    }
+}
+
+/// A `width` x `height` rect centred in `area`, clamped to fit inside it
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+/// The alias configured for a market, or the symbol itself. Only use this for
+/// presentation; data routing and currency detection key off the symbol.
+fn display_name<'a>(names: &'a HashMap<String, String>, symbol: &'a str) -> &'a str {
+    names.get(symbol).map(String::as_str).unwrap_or(symbol)
+}
+
+fn fit_width(preferred: String, fallback: String, width: u16) -> String {
+    if preferred.chars().count() > width as usize {
+        fallback
+    } else {
+        preferred
+    }
+}
+
+fn percent_change(from: f64, to: f64) -> Option<f64> {
+    if from == 0.0 || !from.is_finite() || !to.is_finite() {
+        return None;
+    }
+
+    Some((to - from) / from * 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn start_prices_have_to_be_positive() {
+        let parse = |value: &str| {
+            Args::parse_from(["--start-price", value, "--settings", "/dev/null"].map(String::from))
+        };
+
+        let args = parse("USD/BTC=50000").unwrap();
+        assert_eq!(args.simulator.start_prices.get("USD/BTC"), Some(&50000.0));
+        for value in ["USD/BTC=0", "USD/BTC=-5", "USD/BTC=NaN", "USD/BTC=inf"] {
+            assert!(parse(value).is_err(), "{value}");
+        }
+    }
+
+    #[test]
+    fn retention_parses_counts_and_ages() {
+        assert_eq!(
+            RetentionPolicy::parse("500"),
+            Ok(RetentionPolicy::Count(500))
+        );
+        assert_eq!(
+            RetentionPolicy::parse("90s"),
+            Ok(RetentionPolicy::Age(Duration::from_secs(90)))
+        );
+        assert_eq!(
+            RetentionPolicy::parse("45m"),
+            Ok(RetentionPolicy::Age(Duration::from_secs(45 * 60)))
+        );
+        assert_eq!(
+            RetentionPolicy::parse("2h"),
+            Ok(RetentionPolicy::Age(Duration::from_secs(2 * 3600)))
+        );
+    }
+
+    #[test]
+    fn retention_rejects_zero_overflow_and_junk() {
+        for value in ["0", "0m", "", "h", "-5", "10d", "18446744073709551615h"] {
+            assert!(RetentionPolicy::parse(value).is_err(), "{value}");
+        }
+    }
+
+    #[test]
+    fn currency_follows_the_symbol_unless_overridden() {
+        let args = Args::parse_from(
+            ["--currency", "USD/BTC=eur", "--settings", "/dev/null"].map(String::from),
+        )
+        .unwrap();
+        let mut state = AppState::new(vec![
+            "USD/BTC".to_string(),
+            "IDR/ETH".to_string(),
+            "GBP/SOL".to_string(),
+        ]);
+        state.currencies = args.currencies;
+
+        assert_eq!(
+            state.currency("USD/BTC"),
+            Currency::Other("EUR".to_string())
+        );
+        assert_eq!(state.currency("IDR/ETH"), Currency::Idr);
+        assert_eq!(
+            state.currency("GBP/SOL"),
+            Currency::Other("GBP".to_string())
+        );
+        assert_eq!(
+            state
+                .currency("USD/BTC")
+                .format_price(1234.5, NumberFormat::Full),
+            "EUR 1,234.50"
+        );
+    }
+
+    #[test]
+    fn bigger_moves_get_the_stronger_change_color() {
+        let thresholds = ChangeThreshold::parse_list("2=lightgreen,0=green,-2=lightred").unwrap();
+
+        assert_eq!(change_color(3.0, &thresholds), Color::LightGreen);
+        assert_eq!(change_color(1.0, &thresholds), Color::Green);
+        assert_eq!(change_color(0.0, &thresholds), Color::Gray);
+        assert_eq!(change_color(-1.0, &thresholds), Color::Red);
+        assert_eq!(change_color(-3.0, &thresholds), Color::LightRed);
+    }
+}
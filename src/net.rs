@@ -0,0 +1,168 @@
+//! How the exchange feeds reach the network, so a proxy is configured once
+//! for every feed instead of per client.
+//!
+//! The proxy comes from `--proxy`, or else from the usual `ALL_PROXY`,
+//! `HTTPS_PROXY` and `HTTP_PROXY` variables. HTTP requests go through ureq's
+//! own proxy support; WebSockets are tunnelled with HTTP `CONNECT` or SOCKS
+//! before their handshake.
+//!
+//! In sandbox mode the feeds connect to their exchange's testnet instead of
+//! production, picked per feed with [`Network::endpoint`].
+
+use socks::{Socks4Stream, Socks5Stream};
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::TcpStream,
+};
+use tungstenite::{WebSocket, stream::MaybeTlsStream};
+use ureq::{Agent, Proxy, ProxyProtocol};
+
+pub type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+#[derive(Debug, Clone, Default)]
+pub struct Network {
+    proxy: Option<Proxy>,
+    sandbox: bool,
+}
+
+impl Network {
+    /// Goes through `proxy` (e.g. `socks5://127.0.0.1:1080`), or the proxy
+    /// from the environment when it's `None`
+    pub fn new(proxy: Option<&str>) -> Result<Self, String> {
+        let proxy = match proxy {
+            Some(url) => {
+                Some(Proxy::new(url).map_err(|e| format!("invalid proxy {}: {}", url, e))?)
+            }
+            None => Proxy::try_from_env(),
+        };
+
+        Ok(Network {
+            proxy,
+            sandbox: false,
+        })
+    }
+
+    /// Uses the exchanges' testnets instead of production
+    pub fn with_sandbox(mut self, sandbox: bool) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    pub fn is_sandbox(&self) -> bool {
+        self.sandbox
+    }
+
+    /// `sandbox` in sandbox mode, otherwise `production`
+    pub fn endpoint<'a>(&self, production: &'a str, sandbox: &'a str) -> &'a str {
+        if self.sandbox { sandbox } else { production }
+    }
+
+    /// An HTTP client going through the proxy
+    pub fn agent(&self) -> Agent {
+        Agent::config_builder()
+            .proxy(self.proxy.clone())
+            .build()
+            .into()
+    }
+
+    /// Opens a WebSocket to `url`, tunnelling through the proxy if there is
+    /// one
+    pub fn connect(&self, url: &str) -> Result<Socket, String> {
+        let Some(proxy) = &self.proxy else {
+            return tungstenite::connect(url)
+                .map(|(socket, _)| socket)
+                .map_err(|e| e.to_string());
+        };
+
+        let target = tungstenite::http::Uri::try_from(url).map_err(|e| e.to_string())?;
+        let host = target.host().ok_or("WebSocket URL has no host")?;
+        let port = target.port_u16().unwrap_or(match target.scheme_str() {
+            Some("wss") => 443,
+            _ => 80,
+        });
+
+        let stream = tunnel(proxy, host, port).map_err(|e| format!("proxy: {}", e))?;
+        tungstenite::client_tls(url, stream)
+            .map(|(socket, _)| socket)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// A TCP stream to `host:port` through `proxy`
+fn tunnel(proxy: &Proxy, host: &str, port: u16) -> io::Result<TcpStream> {
+    let address = (proxy.host(), proxy.port());
+    let credentials = proxy.username().zip(proxy.password().or(Some("")));
+
+    match proxy.protocol() {
+        ProxyProtocol::Http => {
+            let mut stream = TcpStream::connect(address)?;
+            let mut request = format!(
+                "CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n",
+                host = host,
+                port = port
+            );
+            if let Some((user, password)) = credentials {
+                let token = base64(format!("{}:{}", user, password).as_bytes());
+                request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", token));
+            }
+            request.push_str("\r\n");
+            stream.write_all(request.as_bytes())?;
+
+            // The proxy answers with a status line and headers, then the
+            // stream belongs to the target
+            let mut reader = BufReader::new(&stream);
+            let mut status = String::new();
+            reader.read_line(&mut status)?;
+            if status.split_whitespace().nth(1) != Some("200") {
+                return Err(io::Error::other(format!(
+                    "CONNECT refused: {}",
+                    status.trim()
+                )));
+            }
+            let mut line = String::new();
+            while reader.read_line(&mut line)? > 2 {
+                line.clear();
+            }
+
+            Ok(stream)
+        }
+        ProxyProtocol::Socks4 | ProxyProtocol::Socks4A => {
+            let user = proxy.username().unwrap_or("");
+            Ok(Socks4Stream::connect(address, (host, port), user)?.into_inner())
+        }
+        ProxyProtocol::Socks5 | ProxyProtocol::Socks5h => {
+            let stream = match credentials {
+                Some((user, password)) => {
+                    Socks5Stream::connect_with_password(address, (host, port), user, password)?
+                }
+                None => Socks5Stream::connect(address, (host, port))?,
+            };
+            Ok(stream.into_inner())
+        }
+        other => Err(io::Error::other(format!(
+            "{:?} proxies aren't supported for WebSockets",
+            other
+        ))),
+    }
+}
+
+/// Standard base64 with padding, for proxy credentials
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,147 @@
+//! Candlestick patterns, recognised one closed candle at a time.
+//!
+//! [`detect`] only looks at a candle and the one before it, so a caller can
+//! classify each candle as it closes instead of rescanning the window.
+
+use crate::Candle;
+
+/// Largest body, as a fraction of the high-low range, that still counts as a
+/// doji
+const DOJI_BODY_RATIO: f64 = 0.1;
+
+/// Shortest lower wick of a hammer, in bodies
+const HAMMER_WICK_RATIO: f64 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    /// Open and close nearly equal: indecision
+    Doji,
+    /// Small body on top of a long lower wick: sellers pushed down and were
+    /// rejected
+    Hammer,
+    /// An up candle whose body covers the previous down candle's
+    BullishEngulfing,
+    /// A down candle whose body covers the previous up candle's
+    BearishEngulfing,
+}
+
+impl Pattern {
+    pub fn name(self) -> &'static str {
+        match self {
+            Pattern::Doji => "Doji",
+            Pattern::Hammer => "Hammer",
+            Pattern::BullishEngulfing => "Bullish engulfing",
+            Pattern::BearishEngulfing => "Bearish engulfing",
+        }
+    }
+
+    /// Whether the pattern hints at a move up (`Some(true)`), down
+    /// (`Some(false)`) or neither
+    pub fn bullish(self) -> Option<bool> {
+        match self {
+            Pattern::Doji => None,
+            Pattern::Hammer | Pattern::BullishEngulfing => Some(true),
+            Pattern::BearishEngulfing => Some(false),
+        }
+    }
+}
+
+/// The pattern `candle` forms after `previous`, if any. Engulfing takes
+/// precedence, as it says the most; a doji is never also a hammer. Candles
+/// without a range or with non-finite prices form none.
+pub fn detect(previous: Option<&Candle>, candle: &Candle) -> Option<Pattern> {
+    let range = candle.high - candle.low;
+    if !range.is_finite() || range <= 0.0 || !candle.open.is_finite() || !candle.close.is_finite() {
+        return None;
+    }
+
+    if let Some(previous) = previous {
+        let up = candle.close > candle.open;
+        let previous_up = previous.close > previous.open;
+        let covers = candle.open.min(candle.close) <= previous.open.min(previous.close)
+            && candle.open.max(candle.close) >= previous.open.max(previous.close)
+            && (candle.close - candle.open).abs() > (previous.close - previous.open).abs();
+
+        if covers && up && previous.close < previous.open {
+            return Some(Pattern::BullishEngulfing);
+        }
+        if covers && candle.close < candle.open && previous_up {
+            return Some(Pattern::BearishEngulfing);
+        }
+    }
+
+    let body = (candle.close - candle.open).abs();
+    let upper_wick = candle.high - candle.open.max(candle.close);
+    let lower_wick = candle.open.min(candle.close) - candle.low;
+
+    if body <= range * DOJI_BODY_RATIO {
+        Some(Pattern::Doji)
+    } else if lower_wick >= body * HAMMER_WICK_RATIO && upper_wick <= body {
+        Some(Pattern::Hammer)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bar(open: f64, high: f64, low: f64, close: f64) -> Candle {
+        Candle {
+            time: 0,
+            open,
+            high,
+            low,
+            close,
+            volume: 1.0,
+        }
+    }
+
+    #[test]
+    fn single_candle_patterns() {
+        // A body of 1 in a range of 10, and of 1.1
+        assert_eq!(
+            detect(None, &bar(100.0, 105.0, 95.0, 101.0)),
+            Some(Pattern::Doji)
+        );
+        assert_eq!(detect(None, &bar(100.0, 105.0, 95.0, 101.1)), None);
+
+        // A lower wick of twice the body and an upper wick no longer than it
+        assert_eq!(
+            detect(None, &bar(100.0, 101.5, 98.0, 101.0)),
+            Some(Pattern::Hammer)
+        );
+        assert_eq!(detect(None, &bar(100.0, 101.5, 98.1, 101.0)), None);
+        assert_eq!(detect(None, &bar(100.0, 102.1, 98.0, 101.0)), None);
+    }
+
+    #[test]
+    fn engulfing_bodies_take_precedence() {
+        let down = bar(101.0, 101.5, 99.5, 100.0);
+        let up = bar(100.0, 101.5, 99.5, 101.0);
+
+        assert_eq!(
+            detect(Some(&down), &bar(99.8, 103.0, 99.0, 101.2)),
+            Some(Pattern::BullishEngulfing)
+        );
+        assert_eq!(
+            detect(Some(&up), &bar(101.2, 102.0, 99.0, 99.8)),
+            Some(Pattern::BearishEngulfing)
+        );
+        // The same direction as the candle before isn't engulfing
+        assert_eq!(detect(Some(&up), &bar(99.8, 103.0, 99.0, 101.2)), None);
+        // Nor is a body that only matches the one before
+        assert_eq!(
+            detect(Some(&down), &bar(100.0, 110.0, 90.0, 101.0)),
+            Some(Pattern::Doji)
+        );
+    }
+
+    #[test]
+    fn candles_without_a_range_form_no_pattern() {
+        assert_eq!(detect(None, &bar(100.0, 100.0, 100.0, 100.0)), None);
+        assert_eq!(detect(None, &bar(100.0, f64::NAN, 95.0, 100.0)), None);
+        assert_eq!(detect(None, &bar(f64::INFINITY, 105.0, 95.0, 100.0)), None);
+    }
+}
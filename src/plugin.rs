@@ -0,0 +1,173 @@
+//! Feeds the app can run on, looked up by name at startup.
+//!
+//! A feed is added by implementing [`FeedPlugin`] and registering it in
+//! [`builtin`] (or on a [`Registry`] of its own); `--feed` and `--composite`
+//! then accept its name, and the markets it registers join the watchlist.
+
+use crate::{
+    binance::BinanceFeed,
+    coinbase::{CoinbaseFeed, MIN_REQUEST_GAP},
+    indodax::{POLL_INTERVAL, indodax_or},
+    net::Network,
+    simulator::{Simulator, SimulatorConfig},
+    source::MarketDataSource,
+};
+use std::time::Duration;
+
+pub trait FeedPlugin {
+    /// What `--feed` and `--composite` call it
+    fn name(&self) -> &str;
+
+    /// Markets this feed has pairs for, in watchlist order
+    fn markets(&self) -> Vec<String>;
+
+    /// Whether the prices are made up rather than from an exchange
+    fn simulated(&self) -> bool {
+        false
+    }
+
+    /// Longest it normally goes between updates, for feeds that poll or
+    /// tick; `None` for streams the exchange pushes
+    fn update_interval(&self) -> Option<Duration> {
+        None
+    }
+
+    fn source(&self) -> Box<dyn MarketDataSource>;
+}
+
+/// Registered feeds, by name
+#[derive(Default)]
+pub struct Registry {
+    plugins: Vec<Box<dyn FeedPlugin>>,
+}
+
+impl Registry {
+    /// Adds `plugin`, replacing any registered under the same name
+    pub fn register(mut self, plugin: Box<dyn FeedPlugin>) -> Self {
+        self.plugins.retain(|p| p.name() != plugin.name());
+        self.plugins.push(plugin);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Result<&dyn FeedPlugin, String> {
+        self.plugins
+            .iter()
+            .find(|p| p.name() == name)
+            .map(|p| p.as_ref())
+            .ok_or_else(|| {
+                let names: Vec<&str> = self.plugins.iter().map(|p| p.name()).collect();
+                format!("unknown feed: {} (available: {})", name, names.join(", "))
+            })
+    }
+}
+
+/// The feeds that ship with the app
+pub fn builtin(
+    simulator: &SimulatorConfig,
+    coinbase_interval: Duration,
+    network: &Network,
+) -> Registry {
+    Registry::default()
+        .register(Box::new(SimulatorPlugin(simulator.clone())))
+        .register(Box::new(BinancePlugin(network.clone())))
+        .register(Box::new(CoinbasePlugin(coinbase_interval, network.clone())))
+        .register(Box::new(IndodaxPlugin(simulator.clone(), network.clone())))
+}
+
+fn pairs(markets: &[&str]) -> Vec<String> {
+    markets.iter().map(|m| m.to_string()).collect()
+}
+
+struct SimulatorPlugin(SimulatorConfig);
+
+impl FeedPlugin for SimulatorPlugin {
+    fn name(&self) -> &str {
+        "simulator"
+    }
+
+    fn markets(&self) -> Vec<String> {
+        self.0.markets()
+    }
+
+    fn simulated(&self) -> bool {
+        true
+    }
+
+    fn update_interval(&self) -> Option<Duration> {
+        Some(self.0.tick_interval)
+    }
+
+    fn source(&self) -> Box<dyn MarketDataSource> {
+        Box::new(Simulator::new(self.0.clone()))
+    }
+}
+
+struct BinancePlugin(Network);
+
+impl FeedPlugin for BinancePlugin {
+    fn name(&self) -> &str {
+        "binance"
+    }
+
+    fn markets(&self) -> Vec<String> {
+        pairs(&["USD/BTC", "USD/ETH"])
+    }
+
+    fn source(&self) -> Box<dyn MarketDataSource> {
+        Box::new(BinanceFeed {
+            network: self.0.clone(),
+        })
+    }
+}
+
+/// Polled every `.0`
+struct CoinbasePlugin(Duration, Network);
+
+impl FeedPlugin for CoinbasePlugin {
+    fn name(&self) -> &str {
+        "coinbase"
+    }
+
+    fn markets(&self) -> Vec<String> {
+        pairs(&["USD/BTC", "USD/ETH"])
+    }
+
+    /// A round is the interval plus the gap after each market's request
+    fn update_interval(&self) -> Option<Duration> {
+        Some(self.0 + MIN_REQUEST_GAP * self.markets().len() as u32)
+    }
+
+    fn source(&self) -> Box<dyn MarketDataSource> {
+        Box::new(CoinbaseFeed {
+            interval: self.0,
+            network: self.1.clone(),
+        })
+    }
+}
+
+/// Falls back to simulating with `.0` when Indodax is unreachable
+struct IndodaxPlugin(SimulatorConfig, Network);
+
+impl FeedPlugin for IndodaxPlugin {
+    fn name(&self) -> &str {
+        "indodax"
+    }
+
+    fn markets(&self) -> Vec<String> {
+        pairs(&["IDR/BTC", "IDR/ETH"])
+    }
+
+    /// The simulator it falls back to ticks faster than this
+    fn update_interval(&self) -> Option<Duration> {
+        Some(POLL_INTERVAL.max(self.0.tick_interval))
+    }
+
+    /// Indodax has no testnet, so sandbox mode always simulates
+    fn source(&self) -> Box<dyn MarketDataSource> {
+        let fallback = Box::new(Simulator::new(self.0.clone()));
+        if self.1.is_sandbox() {
+            return fallback;
+        }
+        indodax_or(self.1.clone(), fallback)
+    }
+}
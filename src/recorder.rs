@@ -0,0 +1,49 @@
+//! Appends received candles to a JSON-lines file, one object per line:
+//!
+//! ```text
+//! {"candle":{"close":67050.0,"high":67100.0,...},"market":"USD/BTC","received_at":1717000000123}
+//! ```
+//!
+//! `received_at` is wall-clock milliseconds when the candle reached the app,
+//! so a recording keeps both what arrived and when.
+
+use chrono::Utc;
+use crypto_tracking::Candle;
+use serde_json::json;
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+};
+
+#[derive(Debug)]
+pub struct Recorder {
+    file: BufWriter<File>,
+}
+
+impl Recorder {
+    /// Opens `path` for appending, so recording again continues the file
+    pub fn open(path: &str) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Recorder {
+            file: BufWriter::new(file),
+        })
+    }
+
+    pub fn record(&mut self, market: &str, candle: &Candle) -> io::Result<()> {
+        let line = json!({
+            "market": market,
+            "received_at": Utc::now().timestamp_millis(),
+            "candle": {
+                "time": candle.time,
+                "open": candle.open,
+                "high": candle.high,
+                "low": candle.low,
+                "close": candle.close,
+                "volume": candle.volume,
+            },
+        });
+        writeln!(self.file, "{}", line)?;
+        // Flushed per line so a crash loses at most the candle in flight
+        self.file.flush()
+    }
+}
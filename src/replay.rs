@@ -0,0 +1,226 @@
+//! Replays OHLCV rows from a CSV file through the normal candle channel.
+//!
+//! The file needs a header naming its columns, in any order:
+//!
+//! ```text
+//! market,time,open,high,low,close,volume
+//! USD/BTC,1717000000,67000.5,67100,66950,67050,12.3
+//! ```
+//!
+//! `time` is in seconds, or milliseconds when it's too large to be seconds.
+//! `market` and `volume` are optional: without a market column every row
+//! belongs to the first watchlist market, and missing volume counts as 0.
+//! Rows are played in file order at a fixed number of candles per second and
+//! can be paused and stepped through [`ReplayControl`].
+//!
+//! Session recordings (see `recorder`) replay the same way through
+//! [`CsvReplay::parse_recording`].
+
+use crate::source::{MarketDataSource, Message};
+use crypto_tracking::Candle;
+use serde_json::Value;
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Pause and single-step switches shared between the UI and the replay
+/// thread
+#[derive(Debug, Default)]
+pub struct ReplayControl {
+    paused: AtomicBool,
+    /// Candles to let through while paused
+    steps: AtomicUsize,
+}
+
+impl ReplayControl {
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn toggle_pause(&self) {
+        self.paused.fetch_xor(true, Ordering::Relaxed);
+    }
+
+    /// Plays the next candle while paused
+    pub fn step(&self) {
+        self.steps.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Blocks while paused until a step is requested or playback resumes
+    fn wait_turn(&self) {
+        while self.is_paused() {
+            if self
+                .steps
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+                .is_ok()
+            {
+                return;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CsvReplay {
+    rows: Vec<(String, Candle)>,
+    /// Candles played per second
+    speed: f64,
+    control: Arc<ReplayControl>,
+}
+
+impl CsvReplay {
+    /// Parses `source`; rows without a market column go to `default_market`
+    pub fn parse(source: &str, default_market: &str, speed: f64) -> Result<Self, String> {
+        let mut lines = source
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+        let (_, header) = lines.next().ok_or("replay file is empty")?;
+
+        let columns: Vec<String> = header.split(',').map(|c| c.trim().to_lowercase()).collect();
+        let column = |names: &[&str]| columns.iter().position(|c| names.contains(&c.as_str()));
+        let required =
+            |name: &str| column(&[name]).ok_or(format!("replay header has no {} column", name));
+
+        let market_col = column(&["market", "symbol"]);
+        let time_col = column(&["time", "timestamp"]).ok_or("replay header has no time column")?;
+        let open_col = required("open")?;
+        let high_col = required("high")?;
+        let low_col = required("low")?;
+        let close_col = required("close")?;
+        let volume_col = column(&["volume"]);
+
+        let mut rows = Vec::new();
+        for (number, line) in lines {
+            let error = |message: &str| format!("replay line {}: {}", number + 1, message);
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let number_at = |i: usize| -> Result<f64, String> {
+                fields
+                    .get(i)
+                    .and_then(|field| field.parse::<f64>().ok())
+                    .filter(|value| value.is_finite())
+                    .ok_or_else(|| error("expected a number in every price column"))
+            };
+
+            let time = number_at(time_col)?;
+            // Seconds stay below 1e11 for the next few thousand years
+            let time = if time >= 1e11 { time / 1000.0 } else { time };
+            let market = match market_col {
+                Some(i) => fields.get(i).ok_or_else(|| error("missing market"))?,
+                None => default_market,
+            };
+
+            rows.push((
+                market.to_string(),
+                Candle {
+                    time: time as i64,
+                    open: number_at(open_col)?,
+                    high: number_at(high_col)?,
+                    low: number_at(low_col)?,
+                    close: number_at(close_col)?,
+                    volume: volume_col.map_or(Ok(0.0), number_at)?,
+                },
+            ));
+        }
+
+        Ok(CsvReplay {
+            rows,
+            speed,
+            control: Arc::default(),
+        })
+    }
+
+    /// Parses a JSON-lines session recording, replaying its candles in the
+    /// order they were received
+    pub fn parse_recording(source: &str, speed: f64) -> Result<Self, String> {
+        let mut rows = Vec::new();
+        for (number, line) in source.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let entry: Value = serde_json::from_str(line)
+                .map_err(|e| format!("recording line {}: {}", number + 1, e))?;
+            let candle = &entry["candle"];
+            let row = (|| {
+                Some((
+                    entry["market"].as_str()?.to_string(),
+                    Candle {
+                        time: candle["time"].as_i64()?,
+                        open: candle["open"].as_f64()?,
+                        high: candle["high"].as_f64()?,
+                        low: candle["low"].as_f64()?,
+                        close: candle["close"].as_f64()?,
+                        volume: candle["volume"].as_f64()?,
+                    },
+                ))
+            })()
+            .ok_or(format!(
+                "recording line {}: not a recorded candle",
+                number + 1
+            ))?;
+            rows.push(row);
+        }
+
+        Ok(CsvReplay {
+            rows,
+            speed,
+            control: Arc::default(),
+        })
+    }
+
+    /// Every market in the file, in order of first appearance
+    pub fn markets(&self) -> Vec<String> {
+        let mut markets: Vec<String> = Vec::new();
+        for (market, _) in &self.rows {
+            if !markets.contains(market) {
+                markets.push(market.clone());
+            }
+        }
+        markets
+    }
+
+    pub fn control(&self) -> Arc<ReplayControl> {
+        self.control.clone()
+    }
+}
+
+impl MarketDataSource for CsvReplay {
+    fn subscribe(&self, markets: &[String]) -> mpsc::Receiver<Message> {
+        let (tx, rx) = mpsc::channel();
+        let rows: Vec<(String, Candle)> = self
+            .rows
+            .iter()
+            .filter(|(market, _)| markets.contains(market))
+            .cloned()
+            .collect();
+        let interval = Duration::from_secs_f64(1.0 / self.speed);
+        let control = self.control.clone();
+
+        thread::spawn(move || {
+            for (market, candle) in rows {
+                control.wait_turn();
+                if tx.send(Message::NewCandle(market, candle)).is_err() {
+                    return;
+                }
+                if !control.is_paused() {
+                    thread::sleep(interval);
+                }
+            }
+        });
+
+        rx
+    }
+
+    /// The file is the whole session, so there's no history before it
+    fn fetch_history(&self, _market: &str, _limit: usize) -> Result<Vec<Candle>, String> {
+        Ok(Vec::new())
+    }
+}
@@ -0,0 +1,229 @@
+//! Indicators written as Rhai scripts, loaded at startup so new ones don't
+//! need a rebuild.
+//!
+//! Every `*.rhai` file in `--scripts`, or else in
+//! `$XDG_CONFIG_HOME/crypto_tracking/indicators` (or
+//! `~/.config/crypto_tracking/indicators`) when that exists, is one indicator
+//! named after the file. It defines `indicator(candles)`, which gets the
+//! candles oldest first as maps with `time`, `open`, `high`, `low`, `close`
+//! and `volume`, and returns either an array with a value per candle or a
+//! map of such arrays to plot several series. A shorter array lines up with
+//! the newest candles, and `()` leaves a candle without a value:
+//!
+//! ```text
+//! // Midpoint of each candle's range
+//! fn indicator(candles) {
+//!     candles.map(|c| (c.high + c.low) / 2.0)
+//! }
+//! ```
+//!
+//! Series are drawn over the candles, on the price scale, unless the script
+//! sets `let pane = true;` at its top level to get a panel of its own.
+
+use crypto_tracking::Candle;
+use rhai::{AST, Array, CallFnOptions, Dynamic, Engine, Map, Scope};
+use std::{collections::HashMap, fs, io, path::PathBuf};
+
+/// Operations a script may take per call before it's stopped, so a runaway
+/// loop can't freeze the UI
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// The scripts directory used without `--scripts`; `None` without a home
+/// directory
+pub fn scripts_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(base.join("crypto_tracking").join("indicators"))
+}
+
+struct Script {
+    name: String,
+    pane: bool,
+    ast: AST,
+}
+
+/// A named series of `(x, value)` points in the candlestick x layout
+pub type Series = (String, Vec<(f64, f64)>);
+
+/// What one script returned for a market's candles
+#[derive(Debug, Clone)]
+pub struct ScriptOutput {
+    /// The script's file name without `.rhai`
+    pub name: String,
+    /// Whether it's drawn in its own panel rather than over the candles
+    pub pane: bool,
+    /// The script's series, or why it failed
+    pub series: Result<Vec<Series>, String>,
+}
+
+/// Identifies the candles a market's outputs were computed from, so they're
+/// only run again once the candles change
+type Fingerprint = (usize, Option<i64>, Option<u64>);
+
+#[derive(Default)]
+pub struct Scripts {
+    engine: Engine,
+    scripts: Vec<Script>,
+    outputs: HashMap<String, (Fingerprint, Vec<ScriptOutput>)>,
+}
+
+impl Scripts {
+    /// The scripts in `dir`, or in [`scripts_dir`] when that's `None`. Only
+    /// an explicitly given directory has to exist; a script that doesn't
+    /// compile is an error either way.
+    pub fn load(dir: Option<&str>) -> Result<Self, String> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let (dir, required) = match dir {
+            Some(dir) => (PathBuf::from(dir), true),
+            None => match scripts_dir() {
+                Some(dir) => (dir, false),
+                None => return Ok(Scripts::default()),
+            },
+        };
+
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !required => {
+                return Ok(Scripts::default());
+            }
+            Err(e) => return Err(format!("reading {}: {}", dir.display(), e)),
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+            .collect();
+        paths.sort();
+
+        let mut scripts = Vec::new();
+        for path in paths {
+            let error = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+            let ast = engine.compile_file(path.clone()).map_err(|e| error(&e))?;
+            let mut scope = Scope::new();
+            engine
+                .run_ast_with_scope(&mut scope, &ast)
+                .map_err(|e| error(&e))?;
+            if !ast.iter_functions().any(|f| f.name == "indicator") {
+                return Err(error(&"no indicator(candles) function"));
+            }
+
+            scripts.push(Script {
+                name: path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                pane: scope.get_value::<bool>("pane").unwrap_or(false),
+                ast,
+            });
+        }
+
+        Ok(Scripts {
+            engine,
+            scripts,
+            outputs: HashMap::new(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty()
+    }
+
+    /// Runs every script over `market`'s `candles`, unless they're the same
+    /// candles as last time
+    pub fn refresh(&mut self, market: &str, candles: &[Candle]) {
+        let fingerprint = (
+            candles.len(),
+            candles.last().map(|c| c.time),
+            candles.last().map(|c| c.close.to_bits()),
+        );
+        if self.scripts.is_empty()
+            || self
+                .outputs
+                .get(market)
+                .is_some_and(|(last, _)| *last == fingerprint)
+        {
+            return;
+        }
+
+        let array: Array = candles.iter().map(candle_map).collect();
+        let outputs = self
+            .scripts
+            .iter()
+            .map(|script| ScriptOutput {
+                name: script.name.clone(),
+                pane: script.pane,
+                series: self.run(script, array.clone(), candles.len()),
+            })
+            .collect();
+        self.outputs
+            .insert(market.to_string(), (fingerprint, outputs));
+    }
+
+    /// Outputs from the last [`Scripts::refresh`] of `market`
+    pub fn outputs(&self, market: &str) -> &[ScriptOutput] {
+        self.outputs
+            .get(market)
+            .map_or(&[], |(_, outputs)| outputs.as_slice())
+    }
+
+    fn run(&self, script: &Script, candles: Array, count: usize) -> Result<Vec<Series>, String> {
+        let options = CallFnOptions::new().eval_ast(false);
+        let result: Dynamic = self
+            .engine
+            .call_fn_with_options(
+                options,
+                &mut Scope::new(),
+                &script.ast,
+                "indicator",
+                (candles,),
+            )
+            .map_err(|e| e.to_string())?;
+
+        if result.is_map() {
+            let map = result.cast::<Map>();
+            map.into_iter()
+                .map(|(name, values)| Ok((name.to_string(), points(values, count)?)))
+                .collect()
+        } else {
+            Ok(vec![(script.name.clone(), points(result, count)?)])
+        }
+    }
+}
+
+fn candle_map(candle: &Candle) -> Dynamic {
+    let mut map = Map::new();
+    map.insert("time".into(), Dynamic::from_int(candle.time));
+    map.insert("open".into(), Dynamic::from_float(candle.open));
+    map.insert("high".into(), Dynamic::from_float(candle.high));
+    map.insert("low".into(), Dynamic::from_float(candle.low));
+    map.insert("close".into(), Dynamic::from_float(candle.close));
+    map.insert("volume".into(), Dynamic::from_float(candle.volume));
+    map.into()
+}
+
+/// `values` (an array of numbers or `()`) as chart points, lined up with the
+/// newest of `count` candles
+fn points(values: Dynamic, count: usize) -> Result<Vec<(f64, f64)>, String> {
+    let type_name = values.type_name();
+    let values = values
+        .try_cast::<Array>()
+        .ok_or_else(|| format!("expected an array of numbers, got {}", type_name))?;
+    let offset = count.saturating_sub(values.len());
+
+    Ok(values
+        .iter()
+        .enumerate()
+        .filter_map(|(i, value)| {
+            let value = value
+                .as_float()
+                .ok()
+                .or_else(|| value.as_int().ok().map(|v| v as f64))?;
+            value
+                .is_finite()
+                .then_some(((offset + i) as f64 + 0.5, value))
+        })
+        .collect())
+}
@@ -0,0 +1,392 @@
+//! Indicator and chart settings kept in a file, so they don't have to be
+//! passed on every run.
+//!
+//! The file has one `key = value` setting per line:
+//!
+//! ```text
+//! # comments and blank lines are ignored
+//! sma = 20          # SMA period
+//! ema = 9, 21, 50   # EMA periods drawn over the candles
+//! bollinger = 20, 2 # Bollinger Bands period and width in standard deviations
+//! rsi = 14          # RSI period
+//! atr = 14          # average true range period
+//! macd = 12, 26, 9  # MACD fast, slow and signal periods
+//! stochastic = 14, 3, 3 # stochastic lookback, %K smoothing and %D period
+//! cross = 50, 200   # fast and slow SMAs whose crossings are signals
+//! session = 00:00   # local time VWAP resets at each day
+//! style = candles   # candles, or bars for OHLC bars
+//! tags = hodl, watch # chips shown beside the market in the sidebar
+//! ```
+//!
+//! Settings for a single market go in a section named after it, after the
+//! ones for every market, and override those:
+//!
+//! ```text
+//! [USD/BTC]
+//! sma = 50
+//! ```
+//!
+//! It's read from `--settings`, or else from
+//! `$XDG_CONFIG_HOME/crypto_tracking/settings.conf` (or
+//! `~/.config/crypto_tracking/settings.conf`) when that exists. Missing
+//! settings keep their defaults. Changes made in the app are saved to the
+//! market sections, which are rewritten; the rest of the file is left as
+//! it was.
+
+use chrono::NaiveTime;
+use crypto_tracking::chart::CandleStyle;
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub sma_period: usize,
+    /// Periods of the EMAs drawn over the candles, each in its own color
+    pub ema_periods: Vec<usize>,
+    pub bollinger_period: usize,
+    /// Standard deviations between the Bollinger middle line and each band
+    pub bollinger_width: f64,
+    pub rsi_period: usize,
+    pub atr_period: usize,
+    /// MACD fast, slow and signal periods
+    pub macd: (usize, usize, usize),
+    /// Stochastic lookback, %K smoothing and %D periods
+    pub stochastic: (usize, usize, usize),
+    /// Fast and slow SMA periods whose crossings are golden and death
+    /// crosses
+    pub cross: (usize, usize),
+    /// Local time each trading session starts, where VWAP resets
+    pub session_start: NaiveTime,
+    /// How the candlestick chart draws candles
+    pub style: CandleStyle,
+    /// Labels shown as colored chips next to the market
+    pub tags: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            sma_period: 20,
+            ema_periods: vec![9, 21, 50],
+            bollinger_period: 20,
+            bollinger_width: 2.0,
+            rsi_period: 14,
+            atr_period: 14,
+            macd: (12, 26, 9),
+            stochastic: (14, 3, 3),
+            cross: (50, 200),
+            session_start: NaiveTime::MIN,
+            style: CandleStyle::default(),
+            tags: Vec::new(),
+        }
+    }
+}
+
+/// The settings file used without `--settings`; `None` without a home
+/// directory
+pub fn settings_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(base.join("crypto_tracking").join("settings.conf"))
+}
+
+impl Settings {
+    /// Sets `key` from `value` as written in the file
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "sma" => {
+                self.sma_period = value
+                    .parse()
+                    .ok()
+                    .filter(|&p| p > 0)
+                    .ok_or("sma expects a period like 20")?;
+            }
+            "ema" => {
+                self.ema_periods = periods(value).ok_or("ema expects periods like 9, 21, 50")?;
+            }
+            "bollinger" => {
+                let (period, width) = value
+                    .split_once(',')
+                    .and_then(|(period, width)| {
+                        Some((
+                            period.trim().parse().ok().filter(|&p| p > 1)?,
+                            width
+                                .trim()
+                                .parse()
+                                .ok()
+                                .filter(|w: &f64| w.is_finite() && *w > 0.0)?,
+                        ))
+                    })
+                    .ok_or("bollinger expects a period and width like 20, 2")?;
+                self.bollinger_period = period;
+                self.bollinger_width = width;
+            }
+            "rsi" => {
+                self.rsi_period = value
+                    .parse()
+                    .ok()
+                    .filter(|&p| p > 0)
+                    .ok_or("rsi expects a period like 14")?;
+            }
+            "atr" => {
+                self.atr_period = value
+                    .parse()
+                    .ok()
+                    .filter(|&p| p > 0)
+                    .ok_or("atr expects a period like 14")?;
+            }
+            "macd" => {
+                let Some(&[fast, slow, signal]) = periods(value).as_deref() else {
+                    return Err("macd expects fast, slow and signal periods like 12, 26, 9".into());
+                };
+                self.macd = (fast, slow, signal);
+            }
+            "stochastic" => {
+                let Some(&[lookback, smoothing, d]) = periods(value).as_deref() else {
+                    return Err(
+                        "stochastic expects lookback, smoothing and %D periods like 14, 3, 3"
+                            .into(),
+                    );
+                };
+                self.stochastic = (lookback, smoothing, d);
+            }
+            "cross" => {
+                let Some(&[fast, slow]) = periods(value).as_deref() else {
+                    return Err("cross expects fast and slow periods like 50, 200".into());
+                };
+                self.cross = (fast, slow);
+            }
+            "session" => {
+                self.session_start = NaiveTime::parse_from_str(value, "%H:%M")
+                    .map_err(|_| "session expects a time like 09:30")?;
+            }
+            "style" => {
+                self.style =
+                    CandleStyle::from_name(value).ok_or("style expects candles or bars")?;
+            }
+            "tags" => {
+                self.tags = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_string)
+                    .collect();
+            }
+            _ => return Err(format!("unknown setting: {}", key)),
+        }
+
+        Ok(())
+    }
+
+    /// `key`'s value as written in the file; `None` for an unknown key
+    pub fn get(&self, key: &str) -> Option<String> {
+        let list = |values: &[usize]| {
+            values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        Some(match key {
+            "sma" => self.sma_period.to_string(),
+            "ema" => list(&self.ema_periods),
+            "bollinger" => format!("{}, {}", self.bollinger_period, self.bollinger_width),
+            "rsi" => self.rsi_period.to_string(),
+            "atr" => self.atr_period.to_string(),
+            "macd" => list(&[self.macd.0, self.macd.1, self.macd.2]),
+            "stochastic" => list(&[self.stochastic.0, self.stochastic.1, self.stochastic.2]),
+            "cross" => list(&[self.cross.0, self.cross.1]),
+            "session" => self.session_start.format("%H:%M").to_string(),
+            "style" => self.style.name().to_string(),
+            "tags" => self.tags.join(", "),
+            _ => return None,
+        })
+    }
+}
+
+/// Every setting's key, in the order the file documents them
+pub const KEYS: [&str; 11] = [
+    "sma",
+    "ema",
+    "bollinger",
+    "rsi",
+    "atr",
+    "macd",
+    "stochastic",
+    "cross",
+    "session",
+    "style",
+    "tags",
+];
+
+/// The settings file's contents: settings for every market, plus the
+/// overrides in each market's section
+#[derive(Debug, Clone, Default)]
+pub struct SettingsFile {
+    /// Where changes are saved; `None` without a home directory
+    pub path: Option<PathBuf>,
+    pub defaults: Settings,
+    /// Full settings of each market with a section
+    pub markets: BTreeMap<String, Settings>,
+}
+
+impl SettingsFile {
+    /// Settings from `path`, or from [`settings_path`] when that's `None`.
+    /// Only an explicitly given file has to exist.
+    pub fn load(path: Option<&str>) -> Result<Self, String> {
+        let (path, required) = match path {
+            Some(path) => (PathBuf::from(path), true),
+            None => match settings_path() {
+                Some(path) => (path, false),
+                None => return Ok(SettingsFile::default()),
+            },
+        };
+
+        let mut file = match fs::read_to_string(&path) {
+            Ok(source) => {
+                SettingsFile::parse(&source).map_err(|e| format!("{}: {}", path.display(), e))?
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !required => SettingsFile::default(),
+            Err(e) => return Err(format!("reading {}: {}", path.display(), e)),
+        };
+        file.path = Some(path);
+        Ok(file)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut file = SettingsFile::default();
+        // The section the lines are in, if any
+        let mut market: Option<String> = None;
+
+        for (number, line) in source.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let error = |message: &str| format!("line {}: {}", number + 1, message);
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let name = name.trim().to_string();
+                file.markets
+                    .entry(name.clone())
+                    .or_insert_with(|| file.defaults.clone());
+                market = Some(name);
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| error("expected key = value"))?;
+            let settings = match &market {
+                Some(market) => file.markets.get_mut(market).unwrap(),
+                None => &mut file.defaults,
+            };
+            settings.set(key, value).map_err(|e| error(&e))?;
+        }
+
+        Ok(file)
+    }
+
+    /// `market`'s settings: its section's, or else the defaults
+    pub fn for_market(&self, market: &str) -> &Settings {
+        self.markets.get(market).unwrap_or(&self.defaults)
+    }
+
+    /// `market`'s settings to change, starting it a section from the
+    /// defaults if it has none
+    pub fn for_market_mut(&mut self, market: &str) -> &mut Settings {
+        let defaults = &self.defaults;
+        self.markets
+            .entry(market.to_string())
+            .or_insert_with(|| defaults.clone())
+    }
+
+    /// Writes the market sections back to the file, each with only the
+    /// settings that differ from the defaults. Everything before the first
+    /// section is kept as it was, comments included; the sections are
+    /// rewritten.
+    pub fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Err("no settings file to save to".to_string());
+        };
+        let existing = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("reading {}: {}", path.display(), e)),
+        };
+
+        let mut text: String = existing
+            .lines()
+            .take_while(|line| !line.trim_start().starts_with('['))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        text.truncate(text.trim_end().len());
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        for (market, settings) in &self.markets {
+            let changed: Vec<String> = KEYS
+                .iter()
+                .filter_map(|&key| {
+                    let value = settings.get(key)?;
+                    (Some(&value) != self.defaults.get(key).as_ref())
+                        .then(|| format!("{} = {}\n", key, value))
+                })
+                .collect();
+            if changed.is_empty() {
+                continue;
+            }
+            // A blank line between sections
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            text.push_str(&format!("[{}]\n", market));
+            text.extend(changed);
+        }
+
+        path.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(path, text))
+            .map_err(|e| format!("saving {}: {}", path.display(), e))
+    }
+}
+
+/// Whether `tag` can be written to the file and read back as itself
+pub fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty() && !tag.contains([',', '#']) && tag.trim() == tag
+}
+
+/// Comma-separated positive periods, e.g. `9, 21, 50`
+fn periods(value: &str) -> Option<Vec<usize>> {
+    value
+        .split(',')
+        .map(|period| period.trim().parse().ok().filter(|&p| p > 0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_are_saved_in_the_market_section_and_read_back() {
+        let path = std::env::temp_dir().join(format!("tags-{}.conf", std::process::id()));
+        let mut file = SettingsFile {
+            path: Some(path.clone()),
+            ..SettingsFile::default()
+        };
+        file.for_market_mut("USD/BTC").tags = vec!["hodl".to_string(), "watch".to_string()];
+        file.save().unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(text, "[USD/BTC]\ntags = hodl, watch\n");
+
+        let read = SettingsFile::parse(&text).unwrap();
+        assert_eq!(read.for_market("USD/BTC").tags, ["hodl", "watch"]);
+        assert!(read.for_market("USD/ETH").tags.is_empty());
+    }
+}
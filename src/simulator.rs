@@ -0,0 +1,434 @@
+//! The offline simulated feed the app runs on by default.
+//!
+//! Prices follow one of two models: the original random walk, whose moves
+//! are uniform in a fixed price band per market, or geometric Brownian motion
+//! (GBM), whose moves are normally distributed returns. Both honour the
+//! per-market volatility, drift, mean reversion and reset band settings.
+//!
+//! Each tick also sends a few trades inside the new candle and a synthetic
+//! order book around its close.
+
+use crate::source::{MarketDataSource, Message};
+use chrono::Local;
+use crypto_tracking::{Candle, OrderBook, Side, Trade};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::{collections::HashMap, sync::mpsc, thread, time::Duration};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PriceModel {
+    #[default]
+    RandomWalk,
+    Gbm,
+}
+
+/// Where seeded runs start their candle times: 2024-01-01 00:00 UTC
+const SEEDED_EPOCH: i64 = 1_704_067_200;
+
+#[derive(Debug, Clone)]
+pub struct SimulatorConfig {
+    /// Overrides for the built-in starting prices, keyed by symbol
+    pub start_prices: HashMap<String, f64>,
+    /// Fraction of the distance back to the starting price pulled in on each
+    /// tick; 0 keeps the pure random walk
+    pub mean_reversion: f64,
+    /// Snap a market back to its starting price once it drifts more than
+    /// this many percent away from it
+    pub reset_band: Option<f64>,
+    pub model: PriceModel,
+    /// Standard deviation of one tick's return in percent, keyed by symbol;
+    /// other markets keep the spread of the built-in random walk
+    pub volatility: HashMap<String, f64>,
+    /// Expected return per tick in percent
+    pub drift: f64,
+    /// How often a candle is emitted for every market
+    pub tick_interval: Duration,
+    /// Makes every run with the same seed produce the same candles, times
+    /// included
+    pub seed: Option<u64>,
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        SimulatorConfig {
+            start_prices: HashMap::new(),
+            mean_reversion: 0.0,
+            reset_band: None,
+            model: PriceModel::default(),
+            volatility: HashMap::new(),
+            drift: 0.0,
+            tick_interval: Duration::from_secs(1),
+            seed: None,
+        }
+    }
+}
+
+impl SimulatorConfig {
+    /// Standard deviation of one tick's return in percent. Without an
+    /// override this matches the built-in walk at the starting price: its
+    /// moves are uniform in ±factor, whose standard deviation is factor / √3.
+    pub fn volatility_pct(&self, market: &str) -> f64 {
+        match self.volatility.get(market) {
+            Some(&pct) => pct,
+            None => {
+                let start = start_prices(self).get(market).copied().unwrap_or(1.0);
+                volatility_factor(market) / 3f64.sqrt() / start * 100.0
+            }
+        }
+    }
+
+    /// Every market with a starting price: the built-in ones first, then any
+    /// only given by override, by symbol
+    pub fn markets(&self) -> Vec<String> {
+        let mut markets: Vec<String> = ["USD/BTC", "USD/ETH", "IDR/BTC", "IDR/ETH"]
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+        let mut extra: Vec<String> = self
+            .start_prices
+            .keys()
+            .filter(|m| !markets.contains(m))
+            .cloned()
+            .collect();
+        extra.sort();
+        markets.extend(extra);
+        markets
+    }
+
+    /// A generator for one stream of prices. With a seed, each stream gets
+    /// its own fixed sequence so history and live candles don't depend on
+    /// the order they're asked for.
+    fn rng(&self, stream: &str) -> StdRng {
+        match self.seed {
+            Some(seed) => {
+                // FNV-1a, so the mixing stays the same across Rust releases
+                let stream_hash = stream.bytes().fold(0xcbf29ce484222325u64, |hash, b| {
+                    (hash ^ u64::from(b)).wrapping_mul(0x100000001b3)
+                });
+                StdRng::seed_from_u64(seed ^ stream_hash)
+            }
+            None => StdRng::from_os_rng(),
+        }
+    }
+
+    /// Time of the first live candle, with history ending just before it.
+    /// A seeded run starts at [`SEEDED_EPOCH`] so its candles don't depend
+    /// on when it's run; otherwise it starts now.
+    fn start_time(&self) -> i64 {
+        match self.seed {
+            Some(_) => SEEDED_EPOCH,
+            None => Local::now().timestamp(),
+        }
+    }
+
+    /// How far the model moves `price` in one tick, before mean reversion
+    fn movement(&self, rng: &mut impl Rng, market: &str, price: f64) -> f64 {
+        let drift = self.drift / 100.0;
+
+        match self.model {
+            PriceModel::RandomWalk => {
+                let amplitude = match self.volatility.get(market) {
+                    Some(pct) => pct / 100.0 * price * 3f64.sqrt(),
+                    None => volatility_factor(market),
+                };
+                rng.random_range(-1.0..1.0) * amplitude + price * drift
+            }
+            PriceModel::Gbm => {
+                let sigma = self.volatility_pct(market) / 100.0;
+                let log_return = drift - sigma * sigma / 2.0 + sigma * standard_normal(rng);
+                price * log_return.exp_m1()
+            }
+        }
+    }
+}
+
+/// Simulated candles for every market, one per market each tick, starting
+/// from realistic prices
+#[derive(Debug)]
+pub struct Simulator {
+    config: SimulatorConfig,
+}
+
+impl Simulator {
+    pub fn new(config: SimulatorConfig) -> Self {
+        Simulator { config }
+    }
+}
+
+impl MarketDataSource for Simulator {
+    fn subscribe(&self, markets: &[String]) -> mpsc::Receiver<Message> {
+        let (tx, rx) = mpsc::channel();
+        spawn_simulator(markets.to_vec(), tx, self.config.clone());
+        rx
+    }
+
+    /// A simulated run that ends at the market's starting price, so the live
+    /// candles carry on from the last historical one
+    fn fetch_history(&self, market: &str, limit: usize) -> Result<Vec<Candle>, String> {
+        let Some(&start) = start_prices(&self.config).get(market) else {
+            return Ok(Vec::new());
+        };
+
+        let mut rng = self.config.rng(&format!("history {}", market));
+        let mut close = start;
+        let mut time = self.config.start_time();
+        let mut candles = Vec::with_capacity(limit);
+
+        for _ in 0..limit {
+            time -= 60;
+            let movement = self.config.movement(&mut rng, market, close);
+            let open = round_to_tick(close - movement, tick_size(market));
+            candles.push(candle_between(&mut rng, market, time, open, close));
+            close = open;
+        }
+
+        candles.reverse();
+        Ok(candles)
+    }
+}
+
+/// Largest per-tick price move the simulator makes for a market, scaled to
+/// its price magnitude
+fn volatility_factor(market: &str) -> f64 {
+    match market {
+        "USD/BTC" => 100.0,
+        "USD/ETH" => 10.0,
+        "IDR/BTC" => 1000000.0,
+        "IDR/ETH" => 100000.0,
+        _ => 1.0,
+    }
+}
+
+fn spawn_simulator(markets: Vec<String>, tx: mpsc::Sender<Message>, config: SimulatorConfig) {
+    thread::spawn(move || {
+        let mut rng = config.rng("live");
+        // Their own streams, so trades and books don't change a seeded run's
+        // prices
+        let mut trade_rng = config.rng("trades");
+        let mut book_rng = config.rng("book");
+        
+        let mut prices = start_prices(&config);
+
+        // Where each market started; mean reversion and resets pull toward it
+        let anchors = prices.clone();
+        
+        let mut time = config.start_time();
+
+        loop {
+            for market in &markets {
+                let price = prices.get_mut(market).unwrap();
+                let open = *price;
+                
+                let anchor = anchors[market];
+                let reversion = (anchor - *price) * config.mean_reversion;
+                let movement = config.movement(&mut rng, market, *price) + reversion;
+
+                let mut next = *price + movement;
+                if let Some(band) = config.reset_band
+                    && ((next - anchor) / anchor).abs() * 100.0 > band
+                {
+                    next = anchor;
+                }
+                *price = round_to_tick(next, tick_size(market));
+
+                let candle = candle_between(&mut rng, market, time, open, *price);
+
+                let trades = trades_within(&mut trade_rng, market, &candle);
+                let book = synthetic_book(&mut book_rng, market, *price);
+                if tx.send(Message::NewCandle(market.clone(), candle)).is_err()
+                    || trades
+                        .into_iter()
+                        .any(|trade| tx.send(Message::Trade(market.clone(), trade)).is_err())
+                    || tx.send(Message::Depth(market.clone(), book)).is_err()
+                {
+                    return;
+                }
+            }
+
+            thread::sleep(config.tick_interval);
+            time += 60;
+        }
+    });
+}
+
+/// Realistic starting prices, with any configured overrides applied
+fn start_prices(config: &SimulatorConfig) -> HashMap<String, f64> {
+    let mut prices: HashMap<String, f64> = HashMap::new();
+    prices.insert("USD/BTC".to_string(), 103879.0);
+    prices.insert("USD/ETH".to_string(), 2548.64);
+    prices.insert("IDR/BTC".to_string(), 1729998000.0);
+    prices.insert("IDR/ETH".to_string(), 42679530.0);
+    prices.extend(config.start_prices.clone());
+    prices
+}
+
+/// Minimum price increment per market; a tick of 0 disables rounding
+fn tick_size(market: &str) -> f64 {
+    match market {
+        "USD/BTC" => 0.5,
+        "USD/ETH" => 0.01,
+        "IDR/BTC" | "IDR/ETH" => 1.0,
+        _ => 0.0,
+    }
+}
+
+/// A candle moving from `open` to `close`, with wicks reaching a little past
+/// both and a volume scaled to the market
+fn candle_between(rng: &mut impl Rng, market: &str, time: i64, open: f64, close: f64) -> Candle {
+    let volatility_factor = volatility_factor(market);
+    let tick = tick_size(market);
+
+    let high = round_to_tick(
+        open.max(close) + rng.random_range(0.0..volatility_factor * 0.2),
+        tick,
+    );
+    let low = round_to_tick(
+        open.min(close) - rng.random_range(0.0..volatility_factor * 0.2),
+        tick,
+    );
+    
+    // Scale volume based on the market
+    let volume_factor = match market {
+        "USD/BTC" | "IDR/BTC" => 5.0,
+        "USD/ETH" | "IDR/ETH" => 20.0,
+        _ => 1.0,
+    };
+    let volume = rng.random_range(100.0..1000.0) * volume_factor;
+
+    Candle {
+        time,
+        open,
+        high,
+        low,
+        close,
+        volume,
+    }
+}
+
+/// One to three trades during `candle`'s minute, moving from its open toward
+/// its close and staying inside its range. A trade above the one before it
+/// is a buy, below it a sell.
+fn trades_within(rng: &mut impl Rng, market: &str, candle: &Candle) -> Vec<Trade> {
+    let count = rng.random_range(1..=3);
+    let mut offsets: Vec<i64> = (0..count).map(|_| rng.random_range(0..60)).collect();
+    offsets.sort_unstable();
+
+    let tick = tick_size(market);
+    let mut previous = candle.open;
+    offsets
+        .into_iter()
+        .enumerate()
+        .map(|(i, offset)| {
+            let along = (i + 1) as f64 / count as f64;
+            let target = candle.open + (candle.close - candle.open) * along;
+            let jitter = (candle.high - candle.low) * rng.random_range(-0.25..0.25);
+            let price = round_to_tick((target + jitter).clamp(candle.low, candle.high), tick);
+            let side = if price >= previous {
+                Side::Buy
+            } else {
+                Side::Sell
+            };
+            previous = price;
+
+            Trade {
+                time: candle.time + offset,
+                price,
+                size: candle.volume / 1000.0 * rng.random_range(0.1..1.0),
+                side,
+            }
+        })
+        .collect()
+}
+
+/// A plausible book around `mid`: evenly spaced levels on each side, with
+/// sizes growing away from the spread
+fn synthetic_book(rng: &mut impl Rng, market: &str, mid: f64) -> OrderBook {
+    const LEVELS: usize = 20;
+
+    let tick = tick_size(market);
+    let step = round_to_tick(volatility_factor(market) * 0.1, tick).max(tick);
+    let size_unit = match market {
+        "USD/BTC" | "IDR/BTC" => 0.5,
+        "USD/ETH" | "IDR/ETH" => 5.0,
+        _ => 1.0,
+    };
+    let mut side = |direction: f64| -> Vec<(f64, f64)> {
+        (1..=LEVELS)
+            .map(|level| {
+                let price = round_to_tick(mid + direction * step * level as f64, tick);
+                let size = size_unit * (1.0 + level as f64 * 0.15) * rng.random_range(0.3..1.7);
+                (price, size)
+            })
+            .collect()
+    };
+
+    OrderBook {
+        bids: side(-1.0),
+        asks: side(1.0),
+    }
+}
+
+/// A standard normal sample, by the Box-Muller transform
+fn standard_normal(rng: &mut impl Rng) -> f64 {
+    let u1: f64 = rng.random_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.random();
+    (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+}
+
+fn round_to_tick(price: f64, tick: f64) -> f64 {
+    if tick <= 0.0 || !tick.is_finite() {
+        return price;
+    }
+
+    (price / tick).round() * tick
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_runs_produce_the_same_candles() {
+        let config = SimulatorConfig {
+            seed: Some(7),
+            tick_interval: Duration::from_millis(1),
+            ..SimulatorConfig::default()
+        };
+        let markets = config.markets();
+        let run = || {
+            let simulator = Simulator::new(config.clone());
+            let history = simulator.fetch_history("USD/BTC", 20).unwrap();
+            let live: Vec<(String, Candle)> = simulator
+                .subscribe(&markets)
+                .iter()
+                .filter_map(|message| match message {
+                    Message::NewCandle(market, candle) => Some((market, candle)),
+                    _ => None,
+                })
+                .take(12)
+                .collect();
+            (history, live)
+        };
+
+        let (first, second) = (run(), run());
+        assert_eq!(first.0.last().map(|c| c.time), Some(SEEDED_EPOCH - 60));
+        assert_eq!(first.1[0].1.time, SEEDED_EPOCH);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn prices_round_to_sub_dollar_ticks() {
+        assert!((round_to_tick(2548.6449, 0.01) - 2548.64).abs() < 1e-9);
+        assert!((round_to_tick(2548.6451, 0.01) - 2548.65).abs() < 1e-9);
+        assert_eq!(round_to_tick(103879.3, 0.5), 103879.5);
+        assert_eq!(round_to_tick(103879.2, 0.5), 103879.0);
+    }
+
+    #[test]
+    fn prices_round_to_whole_number_ticks() {
+        assert_eq!(round_to_tick(1729998000.4, 1.0), 1729998000.0);
+        assert_eq!(round_to_tick(1729998000.6, 1.0), 1729998001.0);
+        // Without a usable tick the price is left alone
+        assert_eq!(round_to_tick(12.345, 0.0), 12.345);
+        assert_eq!(round_to_tick(12.345, f64::NAN), 12.345);
+    }
+}
@@ -0,0 +1,191 @@
+//! Where candles come from. The UI only reads [`Message`]s from a channel, so
+//! a new backend plugs in by implementing [`MarketDataSource`] without
+//! touching the render loop.
+
+use crate::derivatives::PerpStats;
+use crypto_tracking::{Candle, OrderBook, Trade};
+use std::{
+    collections::VecDeque,
+    sync::mpsc::{self, RecvTimeoutError, TrySendError},
+    thread,
+    time::Duration,
+};
+
+/// How often a full channel is tried again while messages wait
+const RETRY_INTERVAL: Duration = Duration::from_millis(20);
+
+pub enum Message {
+    NewCandle(String, Candle),
+    /// The connection behind these markets changed state
+    FeedStatus(Vec<String>, FeedStatus),
+    /// New funding and open interest for the market's perpetual
+    Derivatives(String, PerpStats),
+    /// A trade executed on the market
+    Trade(String, Trade),
+    /// The market's order book changed
+    Depth(String, OrderBook),
+    /// Latest close per named source behind a composite market
+    Quotes(String, Vec<(String, f64)>),
+    /// The producer is still running, even with nothing new to send
+    Heartbeat,
+    /// The producer behind these markets stopped and was started again
+    Restarted(Vec<String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedStatus {
+    Connected,
+    /// The connection dropped or couldn't be made; `attempt` counts the
+    /// retries since the last successful connection
+    Reconnecting {
+        attempt: u32,
+        retry_in: Duration,
+    },
+}
+
+pub trait MarketDataSource: Send + Sync {
+    /// Starts streaming candles for `markets` in the background. The
+    /// receiver disconnects once the source stops for good.
+    fn subscribe(&self, markets: &[String]) -> mpsc::Receiver<Message>;
+
+    /// Up to `limit` of the most recent candles for `market`, oldest first,
+    /// so the chart starts with a full window
+    fn fetch_history(&self, market: &str, limit: usize) -> Result<Vec<Candle>, String>;
+}
+
+/// A market filter and the source serving the markets it accepts
+type Route = (fn(&str) -> bool, Box<dyn MarketDataSource>);
+
+/// Several sources behind one: each market is served by the first route whose
+/// filter accepts it, and every route's candles arrive on the same channel
+#[derive(Default)]
+pub struct Routed {
+    routes: Vec<Route>,
+}
+
+impl Routed {
+    pub fn route(mut self, accepts: fn(&str) -> bool, source: Box<dyn MarketDataSource>) -> Self {
+        self.routes.push((accepts, source));
+        self
+    }
+
+    /// Index of the route serving `market`
+    fn route_of(&self, market: &str) -> Option<usize> {
+        self.routes.iter().position(|(accepts, _)| accepts(market))
+    }
+}
+
+impl MarketDataSource for Routed {
+    fn subscribe(&self, markets: &[String]) -> mpsc::Receiver<Message> {
+        let (tx, rx) = mpsc::channel();
+
+        for (i, (_, source)) in self.routes.iter().enumerate() {
+            let routed: Vec<String> = markets
+                .iter()
+                .filter(|m| self.route_of(m) == Some(i))
+                .cloned()
+                .collect();
+            if routed.is_empty() {
+                continue;
+            }
+
+            let route_rx = source.subscribe(&routed);
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for message in route_rx {
+                    if tx.send(message).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+
+        rx
+    }
+
+    fn fetch_history(&self, market: &str, limit: usize) -> Result<Vec<Candle>, String> {
+        match self.route_of(market) {
+            Some(i) => self.routes[i].1.fetch_history(market, limit),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// `source` behind a channel that holds at most `capacity` messages, so a
+/// feed that outruns the UI can't pile up memory. Messages that don't fit
+/// wait in a backlog where a newer candle for the same minute, or a newer
+/// book, quote set or derivatives update for the same market, replaces the
+/// one waiting. Past `capacity` waiting messages the oldest trade is
+/// dropped, or the oldest message when there are no trades.
+pub fn bounded(source: mpsc::Receiver<Message>, capacity: usize) -> mpsc::Receiver<Message> {
+    let (tx, rx) = mpsc::sync_channel(capacity);
+
+    thread::spawn(move || {
+        let mut backlog = VecDeque::new();
+        loop {
+            while let Some(message) = backlog.pop_front() {
+                match tx.try_send(message) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(message)) => {
+                        backlog.push_front(message);
+                        break;
+                    }
+                    Err(TrySendError::Disconnected(_)) => return,
+                }
+            }
+
+            let next = if backlog.is_empty() {
+                source.recv().map_err(|_| RecvTimeoutError::Disconnected)
+            } else {
+                source.recv_timeout(RETRY_INTERVAL)
+            };
+            match next {
+                Ok(message) => merge(&mut backlog, message, capacity),
+                Err(RecvTimeoutError::Timeout) => {}
+                // The source is done; what's waiting still gets delivered
+                Err(RecvTimeoutError::Disconnected) => {
+                    for message in backlog {
+                        if tx.send(message).is_err() {
+                            return;
+                        }
+                    }
+                    return;
+                }
+            }
+        }
+    });
+
+    rx
+}
+
+/// Queues `message` behind the ones waiting in `backlog`
+fn merge(backlog: &mut VecDeque<Message>, message: Message, capacity: usize) {
+    match backlog
+        .iter()
+        .position(|waiting| supersedes(&message, waiting))
+    {
+        Some(i) => backlog[i] = message,
+        None => backlog.push_back(message),
+    }
+
+    if backlog.len() > capacity {
+        let oldest = backlog
+            .iter()
+            .position(|m| matches!(m, Message::Trade(..)))
+            .unwrap_or(0);
+        backlog.remove(oldest);
+    }
+}
+
+/// Whether `newer` leaves nothing in `older` worth showing
+fn supersedes(newer: &Message, older: &Message) -> bool {
+    match (newer, older) {
+        (Message::NewCandle(market, candle), Message::NewCandle(other, earlier)) => {
+            market == other && candle.time == earlier.time
+        }
+        (Message::Depth(market, _), Message::Depth(other, _))
+        | (Message::Quotes(market, _), Message::Quotes(other, _))
+        | (Message::Derivatives(market, _), Message::Derivatives(other, _)) => market == other,
+        _ => false,
+    }
+}
@@ -0,0 +1,82 @@
+//! Restarts a source whose producer thread died or hung, instead of leaving
+//! its markets frozen on stale prices.
+//!
+//! A producer that panics drops its end of the channel, which disconnects
+//! the receiver; one that hangs goes quiet. Every message counts as a sign
+//! of life, and feeds that can go a while without news send
+//! [`Message::Heartbeat`]s in between. Either way the source is subscribed
+//! again and a [`Message::Restarted`] tells the UI.
+
+use crate::source::{MarketDataSource, Message};
+use crypto_tracking::Candle;
+use std::{
+    sync::{Arc, mpsc},
+    thread,
+    time::Duration,
+};
+
+/// Pause before a restart, so a producer that dies straight away doesn't
+/// spin
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// A source restarted when it's silent for longer than `timeout`
+pub struct Supervised {
+    source: Arc<dyn MarketDataSource>,
+    timeout: Duration,
+}
+
+impl Supervised {
+    pub fn new(source: Box<dyn MarketDataSource>, timeout: Duration) -> Self {
+        Supervised {
+            source: Arc::from(source),
+            timeout,
+        }
+    }
+}
+
+impl MarketDataSource for Supervised {
+    fn subscribe(&self, markets: &[String]) -> mpsc::Receiver<Message> {
+        let (tx, rx) = mpsc::channel();
+        let source = Arc::clone(&self.source);
+        let markets = markets.to_vec();
+        let timeout = self.timeout;
+
+        thread::spawn(move || {
+            let mut source_rx = source.subscribe(&markets);
+            // A source whose first subscription ends before sending anything
+            // has nothing for these markets, so it's left stopped. Once it has
+            // sent something or been restarted, ending is a crash like any
+            // other.
+            let mut may_stop = true;
+
+            loop {
+                match source_rx.recv_timeout(timeout) {
+                    Ok(Message::Heartbeat) => may_stop = false,
+                    Ok(message) => {
+                        may_stop = false;
+                        if tx.send(message).is_err() {
+                            return;
+                        }
+                    }
+                    Err(mpsc::RecvTimeoutError::Disconnected) if may_stop => return,
+                    // A hung producer finds its receiver gone on its next
+                    // send and stops on its own
+                    Err(_) => {
+                        if tx.send(Message::Restarted(markets.clone())).is_err() {
+                            return;
+                        }
+                        thread::sleep(RESTART_DELAY);
+                        source_rx = source.subscribe(&markets);
+                        may_stop = false;
+                    }
+                }
+            }
+        });
+
+        rx
+    }
+
+    fn fetch_history(&self, market: &str, limit: usize) -> Result<Vec<Candle>, String> {
+        self.source.fetch_history(market, limit)
+    }
+}
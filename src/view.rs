@@ -0,0 +1,256 @@
+//! Which of a market's candles the charts show: how far they're zoomed in,
+//! how far back they're panned and where the crosshair sits.
+//!
+//! `+`/`-` (or the mouse wheel) zoom, Left/Right (or `h`/`l`) pan back and
+//! forward, and End (or `f`) follows new candles again. `X` puts a crosshair
+//! on the newest candle shown; while it's up Left/Right move it instead,
+//! panning along to keep it in view, and Esc (or `X` again) takes it away.
+
+use crossterm::event::{KeyCode, MouseEventKind};
+use crypto_tracking::{Candle, chart::Marker};
+
+/// Fewest candles zooming in leaves on the charts
+const MIN_ZOOM: usize = 10;
+
+/// Candles the charts show to begin with, zoomed in on the newest of what's
+/// retained
+const DEFAULT_ZOOM: usize = 30;
+
+/// The zoom after zooming in or out once from `zoom` with `stored` candles,
+/// by half as many candles again each step. `None` shows every candle,
+/// which is where zooming out stops.
+fn zoomed(zoom: Option<usize>, stored: usize, zoom_in: bool) -> Option<usize> {
+    let shown = zoom.unwrap_or(stored).min(stored);
+    let shown = if zoom_in {
+        (shown * 2 / 3).max(MIN_ZOOM)
+    } else {
+        shown * 3 / 2 + 1
+    };
+    (shown < stored).then_some(shown)
+}
+
+/// Furthest the charts can pan back from the newest of `stored` candles at
+/// `zoom`
+fn max_pan(zoom: Option<usize>, stored: usize) -> usize {
+    stored - zoom.unwrap_or(stored).min(stored)
+}
+
+/// Zoom, pan and crosshair of the charts, kept from frame to frame. Positions
+/// count back from the newest candle, so they stay put as candles arrive
+/// unless [`ChartView::candle_arrived`] moves them along.
+#[derive(Debug, Clone, Copy)]
+pub struct ChartView {
+    /// How many of the newest candles the charts show; `None` shows them all
+    pub zoom: Option<usize>,
+    /// How many candles back from the newest the charts are panned; at 0
+    /// they follow new candles
+    pub pan: usize,
+    /// While set, the candle the crosshair is on, as candles back from the
+    /// newest
+    pub crosshair: Option<usize>,
+}
+
+impl Default for ChartView {
+    fn default() -> Self {
+        ChartView {
+            zoom: Some(DEFAULT_ZOOM),
+            pan: 0,
+            crosshair: None,
+        }
+    }
+}
+
+impl ChartView {
+    /// The part of `candle_count` candles shown
+    pub fn view(&self, candle_count: usize) -> View {
+        View::new(candle_count, self.zoom, self.pan)
+    }
+
+    /// How many of `stored` candles are shown
+    pub fn shown(&self, stored: usize) -> usize {
+        self.zoom.unwrap_or(stored).min(stored)
+    }
+
+    /// A candle newer than the selected market's newest arrived. A panned
+    /// chart, and its crosshair, stay on the candles they show.
+    pub fn candle_arrived(&mut self) {
+        if self.pan > 0 {
+            self.pan += 1;
+            if let Some(cursor) = self.crosshair.as_mut() {
+                *cursor += 1;
+            }
+        }
+    }
+
+    /// Zooms like `+` and `-` on a wheel scroll over the charts
+    pub fn handle_mouse(&mut self, kind: MouseEventKind, stored: usize) {
+        match kind {
+            MouseEventKind::ScrollUp => self.zoom_by(stored, true),
+            MouseEventKind::ScrollDown => self.zoom_by(stored, false),
+            _ => {}
+        }
+    }
+
+    /// Handles a zoom or pan key, or `X`, on a chart of `stored` candles;
+    /// `false` for any other key
+    pub fn handle_key(&mut self, code: KeyCode, stored: usize) -> bool {
+        match code {
+            KeyCode::Char('+') => self.zoom_by(stored, true),
+            KeyCode::Char('-') => self.zoom_by(stored, false),
+            // Pans back through the stored candles, or forward until the
+            // chart follows new ones again
+            KeyCode::Left | KeyCode::Char('h') => {
+                self.pan = (self.pan + 1).min(max_pan(self.zoom, stored));
+            }
+            KeyCode::Right | KeyCode::Char('l') => self.pan = self.pan.saturating_sub(1),
+            KeyCode::End | KeyCode::Char('f') => self.pan = 0,
+            // Puts a crosshair on the newest candle shown
+            KeyCode::Char('X') => self.crosshair = Some(self.pan),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Moves the crosshair, or takes it away, panning along to keep it in
+    /// view. Only for while the crosshair is up.
+    pub fn handle_crosshair_key(&mut self, code: KeyCode, stored: usize) {
+        let Some(cursor) = self.crosshair.as_mut() else {
+            return;
+        };
+        match code {
+            KeyCode::Left | KeyCode::Char('h') => {
+                *cursor = (*cursor + 1).min(stored.saturating_sub(1));
+            }
+            KeyCode::Right | KeyCode::Char('l') => *cursor = cursor.saturating_sub(1),
+            KeyCode::Esc | KeyCode::Char('X') => self.crosshair = None,
+            _ => {}
+        }
+
+        if let Some(cursor) = self.crosshair {
+            let shown = self.shown(stored);
+            if cursor < self.pan {
+                self.pan = cursor;
+            } else if cursor >= self.pan + shown {
+                self.pan = cursor + 1 - shown;
+            }
+        }
+    }
+
+    fn zoom_by(&mut self, stored: usize, zoom_in: bool) {
+        self.zoom = zoomed(self.zoom, stored, zoom_in);
+        self.pan = self.pan.min(max_pan(self.zoom, stored));
+    }
+}
+
+/// The part of a market's candles the charts are zoomed and panned to.
+/// Indicator points and markers are laid out over every candle, so they're
+/// shifted to line up with the candles shown.
+#[derive(Debug, Clone, Copy)]
+pub struct View {
+    /// Position of the first candle shown
+    pub start: usize,
+    /// Position just past the last candle shown
+    pub end: usize,
+}
+
+impl View {
+    /// The view of `candle_count` candles at `zoom`, `pan` candles back
+    /// from the newest
+    fn new(candle_count: usize, zoom: Option<usize>, pan: usize) -> Self {
+        let pan = pan.min(max_pan(zoom, candle_count));
+        View {
+            start: candle_count - zoom.unwrap_or(candle_count).min(candle_count) - pan,
+            end: candle_count - pan,
+        }
+    }
+
+    pub fn candles(self, candles: &[Candle]) -> &[Candle] {
+        &candles[self.start.min(candles.len())..self.end.min(candles.len())]
+    }
+
+    pub fn points(self, points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+        let (start, end) = (self.start as f64, self.end as f64);
+        points
+            .iter()
+            .filter(|&&(x, _)| x >= start && x < end)
+            .map(|&(x, y)| (x - start, y))
+            .collect()
+    }
+
+    pub fn markers(self, markers: Vec<Marker>) -> Vec<Marker> {
+        markers
+            .into_iter()
+            .filter(|marker| marker.index < self.end)
+            .filter_map(|marker| {
+                Some(Marker {
+                    index: marker.index.checked_sub(self.start)?,
+                    ..marker
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zooming_stays_between_the_fewest_candles_and_all_of_them() {
+        let mut view = ChartView::default();
+        for _ in 0..10 {
+            view.handle_key(KeyCode::Char('+'), 100);
+        }
+        assert_eq!(view.zoom, Some(MIN_ZOOM));
+        for _ in 0..10 {
+            view.handle_key(KeyCode::Char('-'), 100);
+        }
+        assert_eq!(view.zoom, None);
+        assert_eq!(view.shown(100), 100);
+    }
+
+    #[test]
+    fn panning_stops_at_the_oldest_candle_and_zooming_out_pulls_it_back() {
+        let mut view = ChartView::default();
+        for _ in 0..100 {
+            view.handle_key(KeyCode::Left, 40);
+        }
+        assert_eq!(view.pan, 10);
+        let shown = view.view(40);
+        assert_eq!((shown.start, shown.end), (0, 30));
+
+        view.handle_key(KeyCode::Char('-'), 40);
+        assert_eq!(view.pan, 0);
+        view.handle_key(KeyCode::Char('+'), 40);
+        view.handle_key(KeyCode::Char('h'), 40);
+        view.handle_key(KeyCode::End, 40);
+        assert_eq!(view.pan, 0);
+    }
+
+    #[test]
+    fn a_panned_chart_and_its_crosshair_stay_on_their_candles() {
+        let mut view = ChartView::default();
+        view.candle_arrived();
+        assert_eq!(view.pan, 0);
+
+        view.handle_key(KeyCode::Left, 100);
+        view.handle_key(KeyCode::Char('X'), 100);
+        view.candle_arrived();
+        assert_eq!((view.pan, view.crosshair), (2, Some(2)));
+    }
+
+    #[test]
+    fn the_crosshair_pans_the_chart_to_stay_in_view() {
+        let mut view = ChartView::default();
+        view.handle_key(KeyCode::Char('X'), 100);
+        for _ in 0..DEFAULT_ZOOM {
+            view.handle_crosshair_key(KeyCode::Left, 100);
+        }
+        assert_eq!(view.crosshair, Some(DEFAULT_ZOOM));
+        assert_eq!(view.pan, 1);
+
+        view.handle_crosshair_key(KeyCode::Esc, 100);
+        assert_eq!(view.crosshair, None);
+        assert_eq!(view.pan, 1);
+    }
+}